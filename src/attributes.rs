@@ -0,0 +1,278 @@
+//! Computes the attributes of every sequence while SEQUENCE is loaded.
+//!
+//! The length, the monoisotopic and average mass and the CRC64 checksum are
+//! columns of SEQUENCE, so that the API does not compute them per request
+//! and a stored sequence can be compared with the UniProt version the sites
+//! were mapped to. They are not part of the export: `Appender` adds them to
+//! every record on its way into COPY. The CRC64 is the one UniProt lists for
+//! every sequence, e.g. `711F0E657A6661BA`. Sequences with letters other
+//! than the 20 standard amino acids are flagged in `NON_STANDARD`; their
+//! masses are NULL unless the letters are U (selenocysteine) or O
+//! (pyrrolysine).
+
+use std::io;
+use std::io::{BufRead, Read};
+
+use csv;
+use csv::Dialect;
+use schema::{Column, ColumnType, Table};
+
+pub const COLUMNS: [Column; 5] = [
+    Column {
+        name: "LENGTH",
+        kind: ColumnType::BigInt,
+        not_null: false,
+    },
+    Column {
+        name: "MONOISOTOPIC_MASS",
+        kind: ColumnType::Double,
+        not_null: false,
+    },
+    Column {
+        name: "AVERAGE_MASS",
+        kind: ColumnType::Double,
+        not_null: false,
+    },
+    Column {
+        name: "CRC64",
+        kind: ColumnType::Char(16),
+        not_null: false,
+    },
+    Column {
+        name: "NON_STANDARD",
+        kind: ColumnType::Char(1),
+        not_null: false,
+    },
+];
+
+const STANDARD: &str = "ACDEFGHIKLMNPQRSTVWY";
+
+/// Monoisotopic and average mass of a residue, i.e. of the amino acid less
+/// one water.
+fn residue_mass(residue: char) -> Option<(f64, f64)> {
+    Some(match residue {
+        'A' => (71.03711, 71.0788),
+        'R' => (156.10111, 156.1875),
+        'N' => (114.04293, 114.1038),
+        'D' => (115.02694, 115.0886),
+        'C' => (103.00919, 103.1388),
+        'E' => (129.04259, 129.1155),
+        'Q' => (128.05858, 128.1307),
+        'G' => (57.02146, 57.0519),
+        'H' => (137.05891, 137.1411),
+        'I' => (113.08406, 113.1594),
+        'L' => (113.08406, 113.1594),
+        'K' => (128.09496, 128.1741),
+        'M' => (131.04049, 131.1926),
+        'F' => (147.06841, 147.1766),
+        'P' => (97.05276, 97.1167),
+        'S' => (87.03203, 87.0782),
+        'T' => (101.04768, 101.1051),
+        'W' => (186.07931, 186.2132),
+        'Y' => (163.06333, 163.1760),
+        'V' => (99.06841, 99.1326),
+        'U' => (150.95364, 150.0388),
+        'O' => (237.14773, 237.3018),
+        _ => return None,
+    })
+}
+
+/// Added once per chain for its termini.
+const WATER: (f64, f64) = (18.01056, 18.01528);
+
+/// Polynomial of CRC-64-ISO, reversed.
+const POLYNOMIAL: u64 = 0xD800_0000_0000_0000;
+
+/// The CRC of every byte value, computed at compile time.
+const CRC_TABLE: [u64; 256] = crc_table();
+
+const fn crc_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut part = index as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            part = if part & 1 == 1 { (part >> 1) ^ POLYNOMIAL } else { part >> 1 };
+            bit += 1;
+        }
+        table[index] = part;
+        index += 1;
+    }
+    table
+}
+
+/// Number of sequences with non-standard letters quoted in the warning.
+const EXAMPLES: usize = 5;
+
+pub struct Attributes {
+    pub length: u64,
+    /// `None` when a letter has no known mass, e.g. X.
+    pub mass: Option<(f64, f64)>,
+    pub crc64: String,
+    /// The distinct letters other than the 20 standard amino acids.
+    pub non_standard: Vec<char>,
+}
+
+pub fn measure(sequence: &str) -> Attributes {
+    let mut mass = Some(WATER);
+    let mut non_standard = Vec::new();
+    let mut length = 0;
+    for residue in sequence.chars().map(|c| c.to_ascii_uppercase()) {
+        length += 1;
+        if !STANDARD.contains(residue) && !non_standard.contains(&residue) {
+            non_standard.push(residue);
+        }
+        mass = match (mass, residue_mass(residue)) {
+            (Some((monoisotopic, average)), Some(residue)) => Some((monoisotopic + residue.0, average + residue.1)),
+            _ => None,
+        };
+    }
+    Attributes {
+        length,
+        mass: if length == 0 { None } else { mass },
+        crc64: crc64(sequence.as_bytes()),
+        non_standard,
+    }
+}
+
+/// The CRC64 of a sequence as UniProt and SwissKnife compute it, in upper
+/// case hex.
+pub fn crc64(bytes: &[u8]) -> String {
+    let crc = bytes
+        .iter()
+        .fold(0u64, |crc, &byte| CRC_TABLE[((crc ^ u64::from(byte)) & 0xff) as usize] ^ (crc >> 8));
+    format!("{:016X}", crc)
+}
+
+#[derive(Default)]
+pub struct Computed {
+    /// Sequences with letters other than the 20 standard amino acids.
+    pub non_standard: u64,
+    /// Some of the sequences with non-standard letters, e.g. `Q9XYZ1 (X, B)`.
+    pub examples: Vec<String>,
+}
+
+/// Sits between the SEQUENCE export and COPY and appends the attribute
+/// columns to every record, in the dialect of the export. A record without
+/// the fields of the table, or with a SEQ that is not UTF-8, ends the COPY
+/// with an error naming its line, as COPY would only report the computed
+/// columns as missing.
+pub struct Appender<R> {
+    records: csv::Reader<R>,
+    dialect: Dialect,
+    /// Number of fields of a record of the export.
+    fields: usize,
+    id: Option<usize>,
+    sequence: Option<usize>,
+    header_seen: bool,
+    pending: Vec<u8>,
+    position: usize,
+    pub computed: Computed,
+    /// Why a record was rejected, kept as COPY only passes on its message.
+    pub error: Option<io::Error>,
+}
+
+impl<R: BufRead> Appender<R> {
+    pub fn new(table: &Table, input: R, dialect: &Dialect) -> Appender<R> {
+        let position = |name: &str| table.columns.iter().position(|column| column.name == name);
+        Appender {
+            records: csv::Reader::new(input, dialect),
+            dialect: dialect.clone(),
+            fields: table.columns.len(),
+            id: position("ID"),
+            sequence: position("SEQ"),
+            header_seen: !dialect.header,
+            pending: Vec::new(),
+            position: 0,
+            computed: Computed::default(),
+            error: None,
+        }
+    }
+
+    /// Reads the next record into `pending`. Returns `false` at the end of
+    /// the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut record = match self.records.read_record()? {
+            Some(record) => record,
+            None => return Ok(false),
+        };
+        if !self.header_seen {
+            self.header_seen = true;
+            self.pending = record.raw;
+            return Ok(true);
+        }
+        let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", record.line, reason));
+        if record.fields.len() != self.fields {
+            return Err(invalid(format!("expected {} fields, found {}", self.fields, record.fields.len())));
+        }
+        let sequence = match self.sequence.map(|index| self.dialect.value(&record.fields[index], "SEQ")) {
+            Some(Ok(sequence)) => sequence.map(measure),
+            None => None,
+            Some(Err(error)) => return Err(invalid(format!("SEQ has {}", error))),
+        };
+
+        let mut terminator = Vec::new();
+        while matches!(record.raw.last(), Some(b'\n') | Some(b'\r')) {
+            terminator.insert(0, record.raw.pop().unwrap());
+        }
+        let null = self.dialect.null.as_str();
+        let values: Vec<String> = match sequence {
+            Some(attributes) => {
+                let (monoisotopic, average) = match attributes.mass {
+                    Some((monoisotopic, average)) => (format!("{:.5}", monoisotopic), format!("{:.4}", average)),
+                    None => (null.to_string(), null.to_string()),
+                };
+                let non_standard = !attributes.non_standard.is_empty();
+                if non_standard {
+                    self.computed.non_standard += 1;
+                    if self.computed.examples.len() < EXAMPLES {
+                        let id = self
+                            .id
+                            .and_then(|id| self.dialect.value(&record.fields[id], "ID").ok().flatten())
+                            .unwrap_or_default();
+                        let letters: Vec<String> = attributes.non_standard.iter().map(char::to_string).collect();
+                        self.computed.examples.push(format!("{} ({})", id, letters.join(", ")));
+                    }
+                }
+                vec![
+                    attributes.length.to_string(),
+                    monoisotopic,
+                    average,
+                    attributes.crc64,
+                    if non_standard { "Y" } else { "N" }.to_string(),
+                ]
+            }
+            None => vec![null.to_string(); COLUMNS.len()],
+        };
+        self.pending = record.raw;
+        for value in values {
+            self.pending.push(self.dialect.delimiter);
+            self.pending.extend_from_slice(value.as_bytes());
+        }
+        self.pending.extend_from_slice(if terminator.is_empty() { b"\n" } else { &terminator });
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Appender<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.pending.len() {
+            self.pending.clear();
+            self.position = 0;
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return Ok(0),
+                Err(error) => {
+                    let copy = io::Error::new(error.kind(), error.to_string());
+                    self.error = Some(error);
+                    return Err(copy);
+                }
+            }
+        }
+        let count = buf.len().min(self.pending.len() - self.position);
+        buf[..count].copy_from_slice(&self.pending[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
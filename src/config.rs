@@ -0,0 +1,632 @@
+//! Optional TOML configuration file, given with `--config`.
+//!
+//! Settings are grouped per table, e.g.
+//!
+//! ```toml
+//! [tables.MV_EVENT]
+//! file = "./mv_event_export.tsv"
+//! delimiter = "\t"
+//! null = "\\N"
+//! force_not_null = ["NOTE"]
+//! ```
+//!
+//! Every setting is optional; tables that are not listed are read with the
+//! defaults, i.e. comma separated with a header row.
+//!
+//! Tables that are not built into the importer can be defined by listing
+//! their columns, and indexes can be added to any table:
+//!
+//! ```toml
+//! [tables.CURATED_SITE]
+//! file = "./curated_site_export.csv"
+//! columns = [
+//!     { name = "SITE_ID", type = "BIGINT", not_null = true },
+//!     { name = "SUB_CODE", type = "VARCHAR(25)" },
+//! ]
+//! indexes = [{ name = "curated_site_sub_code_idx", columns = ["SUB_CODE"] }]
+//! ```
+//!
+//! An index key is a column or an expression such as `lower(GENE_NAME)`.
+//! Indexes can also be unique, partial (`where`), use another `method`, or
+//! an operator class for every key:
+//!
+//! ```toml
+//! extensions = ["pg_trgm"]
+//!
+//! [tables.MV_ENTRY]
+//! indexes = [{ name = "gene_name_trgm_idx", columns = ["GENE_NAME"], method = "gin", opclass = "gin_trgm_ops" }]
+//! ```
+//!
+//! Controlled vocabularies become lookup tables, named after the section,
+//! that the listed columns are checked against:
+//!
+//! ```toml
+//! [vocabularies.EVENT_NAME]
+//! columns = ["MV_EVENT.EVENT_NAME", "MV_EFIP.PTM_EVENT_NAME"]
+//! values = ["Acetylation", "Phosphorylation"]
+//! on_unknown = "warn"
+//! foreign_keys = true
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use toml;
+
+use csv::Dialect;
+use fasta;
+use ontology;
+use search;
+use sites;
+use schema::{is_column, Column, ColumnType, Index, IndexKey, IndexMethod, Table, DERIVED, INDEXES, SEQUENCE, TABLES};
+use vocabulary;
+use vocabulary::{Member, Vocabulary};
+use windows;
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Extensions created before the indexes, e.g. `pg_trgm` for trigram
+    /// operator classes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub tables: BTreeMap<String, TableConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vocabularies: BTreeMap<String, VocabularyConfig>,
+    /// Tables defined in the file rather than built in, set up by `load`.
+    #[serde(skip)]
+    defined: Vec<&'static Table>,
+    /// Indexes listed in the file, set up by `load`.
+    #[serde(skip)]
+    indexes: Vec<&'static Index>,
+    /// Built-in tables with columns added in the file, set up by `load`.
+    #[serde(skip)]
+    extended: Vec<&'static Table>,
+    /// Lookup tables of the vocabularies, set up by `load`.
+    #[serde(skip)]
+    vocabularies_defined: Vec<Vocabulary>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableConfig {
+    /// Path of the export, instead of the default file name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub force_null: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub force_not_null: Vec<String>,
+    /// Whether to ANALYZE the table after COMMIT. Default - true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analyze: Option<bool>,
+    /// VACUUM the table after COMMIT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vacuum: Option<Vacuum>,
+    /// Index to CLUSTER the table on before COMMIT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// Columns of a table that is not built in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<ColumnConfig>,
+    /// Columns of SEQUENCE filled from the headers of a FASTA file:
+    /// ENTRY_NAME, ORGANISM and SEQUENCE_VERSION.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header_columns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexes: Vec<IndexConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VocabularyConfig {
+    /// Columns taking the values, e.g. `MV_EVENT.EVENT_NAME`.
+    pub columns: Vec<String>,
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub on_unknown: OnUnknown,
+    /// Whether to tie the columns to the lookup table.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub foreign_keys: bool,
+}
+
+/// What to do with values of a column that are not in its vocabulary.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnUnknown {
+    /// Fail the import.
+    #[default]
+    Fail,
+    /// Report them and load them all the same.
+    Warn,
+    /// Add them to the lookup table.
+    AutoAdd,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vacuum {
+    Plain,
+    /// VACUUM (FREEZE), so that later vacuums of the unchanged rows are
+    /// cheap.
+    Freeze,
+}
+
+/// What to do with a table once it is loaded.
+pub struct Maintenance {
+    pub analyze: bool,
+    pub vacuum: Option<Vacuum>,
+    pub cluster: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnConfig {
+    pub name: String,
+    /// BIGINT, DOUBLE PRECISION, DATE, VARCHAR(n), CHAR(n) or TEXT.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub not_null: bool,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexConfig {
+    pub name: String,
+    /// Columns or expressions, e.g. `lower(GENE_NAME)`.
+    pub columns: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unique: bool,
+    /// btree, hash, gin or gist. Default - btree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Operator class of every key, e.g. `text_pattern_ops`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opclass: Option<String>,
+    /// Condition of a partial index, e.g. `ENZ_CODE IS NOT NULL`.
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Config {
+    /// Reads and checks a configuration file.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let mut config: Config = toml::from_str(&text).map_err(|error| format!("{}: {}", path.display(), error))?;
+        config.check().map_err(|error| format!("{}: {}", path.display(), error))?;
+        config.define();
+        Ok(config)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        for (name, settings) in &self.tables {
+            if DERIVED.iter().any(|table| table.name.eq_ignore_ascii_case(name)) {
+                return Err(format!("tables.{}: the table is built from the other tables and has no settings", name));
+            }
+            let columns: Vec<&str> = match find_table(name) {
+                Some(_) if !settings.columns.is_empty() => {
+                    return Err(format!("tables.{}: columns can only be given for tables that are not built in", name));
+                }
+                Some(table) => {
+                    settings.check_header_columns(table).map_err(|error| format!("tables.{}: {}", name, error))?;
+                    let added = settings.header_columns.iter().map(String::as_str);
+                    table.all_columns().map(|column| column.name).chain(added).collect()
+                }
+                None if !settings.header_columns.is_empty() => {
+                    return Err(format!("tables.{}: header_columns can only be given for SEQUENCE", name));
+                }
+                None if settings.columns.is_empty() => return Err(format!("unknown table {}", name)),
+                None if !is_identifier(name) => return Err(format!("{:?} is not a valid table name", name)),
+                None => {
+                    settings.check_definition().map_err(|error| format!("tables.{}: {}", name, error))?;
+                    settings.columns.iter().map(|column| column.name.as_str()).collect()
+                }
+            };
+            settings.dialect().map_err(|error| format!("tables.{}: {}", name, error))?;
+            let indexed = settings
+                .indexes
+                .iter()
+                .flat_map(|index| index.columns.iter())
+                .filter(|key| is_column(key));
+            for column in settings.force_null.iter().chain(settings.force_not_null.iter()).chain(indexed) {
+                if !columns.iter().any(|candidate| candidate.eq_ignore_ascii_case(column)) {
+                    return Err(format!("tables.{}: unknown column {}", name, column));
+                }
+            }
+            let computed = find_table(name).map_or(&[][..], |table| table.computed);
+            for column in settings.force_null.iter().chain(settings.force_not_null.iter()) {
+                if computed.iter().any(|candidate| candidate.name.eq_ignore_ascii_case(column)) {
+                    return Err(format!("tables.{}: {} is computed during the import, not read from the export, and cannot be forced", name, column));
+                }
+            }
+            for index in &settings.indexes {
+                if !is_identifier(&index.name) || index.columns.iter().all(|key| key.trim().is_empty()) {
+                    return Err(format!("tables.{}: index {:?} needs a valid name and at least one column", name, index.name));
+                }
+                if index.method.as_ref().is_some_and(|method| IndexMethod::parse(method).is_none()) {
+                    return Err(format!("tables.{}: index {} has an unknown method {:?}", name, index.name, index.method.as_ref().unwrap()));
+                }
+                if index.opclass.as_ref().is_some_and(|opclass| !is_identifier(opclass)) {
+                    return Err(format!("tables.{}: index {} has an invalid opclass", name, index.name));
+                }
+            }
+            if let Some(ref cluster) = settings.cluster {
+                let built_in = INDEXES.iter().any(|index| index.table.eq_ignore_ascii_case(name) && index.name == cluster);
+                if !built_in && !settings.indexes.iter().any(|index| &index.name == cluster) {
+                    return Err(format!("tables.{}: cluster must name an index of the table, got {:?}", name, cluster));
+                }
+            }
+        }
+        for (name, vocabulary) in &self.vocabularies {
+            self.check_vocabulary(name, vocabulary).map_err(|error| format!("vocabularies.{}: {}", name, error))?;
+        }
+        let lookup_indexes: Vec<String> = self.vocabularies.keys().map(|name| lookup_index_name(name)).collect();
+        let mut names: Vec<&str> = INDEXES
+            .iter()
+            .chain(search::INDEXES.iter())
+            .chain(sites::INDEXES.iter())
+            .chain(windows::INDEXES.iter())
+            .chain(ontology::INDEXES.iter())
+            .map(|index| index.name)
+            .chain(lookup_indexes.iter().map(String::as_str))
+            .collect();
+        for index in self.tables.values().flat_map(|settings| settings.indexes.iter()) {
+            if names.iter().any(|name| name.eq_ignore_ascii_case(&index.name)) {
+                return Err(format!("index {} is defined twice", index.name));
+            }
+            names.push(&index.name);
+        }
+        for extension in &self.extensions {
+            if !is_identifier(extension) {
+                return Err(format!("{:?} is not a valid extension name", extension));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_vocabulary(&self, name: &str, vocabulary: &VocabularyConfig) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err(format!("{:?} is not a valid table name", name));
+        }
+        let taken = find_table(name).is_some()
+            || DERIVED.iter().any(|table| table.name.eq_ignore_ascii_case(name))
+            || self.tables.keys().any(|table| table.eq_ignore_ascii_case(name))
+            || self.vocabularies.keys().filter(|other| other.eq_ignore_ascii_case(name)).count() > 1;
+        if taken {
+            return Err("the name of the lookup table is already taken by another table".to_string());
+        }
+        if vocabulary.columns.is_empty() || vocabulary.values.is_empty() {
+            return Err("needs at least one column and one value".to_string());
+        }
+        for (index, value) in vocabulary.values.iter().enumerate() {
+            if vocabulary.values[..index].contains(value) {
+                return Err(format!("value {:?} is listed twice", value));
+            }
+        }
+        for (index, column) in vocabulary.columns.iter().enumerate() {
+            let member = parse_member(column).ok_or_else(|| format!("{:?} must be TABLE.COLUMN", column))?;
+            match self.column_kind(&member.table, &member.column) {
+                Some(ColumnType::Varchar(_)) | Some(ColumnType::Char(_)) | Some(ColumnType::Text) => (),
+                Some(kind) => return Err(format!("{} is a {} column, not a text column", column, kind)),
+                None => return Err(format!("unknown column {}", column)),
+            }
+            if vocabulary.columns[..index].iter().any(|other| other.eq_ignore_ascii_case(column)) {
+                return Err(format!("column {} is listed twice", column));
+            }
+        }
+        Ok(())
+    }
+
+    /// Type of a column of a built-in table or a table defined in the file.
+    fn column_kind(&self, table: &str, column: &str) -> Option<ColumnType> {
+        if let Some(table) = find_table(table) {
+            return table.all_columns().find(|candidate| candidate.name.eq_ignore_ascii_case(column)).map(|column| column.kind);
+        }
+        self.tables
+            .iter()
+            .find(|&(name, _)| name.eq_ignore_ascii_case(table))
+            .and_then(|(_, settings)| settings.columns.iter().find(|candidate| candidate.name.eq_ignore_ascii_case(column)))
+            .and_then(|column| ColumnType::parse(&column.kind))
+    }
+
+    /// Sets up the tables and indexes defined in the file. They live as long
+    /// as the process, like the built-in ones.
+    fn define(&mut self) {
+        let leak = |value: String| -> &'static str { Box::leak(value.into_boxed_str()) };
+        for (name, settings) in &self.tables {
+            for index in &settings.indexes {
+                let opclass = index.opclass.clone().map(leak);
+                let keys: Vec<IndexKey> = index
+                    .columns
+                    .iter()
+                    .filter(|key| !key.trim().is_empty())
+                    .map(|key| IndexKey {
+                        expression: leak(key.trim().to_string()),
+                        opclass,
+                    })
+                    .collect();
+                self.indexes.push(Box::leak(Box::new(Index {
+                    name: leak(index.name.clone()),
+                    table: leak(name.to_uppercase()),
+                    // Checked by `check`.
+                    method: index.method.as_ref().map_or(IndexMethod::BTree, |method| IndexMethod::parse(method).unwrap()),
+                    unique: index.unique,
+                    keys: Box::leak(keys.into_boxed_slice()),
+                    predicate: index.predicate.clone().map(leak),
+                })));
+            }
+            if let Some(table) = find_table(name) {
+                if !settings.header_columns.is_empty() {
+                    // Checked by `check_header_columns`.
+                    let added = settings.header_columns.iter().map(|name| {
+                        let column = fasta::HEADER_COLUMNS.iter().find(|column| column.name.eq_ignore_ascii_case(name)).unwrap();
+                        *column
+                    });
+                    let columns: Vec<Column> = table.columns.iter().cloned().chain(added).collect();
+                    self.extended.push(Box::leak(Box::new(Table {
+                        name: table.name,
+                        export: table.export,
+                        columns: Box::leak(columns.into_boxed_slice()),
+                        computed: table.computed,
+                    })));
+                }
+                continue;
+            }
+            let columns: Vec<Column> = settings
+                .columns
+                .iter()
+                .map(|column| Column {
+                    name: leak(column.name.to_uppercase()),
+                    // Checked by `check_definition`.
+                    kind: ColumnType::parse(&column.kind).unwrap(),
+                    not_null: column.not_null,
+                })
+                .collect();
+            let export = settings
+                .file
+                .clone()
+                .unwrap_or_else(|| format!("./{}_export.csv", name.to_lowercase()));
+            self.defined.push(Box::leak(Box::new(Table {
+                name: leak(name.to_uppercase()),
+                export: leak(export),
+                columns: Box::leak(columns.into_boxed_slice()),
+                computed: &[],
+            })));
+        }
+        for (name, settings) in &self.vocabularies {
+            let lookup: &'static Table = Box::leak(Box::new(Table {
+                name: leak(name.to_uppercase()),
+                export: "",
+                columns: &[vocabulary::VALUE],
+                computed: &[],
+            }));
+            // Unique, as the foreign keys reference VALUE.
+            self.indexes.push(Box::leak(Box::new(Index {
+                name: leak(lookup_index_name(name)),
+                table: lookup.name,
+                method: IndexMethod::BTree,
+                unique: true,
+                keys: &[IndexKey {
+                    expression: vocabulary::VALUE.name,
+                    opclass: None,
+                }],
+                predicate: None,
+            })));
+            self.vocabularies_defined.push(Vocabulary {
+                lookup,
+                values: settings.values.clone(),
+                // Checked by `check_vocabulary`.
+                members: settings.columns.iter().map(|column| parse_member(column).unwrap()).collect(),
+                on_unknown: settings.on_unknown,
+                foreign_keys: settings.foreign_keys,
+            });
+        }
+    }
+
+    /// Every table of the import, the built-in ones first.
+    pub fn tables(&self) -> Vec<&'static Table> {
+        TABLES
+            .iter()
+            .map(|&table| {
+                self.extended
+                    .iter()
+                    .cloned()
+                    .find(|extended| extended.name == table.name)
+                    .unwrap_or(table)
+            })
+            .chain(self.defined.iter().cloned())
+            .collect()
+    }
+
+    /// The vocabularies of the file, whose lookup tables are built from the
+    /// file rather than read from an export.
+    pub fn vocabularies(&self) -> &[Vocabulary] {
+        &self.vocabularies_defined
+    }
+
+    pub fn table(&self, name: &str) -> Option<&'static Table> {
+        self.tables().into_iter().find(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn maintenance(&self, table: &Table) -> Maintenance {
+        let settings = self.settings(table);
+        Maintenance {
+            analyze: settings.and_then(|settings| settings.analyze).unwrap_or(true),
+            vacuum: settings.and_then(|settings| settings.vacuum),
+            cluster: settings.and_then(|settings| settings.cluster.clone()),
+        }
+    }
+
+    /// Every index of the import, the built-in ones first.
+    pub fn indexes(&self) -> Vec<&'static Index> {
+        INDEXES.iter().chain(self.indexes.iter().cloned()).collect()
+    }
+
+    fn settings(&self, table: &Table) -> Option<&TableConfig> {
+        self.tables
+            .iter()
+            .find(|&(name, _)| name.eq_ignore_ascii_case(table.name))
+            .map(|(_, settings)| settings)
+    }
+
+    /// Path of the export `table` is populated from.
+    pub fn export(&self, table: &Table) -> String {
+        self.settings(table)
+            .and_then(|settings| settings.file.clone())
+            .unwrap_or_else(|| table.export.to_string())
+    }
+
+    pub fn dialect(&self, table: &Table) -> Dialect {
+        match self.settings(table) {
+            // Checked when the file was loaded.
+            Some(settings) => settings.dialect().unwrap(),
+            None => Dialect::default(),
+        }
+    }
+}
+
+impl TableConfig {
+    pub fn dialect(&self) -> Result<Dialect, String> {
+        let default = Dialect::default();
+        let delimiter = byte("delimiter", &self.delimiter, default.delimiter)?;
+        let quote = byte("quote", &self.quote, default.quote)?;
+        // COPY defaults the escape to the quote character.
+        let escape = byte("escape", &self.escape, quote)?;
+        let null = self.null.clone().unwrap_or(default.null);
+        if delimiter == quote {
+            return Err("delimiter and quote must differ".to_string());
+        }
+        if null.contains(char::from(delimiter)) || null.contains(char::from(quote)) {
+            return Err("null must not contain the delimiter or the quote".to_string());
+        }
+        Ok(Dialect {
+            delimiter,
+            quote,
+            escape,
+            null,
+            header: self.header.unwrap_or(default.header),
+            force_null: self.force_null.clone(),
+            force_not_null: self.force_not_null.clone(),
+        })
+    }
+
+    fn check_header_columns(&self, table: &Table) -> Result<(), String> {
+        if self.header_columns.is_empty() {
+            return Ok(());
+        }
+        let file = self.file.as_ref().map_or(table.export, String::as_str);
+        if table.name != SEQUENCE.name || !fasta::is_fasta(file) {
+            return Err("header_columns can only be given for SEQUENCE read from a FASTA file".to_string());
+        }
+        for (index, name) in self.header_columns.iter().enumerate() {
+            if !fasta::HEADER_COLUMNS.iter().any(|column| column.name.eq_ignore_ascii_case(name)) {
+                return Err(format!("unknown header column {}, expected ENTRY_NAME, ORGANISM or SEQUENCE_VERSION", name));
+            }
+            if self.header_columns[..index].iter().any(|other| other.eq_ignore_ascii_case(name)) {
+                return Err(format!("header column {} is listed twice", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_definition(&self) -> Result<(), String> {
+        for (index, column) in self.columns.iter().enumerate() {
+            if !is_identifier(&column.name) {
+                return Err(format!("{:?} is not a valid column name", column.name));
+            }
+            if ColumnType::parse(&column.kind).is_none() {
+                return Err(format!("column {} has an unknown type {:?}", column.name, column.kind));
+            }
+            if self.columns[..index].iter().any(|other| other.name.eq_ignore_ascii_case(&column.name)) {
+                return Err(format!("column {} is listed twice", column.name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_dialect(dialect: &Dialect) -> TableConfig {
+        let default = Dialect::default();
+        let text = |byte: u8| char::from(byte).to_string();
+        TableConfig {
+            file: None,
+            delimiter: Some(text(dialect.delimiter)),
+            quote: Some(text(dialect.quote)),
+            escape: if dialect.escape == dialect.quote {
+                None
+            } else {
+                Some(text(dialect.escape))
+            },
+            null: if dialect.null == default.null {
+                None
+            } else {
+                Some(dialect.null.clone())
+            },
+            header: Some(dialect.header),
+            force_null: dialect.force_null.clone(),
+            force_not_null: dialect.force_not_null.clone(),
+            analyze: None,
+            vacuum: None,
+            cluster: None,
+            columns: Vec::new(),
+            header_columns: Vec::new(),
+            indexes: Vec::new(),
+        }
+    }
+}
+
+/// COPY only accepts single-byte delimiter, quote and escape characters.
+fn byte(name: &str, value: &Option<String>, default: u8) -> Result<u8, String> {
+    match *value {
+        None => Ok(default),
+        Some(ref value) if value.len() == 1 && value.is_ascii() && value != "\n" && value != "\r" => {
+            Ok(value.as_bytes()[0])
+        }
+        Some(ref value) => Err(format!("{} must be a single ASCII character, got {:?}", name, value)),
+    }
+}
+
+/// Table and column names are written into SQL statements as they are.
+fn is_identifier(name: &str) -> bool {
+    let mut characters = name.chars();
+    characters.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.len() <= 63
+}
+
+fn lookup_index_name(vocabulary: &str) -> String {
+    format!("{}_value_idx", vocabulary.to_lowercase())
+}
+
+/// Splits `TABLE.COLUMN`, upper-cased.
+fn parse_member(column: &str) -> Option<Member> {
+    let mut parts = column.trim().splitn(2, '.');
+    let (table, column) = (parts.next()?, parts.next()?);
+    if !is_identifier(table) || !is_identifier(column) {
+        return None;
+    }
+    Some(Member {
+        table: table.to_uppercase(),
+        column: column.to_uppercase(),
+    })
+}
+
+fn find_table(name: &str) -> Option<&'static Table> {
+    TABLES.iter().cloned().find(|table| table.name.eq_ignore_ascii_case(name))
+}
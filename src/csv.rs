@@ -0,0 +1,225 @@
+//! A small CSV reader that follows the rules of `COPY ... CSV`.
+//!
+//! Unlike a general purpose CSV parser it keeps the raw bytes of every
+//! record, so that a record can be passed on to COPY unchanged, and it keeps
+//! quoted empty fields apart from unquoted ones, because only the latter are
+//! NULL for COPY. Records are numbered by the physical line they start on,
+//! which is how Postgres counts lines in its error messages.
+
+use std::io;
+use std::io::BufRead;
+use std::str;
+
+/// The CSV options of one export, as passed to COPY.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: u8,
+    /// Unquoted fields equal to this string are NULL.
+    pub null: String,
+    pub header: bool,
+    /// Columns whose quoted values are NULL too when they match `null`.
+    pub force_null: Vec<String>,
+    /// Columns that are never NULL; a `null` value is an empty string.
+    pub force_not_null: Vec<String>,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect {
+            delimiter: b',',
+            quote: b'"',
+            escape: b'"',
+            null: String::new(),
+            header: true,
+            force_null: Vec::new(),
+            force_not_null: Vec::new(),
+        }
+    }
+}
+
+impl Dialect {
+    /// The options of a `COPY ... FROM STDIN WITH (...)` statement.
+    pub fn copy_options(&self) -> String {
+        let mut options = vec![
+            "FORMAT csv".to_string(),
+            format!("DELIMITER {}", literal(&char::from(self.delimiter).to_string())),
+            format!("QUOTE {}", literal(&char::from(self.quote).to_string())),
+            format!("ESCAPE {}", literal(&char::from(self.escape).to_string())),
+            format!("NULL {}", literal(&self.null)),
+            format!("HEADER {}", self.header),
+        ];
+        if !self.force_null.is_empty() {
+            options.push(format!("FORCE_NULL ({})", self.force_null.join(", ")));
+        }
+        if !self.force_not_null.is_empty() {
+            options.push(format!("FORCE_NOT_NULL ({})", self.force_not_null.join(", ")));
+        }
+        options.join(", ")
+    }
+
+    /// The value of `field` in `column` as COPY sees it: `None` for NULL.
+    pub fn value<'a>(&self, field: &'a Field, column: &str) -> Result<Option<&'a str>, String> {
+        let value = match str::from_utf8(&field.value) {
+            Ok(value) => value,
+            Err(error) => return Err(format!("invalid UTF-8 at byte {}", error.valid_up_to())),
+        };
+        if value != self.null || is_listed(&self.force_not_null, column) {
+            Ok(Some(value))
+        } else if !field.quoted || is_listed(&self.force_null, column) {
+            Ok(None)
+        } else {
+            Ok(Some(value))
+        }
+    }
+}
+
+fn is_listed(columns: &[String], column: &str) -> bool {
+    columns.iter().any(|listed| listed.eq_ignore_ascii_case(column))
+}
+
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Tracks whether a stream of bytes is inside a quoted field, to find where
+/// records end.
+#[derive(Clone, Copy)]
+pub struct QuoteState {
+    quote: u8,
+    escape: u8,
+    in_quotes: bool,
+    escaped: bool,
+}
+
+impl QuoteState {
+    pub fn new(dialect: &Dialect) -> QuoteState {
+        QuoteState {
+            quote: dialect.quote,
+            escape: dialect.escape,
+            in_quotes: false,
+            escaped: false,
+        }
+    }
+
+    pub fn in_quotes(&self) -> bool {
+        self.in_quotes
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.escaped {
+                self.escaped = false;
+                if byte == self.quote || byte == self.escape {
+                    continue;
+                }
+            }
+            if self.in_quotes && byte == self.escape && self.escape != self.quote {
+                self.escaped = true;
+            } else if byte == self.quote {
+                // With the quote as escape, a doubled quote toggles twice.
+                self.in_quotes = !self.in_quotes;
+            }
+        }
+    }
+}
+
+pub struct Field {
+    pub value: Vec<u8>,
+    pub quoted: bool,
+}
+
+pub struct Record {
+    /// The record exactly as read, including its line terminator.
+    pub raw: Vec<u8>,
+    pub fields: Vec<Field>,
+    /// Physical line the record starts on, counting from 1.
+    pub line: u64,
+    /// Number of physical lines the record spans.
+    pub lines: u64,
+}
+
+pub struct Reader<R> {
+    inner: R,
+    dialect: Dialect,
+    line: u64,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(inner: R, dialect: &Dialect) -> Reader<R> {
+        Reader {
+            inner,
+            dialect: dialect.clone(),
+            line: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let line = self.line + 1;
+        let mut raw = Vec::new();
+        let mut quotes = QuoteState::new(&self.dialect);
+        loop {
+            let start = raw.len();
+            if self.inner.read_until(b'\n', &mut raw)? == 0 {
+                break;
+            }
+            self.line += 1;
+            quotes.feed(&raw[start..]);
+            if !quotes.in_quotes() {
+                break;
+            }
+        }
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let fields = split(&raw, &self.dialect);
+        let lines = self.line - line + 1;
+        Ok(Some(Record { raw, fields, line, lines }))
+    }
+}
+
+fn split(raw: &[u8], dialect: &Dialect) -> Vec<Field> {
+    let mut end = raw.len();
+    if end > 0 && raw[end - 1] == b'\n' {
+        end -= 1;
+    }
+    if end > 0 && raw[end - 1] == b'\r' {
+        end -= 1;
+    }
+
+    let mut fields = Vec::new();
+    let mut field = Field { value: Vec::new(), quoted: false };
+    let mut in_quotes = false;
+    let mut index = 0;
+    while index < end {
+        let byte = raw[index];
+        let next = raw[..end].get(index + 1).cloned();
+        if in_quotes {
+            let escaped = next == Some(dialect.quote) || (dialect.escape != dialect.quote && next == Some(dialect.escape));
+            if byte == dialect.escape && escaped {
+                field.value.extend(next);
+                index += 1;
+            } else if byte == dialect.quote {
+                in_quotes = false;
+            } else {
+                field.value.push(byte);
+            }
+        } else if byte == dialect.quote {
+            in_quotes = true;
+            field.quoted = true;
+        } else if byte == dialect.delimiter {
+            fields.push(field);
+            field = Field { value: Vec::new(), quoted: false };
+        } else {
+            field.value.push(byte);
+        }
+        index += 1;
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,186 @@
+//! Normalization of exports to the UTF-8 that COPY expects.
+//!
+//! Some upstream exports are written as Latin-1 or Windows-1252, start with
+//! a byte order mark or end their lines with CRLF. `Transcoder` fixes all of
+//! that line by line while the export is streamed into COPY, so the file
+//! never has to be converted on disk.
+
+use std::io;
+use std::io::{BufRead, Read};
+use std::str;
+
+use csv::{Dialect, QuoteState};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+    /// UTF-8 where a line is valid UTF-8, Windows-1252 otherwise.
+    Auto,
+}
+
+impl Encoding {
+    pub fn parse(value: &str) -> Option<Encoding> {
+        match value.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            "auto" => Some(Encoding::Auto),
+            _ => None,
+        }
+    }
+
+    /// Decodes one line, or returns `None` when it is already UTF-8 and
+    /// can be passed on as is.
+    fn decode(&self, line: &[u8]) -> Option<String> {
+        if line.is_ascii() {
+            return None;
+        }
+        match *self {
+            Encoding::Utf8 => None,
+            Encoding::Latin1 => Some(line.iter().map(|&byte| char::from(byte)).collect()),
+            Encoding::Windows1252 => Some(line.iter().map(|&byte| windows_1252(byte)).collect()),
+            Encoding::Auto => match str::from_utf8(line) {
+                Ok(_) => None,
+                Err(_) => Encoding::Windows1252.decode(line),
+            },
+        }
+    }
+}
+
+/// Windows-1252 differs from Latin-1 only in 0x80-0x9F. The five unused
+/// code points map to the C1 controls, as in the WHATWG encoding standard.
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+        '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+pub struct Transcoder<R> {
+    inner: R,
+    encoding: Encoding,
+    started: bool,
+    quotes: QuoteState,
+    record_converted: bool,
+    line: Vec<u8>,
+    pending: Vec<u8>,
+    position: usize,
+    /// Records with at least one line that had to be transcoded.
+    pub converted: u64,
+    pub stripped_bom: bool,
+    /// Records whose CRLF ending was replaced with LF.
+    pub crlf_lines: u64,
+}
+
+impl<R: BufRead> Transcoder<R> {
+    pub fn new(inner: R, encoding: Encoding, dialect: &Dialect) -> Transcoder<R> {
+        Transcoder {
+            inner,
+            encoding,
+            started: false,
+            quotes: QuoteState::new(dialect),
+            record_converted: false,
+            line: Vec::new(),
+            pending: Vec::new(),
+            position: 0,
+            converted: 0,
+            stripped_bom: false,
+            crlf_lines: 0,
+        }
+    }
+
+    /// Reads and converts the next line. Leaves `pending` empty at the end
+    /// of the input.
+    fn next_line(&mut self) -> io::Result<()> {
+        self.pending.clear();
+        self.position = 0;
+        self.line.clear();
+        if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+            if self.record_converted {
+                self.converted += 1;
+                self.record_converted = false;
+            }
+            return Ok(());
+        }
+
+        let mut line = &self.line[..];
+        if !self.started {
+            self.started = true;
+            if line.starts_with(BOM) {
+                line = &line[BOM.len()..];
+                self.stripped_bom = true;
+            }
+        }
+
+        let (mut body, ending): (&[u8], &[u8]) = if line.ends_with(b"\r\n") {
+            (&line[..line.len() - 2], b"\n")
+        } else if line.ends_with(b"\n") {
+            (&line[..line.len() - 1], b"\n")
+        } else {
+            (line, b"")
+        };
+
+        // Quote and escape are ASCII, the same bytes in every supported
+        // encoding, so records can be told apart before decoding. A CRLF
+        // inside a quoted field is data and is kept as is.
+        self.quotes.feed(body);
+        if line.ends_with(b"\r\n") {
+            if self.quotes.in_quotes() {
+                body = &line[..line.len() - 1];
+            } else {
+                self.crlf_lines += 1;
+            }
+        }
+
+        match self.encoding.decode(body) {
+            Some(decoded) => {
+                self.pending.extend_from_slice(decoded.as_bytes());
+                self.record_converted = true;
+            }
+            None => self.pending.extend_from_slice(body),
+        }
+        self.pending.extend_from_slice(ending);
+
+        if !self.quotes.in_quotes() && self.record_converted {
+            self.converted += 1;
+            self.record_converted = false;
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = {
+            let available = self.fill_buf()?;
+            let count = buf.len().min(available.len());
+            buf[..count].copy_from_slice(&available[..count]);
+            count
+        };
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<R: BufRead> BufRead for Transcoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.pending.len() {
+            self.next_line()?;
+        }
+        Ok(&self.pending[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
@@ -0,0 +1,114 @@
+//! Errors that end an import run.
+
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use postgres;
+
+use locate::Location;
+use quarantine::RejectLimit;
+
+pub enum ImportError {
+    /// The database rejected a statement or the connection to it failed.
+    Database(postgres::Error),
+    /// COPY rejected a record of an export, found at the given location.
+    Copy(postgres::Error, Location),
+    /// A DDL statement did not get its lock, even after retrying, because of
+    /// the listed sessions.
+    Blocked(postgres::Error, Vec<String>),
+    /// The configuration file is missing or invalid.
+    Config(String),
+    /// Another run holds the import lock: the `database.schema` it is for,
+    /// the holding session if it could be found and how long was waited.
+    Locked(String, Option<String>, Duration),
+    /// An input file could not be read.
+    Input(String, io::Error),
+    /// More rows of a table were rejected in tolerant mode than allowed:
+    /// table, rejected rows, rows read and the limit.
+    TooManyRejects(String, u64, u64, RejectLimit),
+    /// A column has values that are not in its vocabulary: `TABLE.COLUMN`,
+    /// the lookup table, the rows and some of the values.
+    Unknown(String, &'static str, u64, String),
+}
+
+impl ImportError {
+    /// A coarse classification of the error, used to count failures by
+    /// cause in the metrics and the report.
+    pub fn class(&self) -> &'static str {
+        match *self {
+            ImportError::Database(ref error) | ImportError::Copy(ref error, _) | ImportError::Blocked(ref error, _) => {
+                database_class(error)
+            }
+            ImportError::Config(_) => "config",
+            ImportError::Locked(..) => "lock",
+            ImportError::Input(_, _) => "input",
+            ImportError::TooManyRejects(..) | ImportError::Unknown(..) => "data",
+        }
+    }
+}
+
+fn database_class(error: &postgres::Error) -> &'static str {
+    if error.as_connection().is_some() || error.as_io().is_some() {
+        return "connection";
+    }
+    let code = match error.code() {
+        Some(state) => state.code(),
+        None => return "database",
+    };
+    match code {
+        "55P03" | "40P01" => "lock",
+        "57014" => "timeout",
+        _ => match &code[..2] {
+            "08" | "28" | "3D" => "connection",
+            "22" | "23" => "data",
+            "42" => "schema",
+            _ => "database",
+        },
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImportError::Database(ref error) => write!(f, "{}", error),
+            ImportError::Copy(ref error, ref location) => write!(f, "{}\n    at {}", error, location),
+            ImportError::Blocked(ref error, ref sessions) => {
+                write!(f, "{}", error)?;
+                for session in sessions {
+                    write!(f, "\n    blocked by {}", session)?;
+                }
+                Ok(())
+            }
+            ImportError::Config(ref error) => write!(f, "invalid configuration - {}", error),
+            ImportError::Locked(ref scope, ref holder, wait) => {
+                write!(f, "another run holds the import lock on {}", scope)?;
+                if let Some(ref holder) = *holder {
+                    write!(f, ": {}", holder)?;
+                }
+                if wait > Duration::from_secs(0) {
+                    write!(f, ", gave up after waiting {}s", wait.as_secs())?;
+                }
+                Ok(())
+            }
+            ImportError::Input(ref path, ref error) => write!(f, "{}: {}", path, error),
+            ImportError::TooManyRejects(ref table, rejected, total, limit) => {
+                write!(f, "{}: {} of {} rows rejected, more than allowed by ", table, rejected, total)?;
+                match limit {
+                    RejectLimit::Count(count) => write!(f, "--max-rejects {}", count),
+                    RejectLimit::Percent(percent) => write!(f, "--max-rejects {}%", percent),
+                    RejectLimit::Unlimited => write!(f, "the reject limit"),
+                }
+            }
+            ImportError::Unknown(ref source, lookup, rows, ref examples) => {
+                write!(f, "{}: {} rows with values not in {}, e.g. {}", source, rows, lookup, examples)
+            }
+        }
+    }
+}
+
+impl From<postgres::Error> for ImportError {
+    fn from(error: postgres::Error) -> ImportError {
+        ImportError::Database(error)
+    }
+}
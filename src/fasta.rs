@@ -0,0 +1,225 @@
+//! Reads FASTA files, e.g. the UniProt `uniprot_sprot.fasta.gz` of a
+//! release, as the export of SEQUENCE.
+//!
+//! The records are converted to CSV while they are streamed into COPY, one
+//! record at a time, so that a release never has to be flattened into
+//! `sequences.csv` by hand and is never held in memory as a whole. UniProt
+//! headers such as
+//!
+//! ```text
+//! >sp|P04637|P53_HUMAN Cellular tumor antigen p53 OS=Homo sapiens OX=9606 GN=TP53 PE=1 SV=4
+//! ```
+//!
+//! give the accession as `ID`, and optionally the entry name, organism and
+//! sequence version. For other headers the first word is the `ID`.
+
+use std::io;
+use std::io::{BufRead, Read};
+
+use csv::Dialect;
+use schema::{Column, ColumnType, Table};
+
+/// Columns that can be filled from the headers in addition to ID and SEQ.
+pub const HEADER_COLUMNS: [Column; 3] = [
+    Column {
+        name: "ENTRY_NAME",
+        kind: ColumnType::Varchar(50),
+        not_null: false,
+    },
+    Column {
+        name: "ORGANISM",
+        kind: ColumnType::Varchar(200),
+        not_null: false,
+    },
+    Column {
+        name: "SEQUENCE_VERSION",
+        kind: ColumnType::BigInt,
+        not_null: false,
+    },
+];
+
+const EXTENSIONS: [&str; 3] = [".fasta", ".fa", ".faa"];
+
+/// Whether an export is a FASTA file, judged by its extension. `.gz` files
+/// are decompressed.
+pub fn is_fasta(path: &str) -> bool {
+    let path = path.to_lowercase();
+    let path = path.strip_suffix(".gz").unwrap_or(&path);
+    EXTENSIONS.iter().any(|extension| path.ends_with(extension))
+}
+
+pub fn is_gzip(path: &str) -> bool {
+    path.to_lowercase().ends_with(".gz")
+}
+
+/// The dialect of the CSV the records are converted to.
+pub fn dialect() -> Dialect {
+    Dialect {
+        header: false,
+        ..Dialect::default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Header {
+    pub id: String,
+    pub entry_name: Option<String>,
+    pub organism: Option<String>,
+    pub version: Option<u64>,
+}
+
+/// Parses a header line without the leading `>`.
+pub fn parse_header(line: &str) -> Header {
+    let line = line.trim();
+    let (identifier, description) = match line.find(char::is_whitespace) {
+        Some(space) => (&line[..space], line[space..].trim_start()),
+        None => (line, ""),
+    };
+    let mut header = Header::default();
+    let parts: Vec<&str> = identifier.split('|').collect();
+    match parts.as_slice() {
+        // sp|P04637|P53_HUMAN, tr|A0A024R161|A0A024R161_HUMAN
+        [database, accession, name] if database.len() == 2 => {
+            header.id = accession.to_string();
+            header.entry_name = Some(name.to_string()).filter(|name| !name.is_empty());
+        }
+        _ => header.id = identifier.to_string(),
+    }
+    header.organism = field(description, "OS");
+    header.version = field(description, "SV").and_then(|version| version.parse().ok());
+    header
+}
+
+/// The value of a `KEY=value` field of a UniProt description. Values run
+/// up to the next field, e.g. `OS=Homo sapiens OX=9606`.
+fn field(description: &str, key: &str) -> Option<String> {
+    let marker = format!("{}=", key);
+    let start = description
+        .match_indices(&marker)
+        .map(|(index, _)| index)
+        .find(|&index| index == 0 || description[..index].ends_with(' '))?
+        + marker.len();
+    let rest = &description[start..];
+    let end = rest
+        .match_indices(' ')
+        .map(|(index, _)| index)
+        .find(|&index| is_field_start(&rest[index + 1..]))
+        .unwrap_or(rest.len());
+    Some(rest[..end].trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Whether text starts with the key of a UniProt field, e.g. `OX=`.
+fn is_field_start(text: &str) -> bool {
+    let key = text.split('=').next().unwrap_or("");
+    text.len() > key.len() && key.len() == 2 && key.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Converts FASTA records into CSV rows with the columns of `table`, one
+/// record at a time.
+pub struct Records<R> {
+    input: R,
+    table: &'static Table,
+    /// Header of the next record, already read.
+    next_header: Option<String>,
+    /// Number of the line read last.
+    line: u64,
+    row: Vec<u8>,
+    position: usize,
+    pub records: u64,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(input: R, table: &'static Table) -> Records<R> {
+        Records {
+            input,
+            table,
+            next_header: None,
+            line: 0,
+            row: Vec::new(),
+            position: 0,
+            records: 0,
+        }
+    }
+
+    fn read_line(&mut self, line: &mut String) -> io::Result<usize> {
+        line.clear();
+        let count = self.input.read_line(line)?;
+        if count > 0 {
+            self.line += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads the next record into `row`. Returns false at the end.
+    fn next_record(&mut self) -> io::Result<bool> {
+        let mut line = String::new();
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => loop {
+                if self.read_line(&mut line)? == 0 {
+                    return Ok(false);
+                }
+                if let Some(header) = line.strip_prefix('>') {
+                    break header.to_string();
+                }
+                if !line.trim().is_empty() {
+                    return Err(invalid(self.line, "sequence data before the first header"));
+                }
+            },
+        };
+        let header_line = self.line;
+        let mut sequence = String::new();
+        while self.read_line(&mut line)? > 0 {
+            if let Some(next) = line.strip_prefix('>') {
+                self.next_header = Some(next.to_string());
+                break;
+            }
+            sequence.extend(line.chars().filter(|c| !c.is_whitespace() && *c != '*'));
+        }
+
+        let header = parse_header(&header);
+        if header.id.is_empty() {
+            return Err(invalid(header_line, "header without an identifier"));
+        }
+        self.row.clear();
+        self.position = 0;
+        for (index, column) in self.table.columns.iter().enumerate() {
+            if index > 0 {
+                self.row.push(b',');
+            }
+            let value = match column.name {
+                "ID" => Some(header.id.clone()),
+                "SEQ" => Some(sequence.clone()),
+                "ENTRY_NAME" => header.entry_name.clone(),
+                "ORGANISM" => header.organism.clone(),
+                "SEQUENCE_VERSION" => header.version.map(|version| version.to_string()),
+                _ => None,
+            };
+            // Unquoted empty fields are NULL in the dialect of the rows.
+            if let Some(value) = value {
+                self.row.push(b'"');
+                self.row.extend_from_slice(value.replace('"', "\"\"").as_bytes());
+                self.row.push(b'"');
+            }
+        }
+        self.row.push(b'\n');
+        self.records += 1;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Records<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.row.len() && !self.next_record()? {
+            return Ok(0);
+        }
+        let count = buf.len().min(self.row.len() - self.position);
+        buf[..count].copy_from_slice(&self.row[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn invalid(line: u64, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
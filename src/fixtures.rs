@@ -0,0 +1,614 @@
+//! Synthetic iPTMnet exports for load testing.
+//!
+//! `generate` writes the five CSV files the importer reads, filled with
+//! made-up but mutually consistent data: every event substrate and enzyme is
+//! a generated entry, every PTM position lies inside the substrate's
+//! generated sequence and carries the residue found there, and eFIP and
+//! proteoform rows point back at generated events and PRO entries. The same
+//! seed always produces byte-identical files.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub struct FixtureSizes {
+    pub entries: usize,
+    pub events: usize,
+    pub efip: usize,
+    pub proteo: usize,
+}
+
+const ENTRY_HEADER: &str = "IPTM_ENTRY_ID,IPTM_ENTRY_CODE,IPTM_ENTRY_TYPE,IPTM_ENTRY_SYMBOL,UNIPROT_ID,PROTEIN_NAME,GENE_NAME,PROTEIN_SYNONYMS,GENE_SYNONYMS,DEFINITION,CATEGORY,IS_REVIEWED,TAXON_CODE,TAXON_SPECIES,TAXON_COMMON,NOTE,SITES,XREF,NUM_ENZYME,NUM_SUBSTRATE,NUM_PPI,NUM_SITE,NUM_FORM,ROLE_AS_ENZYME,ROLE_AS_SUBSTRATE,ROLE_AS_PPI,WEIGHT,LIST_AS_SUBSTRATE,LIST_AS_ENZYME,HAS_OVERLAP_PTM,PROTEIN_SYN,GENE_SYN";
+
+const EVENT_HEADER: &str = "IPTM_EVENT_ID,SUB_FORM_CODE,SUB_CODE,SUB_TYPE,SUB_UNIPROT_ID,SUB_SYMBOL,SUB_TAXON_CODE,SUB_TAXON_COMMON,SUB_SITES,SUB_XREF,ENZ_FORM_CODE,ENZ_CODE,ENZ_TYPE,ENZ_UNIPROT_ID,ENZ_SYMBOL,ENZ_TAXON_CODE,ENZ_TAXON_COMMON,ENZ_SITES,ENZ_XREF,EVENT_NAME,EVENT_LABEL,SOURCE_LABEL,IS_AUTO_GENERATED,RESIDUE,POSITION,MODIFIER,NOTE,PMIDS,NUM_SUBSTRATES";
+
+const EFIP_HEADER: &str = "PPI_EVENT_ID,PTM_EVENT_ID,IMPACT,PPI_SUB_CODE,PPI_SUB_TYPE,PPI_SUB_SYMBOL,PPI_SUB_TAXON_CODE,PPI_SUB_TAXON_COMMON,PPI_SUB_SITES,PPI_PR_CODE,PPI_PR_TYPE,PPI_PR_SYMBOL,PPI_PR_TAXON_CODE,PPI_PR_TAXON_COMMON,PPI_SOURCE_LABEL,PPI_NOTE,PPI_PMIDS,PTM_SUB_CODE,PTM_SUB_TYPE,PTM_SUB_SYMBOL,PTM_SUB_TAXON_CODE,PTM_SUB_TAXON_COMMON,PTM_SUB_SITES,PTM_ENZ_CODE,PTM_ENZ_TYPE,PTM_ENZ_SYMBOL,PTM_ENZ_TAXON_CODE,PTM_ENZ_TAXON_COMMON,PTM_EVENT_NAME,PTM_EVENT_LABEL,PTM_RESIDUE,PTM_POSITION,PTM_SOURCE_LABEL,PTM_NOTE,PTM_PMIDS";
+
+const PROTEO_HEADER: &str = "SUB_CODE,SUB_TYPE,SUB_SYMBOL,SUB_SITES,SUB_XREF,ENZ_CODE,ENZ_TYPE,ENZ_SYMBOL,ENZ_SITES,ENZ_XREF,SITES,EVENT_NAME,EVENT_LABEL,SOURCE_LABEL,IS_AUTO_GENERATED,MODIFIER,PMIDS";
+
+const SEQUENCE_HEADER: &str = "ID,SEQ";
+
+// (taxon code, species, common name)
+const TAXA: [(&str, &str, &str); 5] = [
+    ("9606", "Homo sapiens", "Human"),
+    ("10090", "Mus musculus", "Mouse"),
+    ("10116", "Rattus norvegicus", "Rat"),
+    ("559292", "Saccharomyces cerevisiae", "Yeast"),
+    ("3702", "Arabidopsis thaliana", "Mouse-ear cress"),
+];
+
+// (event name, event label, residues that can carry the modification)
+const PTM_TYPES: [(&str, &str, &str); 8] = [
+    ("Phosphorylation", "p", "STY"),
+    ("Acetylation", "ac", "K"),
+    ("Methylation", "me", "KR"),
+    ("Ubiquitination", "ub", "K"),
+    ("Sumoylation", "sm", "K"),
+    ("N-Glycosylation", "gl", "N"),
+    ("S-Nitrosylation", "sn", "C"),
+    ("Myristoylation", "my", "G"),
+];
+
+const SOURCES: [&str; 6] = ["PSP", "HPRD", "UniProt", "PRO", "RLIMS-P", "neXtProt"];
+
+const IMPACTS: [&str; 3] = ["increased", "decreased", "altered"];
+
+const NAME_WORDS: [&str; 16] = [
+    "Serine", "threonine", "kinase", "receptor", "Tyrosine", "phosphatase", "Histone", "deacetylase",
+    "Cyclin", "dependent", "Ubiquitin", "ligase", "Transcription", "factor", "Zinc", "finger",
+];
+
+// Residues weighted roughly by their frequency in UniProtKB.
+const AMINO_ACIDS: &[u8] = b"AAAAAAAARRRRRNNNNDDDDDCCQQQQEEEEEEEGGGGGGGHHHIIIIILLLLLLLLLKKKKKKMMFFFFPPPPPSSSSSSSTTTTTWYYYVVVVVV";
+
+/// Small, dependency free PRNG (SplitMix64). Good enough for test data and
+/// stable across releases, which keeps seeded output reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn range(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high - low + 1)
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+struct Entry {
+    code: String,
+    is_pro: bool,
+    uniprot_id: String,
+    symbol: String,
+    gene: String,
+    protein_name: String,
+    taxon: usize,
+    // index of the sequence of the entry (itself, or the parent of a PRO entry)
+    sequence: usize,
+    // the UniProt entry a PRO entry is a form of
+    parent: Option<usize>,
+    as_substrate: u64,
+    as_enzyme: u64,
+    as_ppi: u64,
+    sites: Vec<String>,
+}
+
+struct Event {
+    id: u64,
+    substrate: usize,
+    enzyme: Option<usize>,
+    ptm: usize,
+    residue: u8,
+    position: usize,
+    source: usize,
+    pmids: String,
+}
+
+pub fn generate(out_dir: &Path, sizes: &FixtureSizes, seed: u64) -> io::Result<()> {
+    if sizes.entries == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "at least one entry is required"));
+    }
+
+    let mut rng = Rng(seed);
+    let (mut entries, sequences) = generate_entries(&mut rng, sizes.entries);
+
+    let events = generate_events(&mut rng, &mut entries, &sequences, sizes.events);
+    write_events(&out_dir.join("mv_event_export.csv"), &entries, &events)?;
+    write_efip(&out_dir.join("mv_efip_export.csv"), &mut rng, &mut entries, &events, sizes.efip)?;
+    write_proteo(&out_dir.join("mv_proteo_export.csv"), &mut rng, &entries, &events, sizes.proteo)?;
+    write_entries(&out_dir.join("mv_entry_export.csv"), &mut rng, &entries)?;
+    write_sequences(&out_dir.join("sequences.csv"), &entries, &sequences)?;
+
+    Ok(())
+}
+
+fn generate_entries(rng: &mut Rng, count: usize) -> (Vec<Entry>, Vec<Vec<u8>>) {
+    let mut entries: Vec<Entry> = Vec::with_capacity(count);
+    let mut sequences = Vec::new();
+
+    for index in 0..count {
+        // Roughly one in six entries is a PRO proteoform of an earlier
+        // UniProt entry; the first entry is always a UniProt entry.
+        if index > 0 && rng.chance(15) {
+            let parent = loop {
+                let candidate = rng.below(index);
+                if !entries[candidate].is_pro {
+                    break candidate;
+                }
+            };
+            let entry = Entry {
+                code: format!("PR:{:09}", 100_000 + index),
+                is_pro: true,
+                uniprot_id: format!("{}-1", entries[parent].uniprot_id),
+                symbol: format!("{}/iso:1/Phos:1", entries[parent].gene),
+                gene: entries[parent].gene.clone(),
+                protein_name: format!("{} isoform 1", entries[parent].protein_name),
+                taxon: entries[parent].taxon,
+                sequence: entries[parent].sequence,
+                parent: Some(parent),
+                as_substrate: 0,
+                as_enzyme: 0,
+                as_ppi: 0,
+                sites: Vec::new(),
+            };
+            entries.push(entry);
+            continue;
+        }
+
+        let accession = uniprot_accession(index);
+        let gene = gene_name(rng);
+        let length = rng.range(50, 1200);
+        let mut sequence = Vec::with_capacity(length);
+        sequence.push(b'M');
+        for _ in 1..length {
+            sequence.push(*rng.pick(AMINO_ACIDS));
+        }
+        sequences.push(sequence);
+
+        let entry = Entry {
+            code: accession.clone(),
+            is_pro: false,
+            uniprot_id: accession,
+            symbol: gene.clone(),
+            protein_name: protein_name(rng),
+            gene,
+            taxon: rng.below(TAXA.len()),
+            sequence: sequences.len() - 1,
+            parent: None,
+            as_substrate: 0,
+            as_enzyme: 0,
+            as_ppi: 0,
+            sites: Vec::new(),
+        };
+        entries.push(entry);
+    }
+
+    (entries, sequences)
+}
+
+fn generate_events(rng: &mut Rng, entries: &mut [Entry], sequences: &[Vec<u8>], count: usize) -> Vec<Event> {
+    let mut events = Vec::with_capacity(count);
+
+    while events.len() < count {
+        let substrate = rng.below(entries.len());
+        let sequence = &sequences[entries[substrate].sequence];
+        let position = rng.range(1, sequence.len());
+        let residue = sequence[position - 1];
+
+        // Only keep positions whose residue can carry some modification.
+        let candidates: Vec<usize> = (0..PTM_TYPES.len())
+            .filter(|&ptm| PTM_TYPES[ptm].2.as_bytes().contains(&residue))
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        let ptm = *rng.pick(&candidates);
+
+        let enzyme = if rng.chance(60) {
+            Some(rng.below(entries.len()))
+        } else {
+            None
+        };
+
+        let site = format!("{}-{}{}", PTM_TYPES[ptm].1, residue as char, position);
+        if !entries[substrate].sites.contains(&site) {
+            entries[substrate].sites.push(site);
+        }
+        entries[substrate].as_substrate += 1;
+        if let Some(enzyme) = enzyme {
+            entries[enzyme].as_enzyme += 1;
+        }
+
+        events.push(Event {
+            id: events.len() as u64 + 1,
+            substrate,
+            enzyme,
+            ptm,
+            residue,
+            position,
+            source: rng.below(SOURCES.len()),
+            pmids: pmids(rng),
+        });
+    }
+
+    events
+}
+
+fn write_entries(path: &Path, rng: &mut Rng, entries: &[Entry]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", ENTRY_HEADER)?;
+
+    let mut forms = vec![0; entries.len()];
+    for entry in entries {
+        if let Some(parent) = entry.parent {
+            forms[parent] += 1;
+        }
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        let taxon = TAXA[entry.taxon];
+        let (entry_type, category) = if entry.is_pro {
+            ("pro", "organism-gene")
+        } else {
+            ("uniprot", "gene")
+        };
+        let definition = if entry.is_pro {
+            format!("A form of {} that has been phosphorylated.", entry.gene)
+        } else {
+            String::new()
+        };
+
+        write_record(
+            &mut writer,
+            &[
+                &(index + 1).to_string(),
+                &entry.code,
+                entry_type,
+                &entry.symbol,
+                &entry.uniprot_id,
+                &entry.protein_name,
+                &entry.gene,
+                &format!("{} homolog", entry.protein_name),
+                &format!("{}L", entry.gene),
+                &definition,
+                category,
+                flag(!entry.is_pro),
+                taxon.0,
+                taxon.1,
+                taxon.2,
+                "",
+                &entry.sites.join(", "),
+                "",
+                &entry.as_enzyme.to_string(),
+                &entry.as_substrate.to_string(),
+                &entry.as_ppi.to_string(),
+                &entry.sites.len().to_string(),
+                &forms[index].to_string(),
+                flag(entry.as_enzyme > 0),
+                flag(entry.as_substrate > 0),
+                flag(entry.as_ppi > 0),
+                &(entry.as_enzyme + entry.as_substrate + entry.as_ppi + rng.below(10) as u64).to_string(),
+                flag(entry.as_substrate > 0),
+                flag(entry.as_enzyme > 0),
+                flag(entry.sites.len() > 1 && rng.chance(20)),
+                &format!("{} homolog", entry.protein_name),
+                &format!("{}L", entry.gene),
+            ],
+        )?;
+    }
+
+    writer.flush()
+}
+
+fn write_events(path: &Path, entries: &[Entry], events: &[Event]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", EVENT_HEADER)?;
+
+    for event in events {
+        let (sub_form, sub) = form_and_entry(entries, event.substrate);
+        let substrate = &entries[sub];
+        let sub_taxon = TAXA[substrate.taxon];
+        let (name, label, _) = PTM_TYPES[event.ptm];
+        let site = format!("{}-{}{}", label, event.residue as char, event.position);
+
+        let mut record: Vec<String> = vec![
+            event.id.to_string(),
+            sub_form,
+            substrate.code.clone(),
+            "uniprot".to_string(),
+            substrate.uniprot_id.clone(),
+            substrate.symbol.clone(),
+            sub_taxon.0.to_string(),
+            sub_taxon.2.to_string(),
+            site,
+            String::new(),
+        ];
+        match event.enzyme {
+            Some(enzyme) => {
+                let (enz_form, enz) = form_and_entry(entries, enzyme);
+                let enzyme = &entries[enz];
+                let enz_taxon = TAXA[enzyme.taxon];
+                record.extend(vec![
+                    enz_form,
+                    enzyme.code.clone(),
+                    "uniprot".to_string(),
+                    enzyme.uniprot_id.clone(),
+                    enzyme.symbol.clone(),
+                    enz_taxon.0.to_string(),
+                    enz_taxon.2.to_string(),
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+            None => record.extend(vec![String::new(); 9]),
+        }
+        record.extend(vec![
+            name.to_string(),
+            label.to_string(),
+            SOURCES[event.source].to_string(),
+            flag(event.source == 4).to_string(),
+            (event.residue as char).to_string(),
+            event.position.to_string(),
+            String::new(),
+            String::new(),
+            event.pmids.clone(),
+            String::new(),
+        ]);
+
+        let fields: Vec<&str> = record.iter().map(|field| field.as_str()).collect();
+        write_record(&mut writer, &fields)?;
+    }
+
+    writer.flush()
+}
+
+fn write_efip(path: &Path, rng: &mut Rng, entries: &mut [Entry], events: &[Event], count: usize) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", EFIP_HEADER)?;
+
+    if events.is_empty() {
+        return writer.flush();
+    }
+
+    for index in 0..count {
+        let event = rng.pick(events);
+        let partner = rng.below(entries.len());
+        entries[partner].as_ppi += 1;
+        entries[event.substrate].as_ppi += 1;
+
+        let substrate = &entries[event.substrate];
+        let partner = &entries[partner];
+        let sub_taxon = TAXA[substrate.taxon];
+        let partner_taxon = TAXA[partner.taxon];
+        let (name, label, _) = PTM_TYPES[event.ptm];
+        let site = format!("{}-{}{}", label, event.residue as char, event.position);
+        let (enz_code, enz_symbol, enz_taxon_code, enz_taxon_common) = match event.enzyme {
+            Some(enzyme) => {
+                let enzyme = &entries[enzyme];
+                let taxon = TAXA[enzyme.taxon];
+                (enzyme.code.as_str(), enzyme.symbol.as_str(), taxon.0, taxon.2)
+            }
+            None => ("", "", "", ""),
+        };
+        let substrate_type = if substrate.is_pro { "pro" } else { "uniprot" };
+        let partner_type = if partner.is_pro { "pro" } else { "uniprot" };
+        let enzyme_type = if event.enzyme.is_some() { "uniprot" } else { "" };
+
+        write_record(
+            &mut writer,
+            &[
+                &(events.len() + index + 1).to_string(),
+                &event.id.to_string(),
+                *rng.pick(&IMPACTS),
+                &substrate.code,
+                substrate_type,
+                &substrate.symbol,
+                sub_taxon.0,
+                sub_taxon.2,
+                &site,
+                &partner.code,
+                partner_type,
+                &partner.symbol,
+                partner_taxon.0,
+                partner_taxon.2,
+                "eFIP",
+                "",
+                &pmids(rng),
+                &substrate.code,
+                substrate_type,
+                &substrate.symbol,
+                sub_taxon.0,
+                sub_taxon.2,
+                &site,
+                enz_code,
+                enzyme_type,
+                enz_symbol,
+                enz_taxon_code,
+                enz_taxon_common,
+                name,
+                label,
+                &(event.residue as char).to_string(),
+                &event.position.to_string(),
+                SOURCES[event.source],
+                "",
+                &event.pmids,
+            ],
+        )?;
+    }
+
+    writer.flush()
+}
+
+fn write_proteo(path: &Path, rng: &mut Rng, entries: &[Entry], events: &[Event], count: usize) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", PROTEO_HEADER)?;
+
+    // Proteoform rows describe PRO entries, so only events on a protein
+    // that has a PRO form are usable.
+    let mut form_of = vec![None; entries.len()];
+    for (index, entry) in entries.iter().enumerate() {
+        if let Some(parent) = entry.parent {
+            form_of[parent] = Some(index);
+            form_of[index] = Some(index);
+        }
+    }
+    let candidates: Vec<(&Event, usize)> = events
+        .iter()
+        .filter_map(|event| form_of[event.substrate].map(|form| (event, form)))
+        .collect();
+    if candidates.is_empty() {
+        if count > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} proteoform rows requested but 0 written: none of the {} events is on an entry with a PRO form, \
+                     generate more entries or events",
+                    count,
+                    events.len()
+                ),
+            ));
+        }
+        return writer.flush();
+    }
+
+    for _ in 0..count {
+        let (event, form) = *rng.pick(&candidates);
+        let form = &entries[form];
+        let (name, label, _) = PTM_TYPES[event.ptm];
+        let site = format!("{}-{}{}", label, event.residue as char, event.position);
+        let (enz_code, enz_type, enz_symbol) = match event.enzyme {
+            Some(enzyme) => (entries[enzyme].code.as_str(), "uniprot", entries[enzyme].symbol.as_str()),
+            None => ("", "", ""),
+        };
+
+        write_record(
+            &mut writer,
+            &[
+                &form.code,
+                "pro",
+                &form.symbol,
+                &site,
+                "",
+                enz_code,
+                enz_type,
+                enz_symbol,
+                "",
+                "",
+                &site,
+                name,
+                label,
+                "PRO",
+                flag(false),
+                "",
+                &event.pmids,
+            ],
+        )?;
+    }
+
+    writer.flush()
+}
+
+fn write_sequences(path: &Path, entries: &[Entry], sequences: &[Vec<u8>]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", SEQUENCE_HEADER)?;
+
+    for entry in entries.iter().filter(|entry| !entry.is_pro) {
+        writer.write_all(entry.uniprot_id.as_bytes())?;
+        writer.write_all(b",")?;
+        writer.write_all(&sequences[entry.sequence])?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+/// Events are always recorded against the UniProt entry; when the substrate
+/// drawn is a PRO entry it becomes the form code and the parent the entry.
+fn form_and_entry(entries: &[Entry], index: usize) -> (String, usize) {
+    match entries[index].parent {
+        Some(parent) => (entries[index].code.clone(), parent),
+        None => (String::new(), index),
+    }
+}
+
+/// Builds a well-formed, unique accession of the `[OPQ][0-9][A-Z0-9]{3}[0-9]`
+/// family from a running number.
+fn uniprot_accession(index: usize) -> String {
+    const ALNUM: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let prefix = [b'O', b'P', b'Q'][index % 3] as char;
+    let mut rest = index / 3;
+    let first_digit = rest % 10;
+    rest /= 10;
+    let mut middle = String::new();
+    for _ in 0..3 {
+        middle.push(ALNUM[rest % ALNUM.len()] as char);
+        rest /= ALNUM.len();
+    }
+    format!("{}{}{}{}", prefix, first_digit, middle, rest % 10)
+}
+
+fn gene_name(rng: &mut Rng) -> String {
+    const LETTERS: &[u8] = b"ABCDEFGHIKLMNPRSTVWXYZ";
+    let mut name = String::new();
+    for _ in 0..rng.range(2, 4) {
+        name.push(*rng.pick(LETTERS) as char);
+    }
+    format!("{}{}", name, rng.range(1, 99))
+}
+
+fn protein_name(rng: &mut Rng) -> String {
+    format!(
+        "{} {} {} {}",
+        rng.pick(&NAME_WORDS),
+        rng.pick(&NAME_WORDS).to_lowercase(),
+        rng.pick(&NAME_WORDS).to_lowercase(),
+        rng.range(1, 20)
+    )
+}
+
+fn pmids(rng: &mut Rng) -> String {
+    let count = rng.range(0, 3);
+    let ids: Vec<String> = (0..count).map(|_| rng.range(1_000_000, 35_000_000).to_string()).collect();
+    ids.join(",")
+}
+
+fn flag(value: bool) -> &'static str {
+    if value {
+        "Y"
+    } else {
+        "N"
+    }
+}
+
+/// Writes one CSV record, quoting fields the way Postgres' CSV COPY expects.
+/// Empty fields are left unquoted so that they load as NULL.
+fn write_record<W: Write>(writer: &mut W, fields: &[&str]) -> io::Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        if field.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
@@ -0,0 +1,164 @@
+//! Checks the format of the protein and taxon identifiers once they are
+//! loaded.
+//!
+//! The code columns take whatever the exports contain, so a typo such as
+//! `P0463` or a stray space in ` P04637` ends up in the API. Every checked
+//! column has the formats it may hold, UniProt accessions, Protein Ontology
+//! IDs or NCBI taxon IDs, and the values in none of them are counted and
+//! reported per column. The import goes ahead either way.
+
+use postgres::Connection;
+
+use error::ImportError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// E.g. `P04637`, `A0A024R161` or the isoform `P31749-2`.
+    UniProt,
+    /// E.g. `PR:000003090`, or `PR:P04637` for a UniProt-based term.
+    Pro,
+    /// E.g. `9606`.
+    Taxon,
+}
+
+const ACCESSION: &str = "([OPQ][0-9][A-Z0-9]{3}[0-9]|[A-NR-Z][0-9]([A-Z][A-Z0-9]{2}[0-9]){1,2})(-[0-9]+)?";
+
+impl Format {
+    /// The format as a regular expression, not anchored.
+    pub fn pattern(self) -> String {
+        match self {
+            Format::UniProt => ACCESSION.to_string(),
+            Format::Pro => format!("PR:([0-9]{{9}}|{})", ACCESSION),
+            Format::Taxon => "[1-9][0-9]{0,9}".to_string(),
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Format::UniProt => "a UniProt accession",
+            Format::Pro => "a PRO ID",
+            Format::Taxon => "an NCBI taxon ID",
+        }
+    }
+}
+
+/// A column holding identifiers.
+pub struct Identifiers {
+    pub table: &'static str,
+    /// Column of the row id quoted with invalid values, `None` for tables
+    /// without one.
+    pub id: Option<&'static str>,
+    pub column: &'static str,
+    pub formats: &'static [Format],
+}
+
+const PROTEIN: &[Format] = &[Format::UniProt, Format::Pro];
+const UNIPROT: &[Format] = &[Format::UniProt];
+const PRO: &[Format] = &[Format::Pro];
+const TAXON: &[Format] = &[Format::Taxon];
+
+const fn identifiers(table: &'static str, id: Option<&'static str>, column: &'static str, formats: &'static [Format]) -> Identifiers {
+    Identifiers {
+        table,
+        id,
+        column,
+        formats,
+    }
+}
+
+const ENTRY: Option<&str> = Some("IPTM_ENTRY_ID");
+const EVENT: Option<&str> = Some("IPTM_EVENT_ID");
+const PPI: Option<&str> = Some("PPI_EVENT_ID");
+const PTM: Option<&str> = Some("PTM_EVENT_ID");
+
+pub const COLUMNS: [Identifiers; 21] = [
+    identifiers("MV_ENTRY", ENTRY, "IPTM_ENTRY_CODE", PROTEIN),
+    identifiers("MV_ENTRY", ENTRY, "UNIPROT_ID", UNIPROT),
+    identifiers("MV_ENTRY", ENTRY, "TAXON_CODE", TAXON),
+    identifiers("MV_EVENT", EVENT, "SUB_FORM_CODE", PRO),
+    identifiers("MV_EVENT", EVENT, "SUB_CODE", PROTEIN),
+    identifiers("MV_EVENT", EVENT, "SUB_UNIPROT_ID", UNIPROT),
+    identifiers("MV_EVENT", EVENT, "SUB_TAXON_CODE", TAXON),
+    identifiers("MV_EVENT", EVENT, "ENZ_FORM_CODE", PRO),
+    identifiers("MV_EVENT", EVENT, "ENZ_CODE", PROTEIN),
+    identifiers("MV_EVENT", EVENT, "ENZ_UNIPROT_ID", UNIPROT),
+    identifiers("MV_EVENT", EVENT, "ENZ_TAXON_CODE", TAXON),
+    identifiers("MV_EFIP", PPI, "PPI_SUB_CODE", PROTEIN),
+    identifiers("MV_EFIP", PPI, "PPI_SUB_TAXON_CODE", TAXON),
+    identifiers("MV_EFIP", PPI, "PPI_PR_CODE", PROTEIN),
+    identifiers("MV_EFIP", PPI, "PPI_PR_TAXON_CODE", TAXON),
+    identifiers("MV_EFIP", PTM, "PTM_SUB_CODE", PROTEIN),
+    identifiers("MV_EFIP", PTM, "PTM_SUB_TAXON_CODE", TAXON),
+    identifiers("MV_EFIP", PTM, "PTM_ENZ_CODE", PROTEIN),
+    identifiers("MV_EFIP", PTM, "PTM_ENZ_TAXON_CODE", TAXON),
+    identifiers("MV_PROTEO", None, "SUB_CODE", PROTEIN),
+    identifiers("MV_PROTEO", None, "ENZ_CODE", PROTEIN),
+];
+
+/// Number of invalid values quoted in the warning.
+const EXAMPLES: i64 = 5;
+
+pub struct Validated {
+    /// Non-NULL values checked.
+    pub values: u64,
+    pub invalid: u64,
+    /// Some of the invalid values, e.g. `" P04637" (IPTM_EVENT_ID 105)`.
+    pub examples: Vec<String>,
+}
+
+impl Identifiers {
+    /// `TABLE.COLUMN`.
+    pub fn source(&self) -> String {
+        format!("{}.{}", self.table, self.column)
+    }
+
+    /// The formats of the column, e.g. `a UniProt accession or a PRO ID`.
+    pub fn describe(&self) -> String {
+        let names: Vec<&str> = self.formats.iter().map(|format| format.describe()).collect();
+        names.join(" or ")
+    }
+
+    /// Anchored regular expression matching any of the formats.
+    pub fn pattern(&self) -> String {
+        let patterns: Vec<String> = self.formats.iter().map(|format| format.pattern()).collect();
+        format!("^({})$", patterns.join("|"))
+    }
+
+    /// Counts the values of the column and those in none of its formats.
+    pub fn validate(&self, conn: &Connection) -> Result<Validated, ImportError> {
+        let pattern = self.pattern();
+        let rows = conn.query(
+            &format!(
+                "SELECT count({0}), count(*) FILTER (WHERE {0} !~ $1) FROM {1}",
+                self.column, self.table
+            ),
+            &[&pattern],
+        )?;
+        let (values, invalid): (i64, i64) = (rows.get(0).get(0), rows.get(0).get(1));
+        let rows = conn.query(
+            &format!(
+                "SELECT {id}, {column} FROM {table} WHERE {column} !~ $1 ORDER BY 1, 2 LIMIT {limit}",
+                id = self.id.unwrap_or("NULL::BIGINT"),
+                column = self.column,
+                table = self.table,
+                limit = EXAMPLES
+            ),
+            &[&pattern],
+        )?;
+        let examples = rows
+            .iter()
+            .map(|row| {
+                let value: String = row.get(1);
+                match (self.id, row.get::<_, Option<i64>>(0)) {
+                    (Some(id), Some(number)) => format!("{:?} ({} {})", value, id, number),
+                    _ => format!("{:?}", value),
+                }
+            })
+            .collect();
+        Ok(Validated {
+            values: values as u64,
+            invalid: invalid as u64,
+            examples,
+        })
+    }
+}
@@ -0,0 +1,198 @@
+//! Infers the definition of a new table from its CSV export, for the
+//! `infer` command, following the conventions of the built-in tables.
+
+use std::io;
+use std::io::BufRead;
+
+use chrono::NaiveDate;
+
+use config::{ColumnConfig, IndexConfig, TableConfig};
+use csv;
+use csv::Dialect;
+use schema::{Column, ColumnType};
+
+/// VARCHAR sizes of the built-in tables.
+const SIZES: [usize; 6] = [10, 25, 50, 100, 200, 4000];
+
+pub struct Definition {
+    pub settings: TableConfig,
+    /// Data records looked at.
+    pub rows: u64,
+    /// Whether every record was looked at, rather than a sample.
+    pub complete: bool,
+}
+
+/// Reads up to `sample` records of an export (all when `None`) and proposes
+/// the columns and indexes of `table`.
+pub fn infer<R: BufRead>(table: &str, input: R, dialect: &Dialect, sample: Option<u64>) -> io::Result<Definition> {
+    let mut reader = csv::Reader::new(input, dialect);
+    let mut header: Vec<String> = Vec::new();
+    if dialect.header {
+        if let Some(record) = reader.read_record()? {
+            header = record
+                .fields
+                .iter()
+                .map(|field| String::from_utf8_lossy(&field.value).into_owned())
+                .collect();
+        }
+    }
+
+    let mut stats: Vec<ColumnStats> = Vec::new();
+    let mut rows = 0;
+    let mut complete = true;
+    while let Some(record) = reader.read_record()? {
+        if sample.is_some_and(|sample| rows >= sample) {
+            complete = false;
+            break;
+        }
+        rows += 1;
+        while stats.len() < record.fields.len() {
+            stats.push(ColumnStats::new());
+        }
+        for (index, field) in record.fields.iter().enumerate() {
+            // FORCE_NULL and FORCE_NOT_NULL name columns as the header does.
+            let name = header.get(index).map_or("", String::as_str);
+            let value = dialect.value(field, name).unwrap_or(None);
+            stats[index].add(value);
+        }
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    let mut columns = Vec::new();
+    let mut indexes = Vec::new();
+    for index in 0..stats.len().max(header.len()) {
+        let name = column_name(header.get(index).map_or("", String::as_str), index);
+        let name = unique_name(name, &names);
+        let kind = stats.get(index).map_or(ColumnType::Text, ColumnStats::kind);
+        if is_key(&name) && kind != ColumnType::Text {
+            indexes.push(IndexConfig {
+                name: format!("{}_{}_idx", table, name).to_lowercase(),
+                columns: vec![name.clone()],
+                ..IndexConfig::default()
+            });
+        }
+        columns.push(ColumnConfig {
+            name: name.clone(),
+            kind: kind.to_string(),
+            not_null: stats.get(index).is_some_and(|stats| stats.nulls == 0 && stats.values > 0),
+        });
+        names.push(name);
+    }
+
+    let rename = |listed: &[String]| -> Vec<String> {
+        listed
+            .iter()
+            .filter_map(|listed| header.iter().position(|name| name == listed))
+            .map(|index| names[index].clone())
+            .collect()
+    };
+    let mut settings = TableConfig::from_dialect(dialect);
+    settings.force_null = rename(&dialect.force_null);
+    settings.force_not_null = rename(&dialect.force_not_null);
+    settings.columns = columns;
+    settings.indexes = indexes;
+    Ok(Definition { settings, rows, complete })
+}
+
+/// Turns a header into an upper case identifier, e.g. `Sub Code` into
+/// `SUB_CODE`. Columns without a usable header are numbered.
+fn column_name(header: &str, index: usize) -> String {
+    let name: String = header
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    let name = name.trim_matches('_').to_string();
+    if name.is_empty() {
+        format!("COLUMN_{}", index + 1)
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name.chars().take(63).collect()
+    }
+}
+
+fn unique_name(name: String, taken: &[String]) -> String {
+    if !taken.contains(&name) {
+        return name;
+    }
+    (2..).map(|suffix| format!("{}_{}", name, suffix)).find(|name| !taken.contains(name)).unwrap()
+}
+
+/// Columns that are looked up by, like SUB_CODE, UNIPROT_ID or EVENT_NAME.
+fn is_key(name: &str) -> bool {
+    ["ID", "CODE", "NAME"]
+        .iter()
+        .any(|suffix| name == *suffix || name.ends_with(&format!("_{}", suffix)))
+}
+
+struct ColumnStats {
+    nulls: u64,
+    values: u64,
+    integers: u64,
+    numbers: u64,
+    dates: u64,
+    min_length: usize,
+    max_length: usize,
+}
+
+impl ColumnStats {
+    fn new() -> ColumnStats {
+        ColumnStats {
+            nulls: 0,
+            values: 0,
+            integers: 0,
+            numbers: 0,
+            dates: 0,
+            min_length: usize::MAX,
+            max_length: 0,
+        }
+    }
+
+    fn add(&mut self, value: Option<&str>) {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.nulls += 1;
+                return;
+            }
+        };
+        self.values += 1;
+        let trimmed = value.trim();
+        if trimmed.parse::<i64>().is_ok() {
+            self.integers += 1;
+        }
+        if trimmed.parse::<f64>().is_ok_and(|number| number.is_finite()) {
+            self.numbers += 1;
+        }
+        if NaiveDate::parse_from_str(trimmed, "%Y-%m-%d").is_ok() {
+            self.dates += 1;
+        }
+        let length = Column::length(value);
+        self.min_length = self.min_length.min(length);
+        self.max_length = self.max_length.max(length);
+    }
+
+    fn kind(&self) -> ColumnType {
+        if self.values == 0 {
+            ColumnType::Text
+        } else if self.integers == self.values {
+            ColumnType::BigInt
+        } else if self.numbers == self.values {
+            ColumnType::Double
+        } else if self.dates == self.values {
+            ColumnType::Date
+        } else if self.min_length == 1 && self.max_length == 1 {
+            // Y/N flags such as IS_REVIEWED or ROLE_AS_ENZYME.
+            ColumnType::Char(1)
+        } else {
+            // A quarter of headroom keeps new columns out of the warnings of
+            // the profile command.
+            let wanted = self.max_length + self.max_length.div_ceil(4);
+            match SIZES.iter().find(|&&size| size >= wanted) {
+                Some(&size) => ColumnType::Varchar(size),
+                None => ColumnType::Text,
+            }
+        }
+    }
+}
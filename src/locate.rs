@@ -0,0 +1,121 @@
+//! Finds the record of an export that made COPY fail.
+//!
+//! Postgres names the failing line in the context of the error, e.g.
+//! `COPY mv_event, line 48213, column position: "abc"`. The line is counted
+//! in physical lines of the data COPY received, header included, so it can
+//! be looked up in the export directly, after accounting for any records the
+//! tolerant mode left out.
+
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+use csv;
+use csv::Dialect;
+use schema::{excerpt, Table};
+
+/// Where in its input a COPY failed, as reported by the server.
+#[derive(Debug, PartialEq)]
+pub struct CopyContext {
+    pub table: String,
+    pub line: u64,
+    pub column: Option<String>,
+}
+
+/// Parses the `where` field of a COPY error.
+pub fn parse_context(context: &str) -> Option<CopyContext> {
+    let rest = context.lines().filter_map(|line| line.strip_prefix("COPY ")).next()?;
+    let comma = rest.find(", line ")?;
+    let table = rest[..comma].to_string();
+    let rest = &rest[comma + ", line ".len()..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let line = rest[..digits].parse().ok()?;
+    let column = rest[digits..].strip_prefix(", column ").map(|rest| match rest.find(':') {
+        Some(colon) => rest[..colon].to_string(),
+        None => rest.to_string(),
+    });
+    Some(CopyContext { table, line, column })
+}
+
+/// Translates a line of the data sent to COPY into a line of the export.
+/// `skipped` holds the start line and length of every record left out, in
+/// order.
+pub fn source_line(line: u64, skipped: &[(u64, u64)]) -> u64 {
+    let mut offset = 0;
+    for &(start, lines) in skipped {
+        if start > line + offset {
+            break;
+        }
+        offset += lines;
+    }
+    line + offset
+}
+
+/// The failing record of an export.
+pub struct Location {
+    pub path: String,
+    pub line: u64,
+    pub column: Option<String>,
+    pub value: Option<String>,
+    pub excerpt: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} line {}", self.path, self.line)?;
+        if let Some(ref column) = self.column {
+            write!(f, ", column {}", column)?;
+        }
+        if let Some(ref value) = self.value {
+            write!(f, " = {:?}", value)?;
+        }
+        write!(f, ": {}", self.excerpt)
+    }
+}
+
+/// Reads the record of the export at `path` that contains physical line `line`. An
+/// unterminated quoted field at the end of the file is reported one line
+/// past its end, so a line beyond the last record means the last record.
+pub fn locate<R: BufRead>(
+    table: &Table,
+    input: R,
+    dialect: &Dialect,
+    path: &str,
+    line: u64,
+    column: Option<&str>,
+) -> io::Result<Option<Location>> {
+    let mut reader = csv::Reader::new(input, dialect);
+    let mut found = None;
+    while let Some(record) = reader.read_record()? {
+        if record.line > line {
+            break;
+        }
+        let contains = record.line + record.lines > line;
+        found = Some(record);
+        if contains {
+            break;
+        }
+    }
+    let record = match found {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+
+    let index = column.and_then(|column| {
+        table
+            .columns
+            .iter()
+            .position(|candidate| candidate.name.eq_ignore_ascii_case(column))
+    });
+    let value = index
+        .and_then(|index| record.fields.get(index))
+        .map(|field| excerpt(&String::from_utf8_lossy(&field.value), 80));
+    let raw = String::from_utf8_lossy(&record.raw);
+    Ok(Some(Location {
+        path: path.to_string(),
+        line: record.line,
+        column: index.map(|index| table.columns[index].name.to_string()).or(column.map(String::from)),
+        value,
+        excerpt: excerpt(raw.trim_end_matches(['\r', '\n']), 200),
+    }))
+}
@@ -0,0 +1,125 @@
+//! Advisory lock that keeps two import runs from working on the same
+//! database and schema at once.
+//!
+//! The lock is a session-level advisory lock, taken before the transaction
+//! begins and released when the connection closes, so a crashed run never
+//! leaves it behind. Its key is derived from the database and schema names,
+//! so that imports into other schemas are not held up.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use postgres::Connection;
+
+use error::ImportError;
+
+/// First half of the lock key, telling the importer's locks apart from
+/// advisory locks taken by other applications.
+pub const LOCK_CLASS: i32 = 0x4950_544D;
+
+/// Shown in pg_stat_activity, so that a waiting run can name the holder.
+const APPLICATION_NAME: &str = "iptmnet_data_importer";
+
+/// How often a waiting run tries to take the lock again.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+const SCOPE: &str = "current_database() || '.' || current_schema()";
+
+/// Takes the import lock, waiting up to `wait` for another run to release
+/// it. Returns the `database.schema` the lock is for.
+pub fn acquire(conn: &Connection, wait: Duration) -> Result<String, ImportError> {
+    conn.execute(&format!("SET application_name = '{}'", APPLICATION_NAME), &[])?;
+    let rows = conn.query(&format!("SELECT {}", SCOPE), &[])?;
+    let scope: String = rows.get(0).get(0);
+
+    let deadline = Instant::now() + wait;
+    loop {
+        let rows = conn.query(
+            &format!("SELECT pg_try_advisory_lock($1, hashtext({}))", SCOPE),
+            &[&LOCK_CLASS],
+        )?;
+        if rows.get(0).get::<_, bool>(0) {
+            return Ok(scope);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            let holder = holder(conn)?;
+            return Err(ImportError::Locked(scope, holder, wait));
+        }
+        debug!("Import lock on {} is taken, waiting...", scope);
+        thread::sleep(RETRY_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Releases the lock before the connection is closed, e.g. after COMMIT.
+pub fn release(conn: &Connection) -> Result<(), ImportError> {
+    conn.execute(
+        &format!("SELECT pg_advisory_unlock($1, hashtext({}))", SCOPE),
+        &[&LOCK_CLASS],
+    )?;
+    Ok(())
+}
+
+/// Describes the session holding the lock.
+fn holder(conn: &Connection) -> Result<Option<String>, ImportError> {
+    let condition = format!(
+        "pid IN (SELECT pid FROM pg_locks WHERE locktype = 'advisory' AND granted AND objsubid = 2 \
+         AND database = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+         AND classid = {}::oid AND objid = hashtext({})::oid)",
+        LOCK_CLASS, SCOPE
+    );
+    // None when it was released between the attempt and the lookup.
+    Ok(sessions(conn, &condition)?.into_iter().next())
+}
+
+/// Describes the sessions in pg_stat_activity matching `condition`, e.g.
+/// `pid 4711 (postgres from 10.0.0.5, iptmnet_api) in a transaction since
+/// 2018-05-02T03:00:01Z, active: "SELECT ..."`.
+pub fn sessions(conn: &Connection, condition: &str) -> Result<Vec<String>, ImportError> {
+    let timestamp = |column: &str| {
+        format!(
+            "to_char({} AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"')",
+            column
+        )
+    };
+    let rows = conn.query(
+        &format!(
+            "SELECT pid, usename::text, application_name, host(client_addr), {}, {}, state, left(query, 100) \
+             FROM pg_stat_activity WHERE {} ORDER BY pid",
+            timestamp("xact_start"),
+            timestamp("backend_start"),
+            condition
+        ),
+        &[],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let pid: i32 = row.get(0);
+            let mut session = Vec::new();
+            if let Some(user) = row.get::<_, Option<String>>(1) {
+                match row.get::<_, Option<String>>(3) {
+                    Some(client) => session.push(format!("{} from {}", user, client)),
+                    None => session.push(user),
+                }
+            }
+            if let Some(application) = row.get::<_, Option<String>>(2).filter(|name| !name.is_empty()) {
+                session.push(application);
+            }
+            let mut description = format!("pid {}", pid);
+            if !session.is_empty() {
+                description.push_str(&format!(" ({})", session.join(", ")));
+            }
+            match (row.get::<_, Option<String>>(4), row.get::<_, Option<String>>(5)) {
+                (Some(since), _) => description.push_str(&format!(" in a transaction since {}", since)),
+                (None, Some(since)) => description.push_str(&format!(" connected since {}", since)),
+                (None, None) => {}
+            }
+            if let Some(state) = row.get::<_, Option<String>>(6).filter(|state| state != "idle") {
+                let query: String = row.get::<_, Option<String>>(7).unwrap_or_default();
+                description.push_str(&format!(", {}: {:?}", state, query.split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            description
+        })
+        .collect())
+}
@@ -0,0 +1,86 @@
+//! Logger setup.
+//!
+//! All output of the importer goes through the `log` macros. `RUST_LOG` is
+//! honoured when it is set; otherwise the level of the importer's own
+//! messages follows the `-v`/`-q` flags. In the JSON format every record is
+//! written as one object per line, with the key-value pairs attached to the
+//! record (table, step, rows, ...) as additional fields.
+
+use std::io;
+use std::io::Write;
+
+use chrono::Utc;
+use env_logger;
+use env_logger::fmt::Formatter;
+use log::kv::{Error, Key, Value, VisitSource};
+use log::{Level, Record};
+use serde_json;
+use serde_json::{Map, Number};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Initialises the global logger. `verbosity` is the number of `-v` flags
+/// minus the number of `-q` flags.
+pub fn init(verbosity: i64, format: LogFormat) {
+    let level = match verbosity {
+        v if v <= -2 => "error",
+        -1 => "warn",
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let default_filter = format!("iptmnet_data_importer={}", level);
+    let env = env_logger::Env::new().filter_or("RUST_LOG", default_filter);
+
+    let mut builder = env_logger::Builder::from_env(env);
+    match format {
+        LogFormat::Text => builder.format(write_text),
+        LogFormat::Json => builder.format(write_json),
+    };
+    builder.init();
+}
+
+fn write_text(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    match record.level() {
+        Level::Info => writeln!(buf, "{}", record.args()),
+        level => writeln!(buf, "{}: {}", level, record.args()),
+    }
+}
+
+fn write_json(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    let mut object = Map::new();
+    object.insert("timestamp".to_string(), Utc::now().to_rfc3339().into());
+    object.insert("level".to_string(), record.level().to_string().into());
+    object.insert("message".to_string(), record.args().to_string().into());
+
+    let mut fields = JsonFields(object);
+    // A failing visitor only loses fields, never the message itself.
+    let _ = record.key_values().visit(&mut fields);
+
+    serde_json::to_writer(&mut *buf, &fields.0).map_err(io::Error::from)?;
+    writeln!(buf)
+}
+
+struct JsonFields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let json = if let Some(number) = value.to_u64() {
+            number.into()
+        } else if let Some(number) = value.to_i64() {
+            number.into()
+        } else if let Some(number) = value.to_f64().and_then(Number::from_f64) {
+            serde_json::Value::Number(number)
+        } else if let Some(flag) = value.to_bool() {
+            flag.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().to_string(), json);
+        Ok(())
+    }
+}
@@ -1,28 +1,99 @@
+extern crate chrono;
 extern crate clap;
 extern crate postgres;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate simplelog;
 extern crate env_logger;
+extern crate flate2;
+extern crate toml;
 use std::io;
-use std::io::Write;
+use std::io::Read;
 
 #[macro_use]
 extern crate log;
 
+mod attributes;
+mod config;
+mod csv;
+mod encoding;
+mod error;
+mod fasta;
+mod fixtures;
+mod identifiers;
+mod infer;
+mod locate;
+mod lock;
+mod logging;
+mod metrics;
+mod ontology;
+mod pmid;
+mod profile;
+mod quarantine;
+mod report;
+mod residues;
+mod schema;
+mod search;
+mod sites;
+mod sniff;
+mod timeouts;
+mod vocabulary;
+mod windows;
+
 use postgres::{Connection, TlsMode};
+use attributes::Computed;
+use config::{Config, OnUnknown, TableConfig, Vacuum};
+use csv::Dialect;
+use encoding::{Encoding, Transcoder};
+use error::ImportError;
+use logging::LogFormat;
+use metrics::MetricsTargets;
+use quarantine::{Quarantine, RejectLimit};
+use report::Report;
+use schema::Table;
+use timeouts::{Blockers, Timeouts};
+use vocabulary::Vocabulary;
+use std::cell::Cell;
 use std::fs::File;
-use clap::{App, Arg};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use flate2::read::MultiGzDecoder;
 
 fn main() {
-    std::env::set_var("RUST_LOG", "iptmnet_data_importer");
-    env_logger::Builder::from_default_env()
-        .default_format_timestamp(false)
-        .default_format_module_path(false)
-        .init();
-
     let matches = App::new("iPTMnet data importer")
         .version("1.0")
         .author("Sachin Gavali. <saching@ude.edu>")
         .about("Imports data from CSV files into postgres database")
+        .arg(
+            Arg::with_name("VERBOSE")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .global(true)
+                .help("Logs more detail, repeat for even more. Ignored when RUST_LOG is set"),
+        )
+        .arg(
+            Arg::with_name("QUIET")
+                .short("q")
+                .long("quiet")
+                .multiple(true)
+                .global(true)
+                .help("Logs only warnings, repeat to log only errors. Ignored when RUST_LOG is set"),
+        )
+        .arg(
+            Arg::with_name("LOG_FORMAT")
+                .long("log-format")
+                .possible_values(&["text", "json"])
+                .global(true)
+                .help("Format of the log output. Default - text")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("HOST")
                 .long("host")
@@ -47,8 +118,254 @@ fn main() {
                 .help("Password of the user that owns iptmnet database. Default - postgres")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("REPORT")
+                .long("report")
+                .help("Writes a JSON report with the timings, row counts and outcome of every step to this file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("METRICS_FILE")
+                .long("metrics-file")
+                .help("Writes Prometheus metrics of the run to this node-exporter textfile collector file (*.prom)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PUSHGATEWAY")
+                .long("pushgateway")
+                .help("Pushes Prometheus metrics of the run to this Pushgateway, e.g. http://localhost:9091")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("LOCK_WAIT")
+                .long("lock-wait")
+                .help("Waits this many seconds for another run to release the import lock. Default - 0, fails at once")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("LOCK_TIMEOUT")
+                .long("lock-timeout")
+                .help("lock_timeout of the import, e.g. 500ms, 10s or 2min; 0 waits forever. Default - 10s")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("STATEMENT_TIMEOUT")
+                .long("statement-timeout")
+                .help("statement_timeout of the import, e.g. 30min; 0 for no limit. Default - 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("DDL_RETRIES")
+                .long("ddl-retries")
+                .help("How often a DROP that hit the lock timeout is retried. Default - 3")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("DDL_BACKOFF")
+                .long("ddl-backoff")
+                .help("Delay before the first retry of a DROP, doubled for every further one. Default - 1s")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("BLOCKERS")
+                .long("blockers")
+                .possible_values(&["ignore", "report", "terminate"])
+                .help("What to do about sessions holding locks a DROP waits for: ignore, report (log them) or terminate them. Default - report")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("CONFIG")
+                .long("config")
+                .help("Reads per-table settings, such as the CSV dialect of each export, from this TOML file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ENCODING")
+                .long("encoding")
+                .help("Encoding of the exports: utf-8, latin1, windows-1252 or auto (UTF-8 where valid, Windows-1252 otherwise). Default - utf-8")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TOLERANT")
+                .long("tolerant")
+                .help("Checks rows before COPY and writes bad ones to <table>.rejects.csv instead of failing the import"),
+        )
+        .arg(
+            Arg::with_name("MAX_REJECTS")
+                .long("max-rejects")
+                .help("In tolerant mode, fails the import when a table has more rejected rows than this count or percentage (e.g. 100 or 0.5%). Default - unlimited")
+                .requires("TOLERANT")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("SEARCH")
+                .long("search")
+                .help("Adds a full-text search column, trigram indexes on the names and synonyms and the search_entries function to MV_ENTRY"),
+        )
+        .arg(
+            Arg::with_name("PMIDS")
+                .long("pmids")
+                .help("Splits the PMID lists of MV_EVENT and MV_EFIP into the EVENT_PMID link table"),
+        )
+        .arg(
+            Arg::with_name("SITES")
+                .long("sites")
+                .help("Parses the site lists of MV_ENTRY, MV_EVENT and MV_PROTEO into the SITE table"),
+        )
+        .arg(
+            Arg::with_name("SITE_WINDOWS")
+                .long("site-windows")
+                .help("Derives the flanking peptide of every modified position of MV_EVENT into the SITE_WINDOW table"),
+        )
+        .arg(
+            Arg::with_name("WINDOW_FLANK")
+                .long("window-flank")
+                .help("Residues on either side of the modified position in SITE_WINDOW. Default - 7")
+                .requires("SITE_WINDOWS")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ONTOLOGY")
+                .long("ontology")
+                .help("Maps the EVENT_NAME and RESIDUE combinations of MV_EVENT to PSI-MOD terms into the EVENT_TYPE_ONTOLOGY table, from a CSV or TSV mapping file or a PSI-MOD OBO file")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("sniff")
+                .about("Guesses the CSV dialect of an export and prints it as a configuration file section")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The export to look at")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("TABLE")
+                        .long("table")
+                        .help("Table the export is for. Default - guessed from the file name")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("infer")
+                .about("Proposes the definition of a new table from its export, as a configuration file section")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The export of the new table")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("TABLE")
+                        .long("table")
+                        .help("Name of the new table. Default - guessed from the file name")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("SAMPLE")
+                        .long("sample")
+                        .help("Looks at only this many rows instead of the whole export")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("profile")
+                .about("Reports statistics of every column of the exports and flags values close to their column size")
+                .arg(
+                    Arg::with_name("TABLES")
+                        .help("Tables to profile. Default - all")
+                        .multiple(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("TOP")
+                        .long("top")
+                        .help("Number of most frequent values listed per column. Default - 5")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("MARGIN")
+                        .long("margin")
+                        .help("Flags VARCHAR columns whose longest value is within this percentage of the declared size. Default - 10")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .long("format")
+                        .possible_values(&["text", "json"])
+                        .help("Format of the profile. Default - text")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate-fixtures")
+                .about("Writes synthetic, mutually consistent CSV exports for load testing")
+                .arg(
+                    Arg::with_name("OUT_DIR")
+                        .long("out-dir")
+                        .help("Directory the CSV files are written to. Default - current directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ENTRIES")
+                        .long("entries")
+                        .help("Number of MV_ENTRY rows. Default - 1000")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("EVENTS")
+                        .long("events")
+                        .help("Number of MV_EVENT rows. Default - 5000")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("EFIP")
+                        .long("efip")
+                        .help("Number of MV_EFIP rows. Default - 500")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("PROTEO")
+                        .long("proteo")
+                        .help("Number of MV_PROTEO rows. Default - 500")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("SEED")
+                        .long("seed")
+                        .help("Seed of the random generator, the same seed gives the same files. Default - 1")
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
 
+    let verbosity = matches.occurrences_of("VERBOSE") as i64 - matches.occurrences_of("QUIET") as i64;
+    let log_format = match matches.value_of("LOG_FORMAT") {
+        Some("json") => LogFormat::Json,
+        _ => LogFormat::Text,
+    };
+    logging::init(verbosity, log_format);
+
+    if let Some(matches) = matches.subcommand_matches("generate-fixtures") {
+        generate_fixtures(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sniff") {
+        sniff_export(matches);
+        return;
+    }
+
+    if let Some(subcommand) = matches.subcommand_matches("infer") {
+        infer_table(&matches, subcommand);
+        return;
+    }
+
+    if let Some(subcommand) = matches.subcommand_matches("profile") {
+        profile_exports(&matches, subcommand);
+        return;
+    }
+
     let mut username = "postgres";
     let mut password = "postgres";
     let mut host = "localhost";
@@ -80,938 +397,1112 @@ fn main() {
         database = database
     );
 
+    let mut report = Report::new(matches.value_of("REPORT").map(PathBuf::from));
+    let metrics = MetricsTargets {
+        file: matches.value_of("METRICS_FILE").map(PathBuf::from),
+        pushgateway: matches.value_of("PUSHGATEWAY").map(String::from),
+    };
+
+    let config = match load_config(&matches) {
+        Ok(config) => config,
+        Err(error) => return finish(&mut report, &metrics, Some(ImportError::Config(error))),
+    };
+
+    let options = ImportOptions {
+        config,
+        tolerance: if matches.is_present("TOLERANT") {
+            Some(parse_reject_limit(&matches))
+        } else {
+            None
+        },
+        encoding: parse_encoding(&matches),
+        search: matches.is_present("SEARCH"),
+        pmids: matches.is_present("PMIDS"),
+        sites: matches.is_present("SITES"),
+        window_flank: if matches.is_present("SITE_WINDOWS") {
+            Some(parse_flank(&matches))
+        } else {
+            None
+        },
+        ontology: matches.value_of("ONTOLOGY").map(str::to_string),
+        lock_wait: Duration::from_secs(parse_number(&matches, "LOCK_WAIT", 0)),
+        timeouts: Timeouts {
+            lock: parse_duration(&matches, "LOCK_TIMEOUT", Duration::from_secs(10)),
+            statement: parse_duration(&matches, "STATEMENT_TIMEOUT", Duration::from_secs(0)),
+            retries: parse_number(&matches, "DDL_RETRIES", 3) as u32,
+            backoff: parse_duration(&matches, "DDL_BACKOFF", Duration::from_secs(1)),
+            blockers: matches.value_of("BLOCKERS").and_then(Blockers::parse).unwrap_or(Blockers::Report),
+        },
+    };
+
+    // The password is left out so that it never ends up in shipped logs.
     info!(
-        "{}",
-        format!(
-            "Connecting to database at - {}.",
-            connection_string.as_str()
-        )
+        "Connecting to database at - postgres://{}@{}:{}/{}.",
+        username, host, port, database
     );
 
-    let conn;
-    let conn_result = Connection::connect(connection_string.as_str(), TlsMode::None);
-    match conn_result {
-        Ok(value) => {
-            conn = value;
-        },
-        Err(error) => {
-            error!("{}", error);
-            std::process::exit(1);
-        }
-    }
+    let result = Connection::connect(connection_string.as_str(), TlsMode::None)
+        .map_err(ImportError::from)
+        .and_then(|conn| import(&conn, &mut report, &options));
+    finish(&mut report, &metrics, result.err());
+}
+
+/// Settings of an import run that are not about the connection.
+struct ImportOptions {
+    config: Config,
+    /// Reject limit of the tolerant mode, `None` when bad rows fail the run.
+    tolerance: Option<RejectLimit>,
+    /// Encoding of the exports.
+    encoding: Encoding,
+    /// Whether to run the search build step.
+    search: bool,
+    /// Whether to link the events to their PMIDs in EVENT_PMID.
+    pmids: bool,
+    /// Whether to parse the site lists into SITE.
+    sites: bool,
+    /// Residues on either side of the site windows, `None` when SITE_WINDOW
+    /// is not built.
+    window_flank: Option<u32>,
+    /// Mapping or OBO file to build EVENT_TYPE_ONTOLOGY from, `None` when it
+    /// is not built.
+    ontology: Option<String>,
+    /// How long to wait for another run to release the import lock.
+    lock_wait: Duration,
+    timeouts: Timeouts,
+}
 
+/// Runs every step of the import inside a single transaction. Nothing is
+/// committed unless all steps succeed.
+fn import(conn: &Connection, report: &mut Report, options: &ImportOptions) -> Result<(), ImportError> {
+    //LOCK out other runs against the same database and schema
+    let scope = take_lock(conn, report, options.lock_wait)?;
+
+    options.timeouts.apply(conn)?;
 
     //START the transaction
-    let start_transaction_result = conn.execute("BEGIN;", &[]);
-    match start_transaction_result {
-        Ok(_) => info!("STARTED TRANSACTION"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
-        }
-    }
+    execute(conn, report, "begin", "transaction", "BEGIN;")?;
 
     //DROP indexes
-    drop_index(&conn);
+    let timeouts = &options.timeouts;
+    drop_index(conn, report, &options.config, timeouts)?;
 
     //DROP tables
-    drop_tables(&conn);
-    
-    //create table MV_ENTRY
-    create_mv_entry(&conn);
-
-    //create table MV_EVENT
-    create_mv_event(&conn);
+    drop_tables(conn, report, &options.config, timeouts)?;
 
-    //create table MV_EFIP
-    create_mv_efip(&conn);
+    //create tables MV_ENTRY, MV_EVENT, MV_EFIP, MV_PROTEO, SEQUENCE, those defined in the config, EVENT_PMID, SITE, SITE_WINDOW and EVENT_TYPE_ONTOLOGY
+    for table in built_tables(options) {
+        execute(conn, report, "create", table.name, &table.create_statement())?;
+    }
 
-    //create table MV_PROTEO
-    create_mv_proteo(&conn);
-    
-    //create table SEQUENCES
-    create_sequence(&conn);
+    //populate the tables from their exports
+    for table in options.config.tables() {
+        populate(conn, report, table, options)?;
+    }
 
-    //populate mv_entry
-    populate_mv_entry(&conn);
+    //check the format of the protein and taxon identifiers
+    for identifiers in identifiers::COLUMNS.iter() {
+        validate_identifiers(conn, report, identifiers)?;
+    }
 
-    //populate mv event
-    populate_mv_event(&conn);
+    //fill the lookup tables of the vocabularies and check the columns against them
+    for vocabulary in options.config.vocabularies() {
+        check_vocabulary(conn, report, vocabulary)?;
+    }
 
-    //populate mv efip
-    populate_mv_efip(&conn);
-    
-    //populate mv_proteo
-    populate_mv_proteo(&conn);
+    //link the events to their PMIDs and index the links
+    if options.pmids {
+        for list in pmid::LISTS.iter() {
+            link_pmids(conn, report, list)?;
+        }
+        for index in pmid::INDEXES.iter() {
+            execute(conn, report, "index", index.name, &index.create_statement())?;
+        }
+    }
 
-    //populate sequences
-    populate_sequence(&conn);
+    //parse the site lists and index the sites
+    if options.sites {
+        for list in sites::LISTS.iter() {
+            parse_sites(conn, report, list)?;
+        }
+        for index in sites::INDEXES.iter() {
+            execute(conn, report, "index", index.name, &index.create_statement())?;
+        }
+    }
 
-    //create index
-    create_index(&conn);
+    //create the built-in indexes and those listed in the config
+    create_index(conn, report, &options.config)?;
 
-    //END the transaction
-    let end_transaction_result = conn.execute("COMMIT;", &[]);
-    match end_transaction_result {
-        Ok(_) => info!("END TRANSACTION"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
+    //tie the columns of the vocabularies to their lookup tables
+    for vocabulary in options.config.vocabularies().iter().filter(|vocabulary| vocabulary.foreign_keys) {
+        for member in &vocabulary.members {
+            execute(conn, report, "constrain", &member.source(), &vocabulary.foreign_key_statement(member))?;
         }
     }
 
-}
+    //check the residues of the events against the sequences
+    check_residues(conn, report)?;
 
-fn drop_tables(conn: &Connection) {
-    //drop table MV_ENTRY
-    log("DROPPING MV_ENTRY...");
-    let drop_mv_entry_result = conn.execute("DROP TABLE IF EXISTS MV_ENTRY;", &[]);
-    match drop_mv_entry_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    //derive the flanking peptides of the matched residues and index them
+    if let Some(flank) = options.window_flank {
+        build_windows(conn, report, flank)?;
+        for index in windows::INDEXES.iter() {
+            execute(conn, report, "index", index.name, &index.create_statement())?;
         }
     }
 
-    //drop table MV_EVENT
-    log("DROPPING MV_EVENT...");
-    let drop_mv_event_result = conn.execute("DROP TABLE IF EXISTS MV_EVENT;", &[]);
-    match drop_mv_event_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    //map the event names and residues to PSI-MOD terms and index them
+    if let Some(ref path) = options.ontology {
+        map_ontology(conn, report, path)?;
+        for index in ontology::INDEXES.iter() {
+            execute(conn, report, "index", index.name, &index.create_statement())?;
         }
     }
 
-    //drop table MV_EFIP
-    log("DROPPING MV_EFIP...");
-    let drop_mv_entry_result = conn.execute("DROP TABLE IF EXISTS MV_EFIP;", &[]);
-    match drop_mv_entry_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    //build the search column, indexes and function of MV_ENTRY
+    if options.search {
+        for (target, sql) in search::statements() {
+            execute(conn, report, "search", target, &sql)?;
         }
     }
 
-
-    //drop table MV_EFIP
-    log("DROPPING MV_PROTEO...");
-    let drop_mv_entry_result = conn.execute("DROP TABLE IF EXISTS MV_PROTEO;", &[]);
-    match drop_mv_entry_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    //CLUSTER tables while they are not visible to anyone else yet
+    for table in options.config.tables() {
+        if let Some(index) = options.config.maintenance(table).cluster {
+            execute(conn, report, "cluster", table.name, &format!("CLUSTER {} USING {}", table.name, index))?;
         }
     }
 
+    //END the transaction
+    execute(conn, report, "commit", "transaction", "COMMIT;")?;
 
-    //drop table SEQUENCE
-    log("DROPPING SEQUENCE...");
-    let drop_mv_entry_result = conn.execute("DROP TABLE IF EXISTS SEQUENCE;", &[]);
-    match drop_mv_entry_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    //ANALYZE and VACUUM the committed tables
+    maintain(conn, report, options);
+
+    lock::release(conn)?;
+    debug!("Released the import lock on {}", scope);
+    Ok(())
+}
+
+/// Runs ANALYZE and VACUUM, which cannot run inside the transaction. The
+/// import is already committed, so a failure is only a warning.
+fn maintain(conn: &Connection, report: &mut Report, options: &ImportOptions) {
+    let config = &options.config;
+    for table in built_tables(options) {
+        let maintenance = config.maintenance(table);
+        let (step, sql) = match (maintenance.vacuum, maintenance.analyze) {
+            (Some(vacuum), analyze) => {
+                let mut options = Vec::new();
+                if vacuum == Vacuum::Freeze {
+                    options.push("FREEZE");
+                }
+                if analyze {
+                    options.push("ANALYZE");
+                }
+                let options = if options.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", options.join(", "))
+                };
+                ("vacuum", format!("VACUUM{} {}", options, table.name))
+            }
+            (None, true) => ("analyze", format!("ANALYZE {}", table.name)),
+            (None, false) => continue,
+        };
+        if let Err(error) = execute(conn, report, step, table.name, &sql) {
+            warn!(
+                step = step, table = table.name, error_class = error.class();
+                "{} {} failed, the import is committed nevertheless - {}", step_label(step), table.name, error
+            );
         }
     }
 }
 
-fn create_mv_entry(conn: &Connection) {
-    log("CREATING MV_ENTRY...");
-
-    let create_mv_entry_result = conn.execute(
-        "CREATE TABLE IF NOT EXISTS MV_ENTRY
-        (
-            IPTM_ENTRY_ID BIGINT NOT NULL,
-            IPTM_ENTRY_CODE VARCHAR(25) NOT NULL,
-            IPTM_ENTRY_TYPE VARCHAR(10) NOT NULL,
-            IPTM_ENTRY_SYMBOL VARCHAR(4000),
-            UNIPROT_ID VARCHAR(50),
-            PROTEIN_NAME VARCHAR(200),
-            GENE_NAME VARCHAR(50),
-            PROTEIN_SYNONYMS TEXT,
-            GENE_SYNONYMS TEXT,
-            DEFINITION TEXT,
-            CATEGORY VARCHAR(25),
-            IS_REVIEWED CHAR(1),
-            TAXON_CODE VARCHAR(25),
-            TAXON_SPECIES VARCHAR(200),
-            TAXON_COMMON VARCHAR(100),
-            NOTE TEXT,
-            SITES TEXT,
-            XREF VARCHAR(25),
-            NUM_ENZYME BIGINT,
-            NUM_SUBSTRATE BIGINT,
-            NUM_PPI BIGINT,
-            NUM_SITE BIGINT,
-            NUM_FORM BIGINT,
-            ROLE_AS_ENZYME CHAR(1),
-            ROLE_AS_SUBSTRATE CHAR(1),
-            ROLE_AS_PPI CHAR(1),
-            WEIGHT BIGINT,
-            LIST_AS_SUBSTRATE VARCHAR(25),
-            LIST_AS_ENZYME VARCHAR(25),
-            HAS_OVERLAP_PTM CHAR(1),
-            PROTEIN_SYN VARCHAR(4000),
-            GENE_SYN VARCHAR(4000)
-        )",
-        &[],
-    );
-
-    match create_mv_entry_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
+fn take_lock(conn: &Connection, report: &mut Report, wait: Duration) -> Result<String, ImportError> {
+    debug!(step = "lock"; "LOCKING...");
+    let timer = report.start("lock", "import");
+    match lock::acquire(conn, wait) {
+        Ok(scope) => {
+            let done = report.finish(timer, None, None);
+            info!(
+                step = "lock", target = scope.as_str(), duration_seconds = done.duration_seconds;
+                "LOCKING {}...DONE", scope
+            );
+            Ok(scope)
+        }
         Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+            report.fail_step(timer);
+            Err(error)
         }
     }
 }
 
-fn populate_mv_entry(conn: &Connection) {
-    // Read mv_entry_exported.csv
-    let mut file;
-    match File::open("./mv_entry_export.csv") {
-        Ok(value) => {
-            file = value;
-        },
+fn generate_fixtures(matches: &ArgMatches) {
+    let out_dir = Path::new(matches.value_of("OUT_DIR").unwrap_or("."));
+    let sizes = fixtures::FixtureSizes {
+        entries: parse_number(matches, "ENTRIES", 1000) as usize,
+        events: parse_number(matches, "EVENTS", 5000) as usize,
+        efip: parse_number(matches, "EFIP", 500) as usize,
+        proteo: parse_number(matches, "PROTEO", 500) as usize,
+    };
+    let seed = parse_number(matches, "SEED", 1);
+
+    debug!("GENERATING FIXTURES IN {}...", out_dir.display());
+    match fixtures::generate(out_dir, &sizes, seed) {
+        Ok(_) => info!("GENERATING FIXTURES IN {}...DONE", out_dir.display()),
         Err(error) => {
-            error!{"{}",error};
+            error!("{}", error);
             std::process::exit(-1);
         }
     }
+}
 
-    log("POPULATING MV_ENTRY...");
-    
-    let stmt_result = conn.prepare("COPY mv_entry FROM STDIN DELIMITER ',' CSV HEADER");
-    let stmt;
-    match stmt_result {
-        Ok(value) => {
-            stmt = value;
-        },
+fn sniff_export(matches: &ArgMatches) {
+    let path = matches.value_of("FILE").unwrap();
+    let dialect = match File::open(path).and_then(|file| sniff::sniff(BufReader::new(file))) {
+        Ok(dialect) => dialect,
         Err(error) => {
-            error!("{}",error);
+            error!("{}: {}", path, error);
             std::process::exit(-1);
         }
-    }
-
-    let copy_result = stmt.copy_in(&[], &mut file);
-    match copy_result {
-        Ok(_) => {
-            logln("DONE")
-        },
+    };
+
+    let table = match matches.value_of("TABLE") {
+        Some(table) => table.to_string(),
+        None => guess_table(path),
+    };
+
+    let mut settings = TableConfig::from_dialect(&dialect);
+    settings.file = Some(path.to_string());
+    let mut config = Config::default();
+    config.tables.insert(table, settings);
+    match toml::to_string(&config) {
+        Ok(text) => print!("{}", text),
         Err(error) => {
-            error!("{}",error);
+            error!("{}", error);
             std::process::exit(-1);
         }
     }
-
 }
 
-fn create_mv_event(conn: &Connection) {
-    log("CREATING MV_EVENT...");
-    let create_mv_event_result = conn.execute(
-        "CREATE TABLE IF NOT EXISTS MV_EVENT
-        (
-            IPTM_EVENT_ID BIGINT NOT NULL,
-            SUB_FORM_CODE VARCHAR(25),
-            SUB_CODE VARCHAR(25),
-            SUB_TYPE VARCHAR(10),
-            SUB_UNIPROT_ID VARCHAR(50),
-            SUB_SYMBOL VARCHAR(4000),
-            SUB_TAXON_CODE VARCHAR(25),
-            SUB_TAXON_COMMON VARCHAR(100),
-            SUB_SITES TEXT,
-            SUB_XREF VARCHAR(25),
-            ENZ_FORM_CODE VARCHAR(25),
-            ENZ_CODE VARCHAR(25),
-            ENZ_TYPE VARCHAR(10),
-            ENZ_UNIPROT_ID VARCHAR(50),
-            ENZ_SYMBOL VARCHAR(4000),
-            ENZ_TAXON_CODE VARCHAR(25),
-            ENZ_TAXON_COMMON VARCHAR(100),
-            ENZ_SITES TEXT,
-            ENZ_XREF VARCHAR(25),
-            EVENT_NAME VARCHAR(50),
-            EVENT_LABEL VARCHAR(10),
-            SOURCE_LABEL VARCHAR(10),
-            IS_AUTO_GENERATED CHAR(1),
-            RESIDUE VARCHAR(1),
-            POSITION BIGINT,
-            MODIFIER VARCHAR(50),
-            NOTE TEXT,
-            PMIDS TEXT,
-            NUM_SUBSTRATES VARCHAR(4000)
-        )",
-        &[],
-    );
-
-    match create_mv_event_result {
-        Ok(_) => logln("DONE"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
+/// Guesses the table of an export from its file name without the extension,
+/// e.g. MV_EVENT for mv_event_export.tsv and CURATED_SITE for curated_site_export.csv.
+fn guess_table(path: &str) -> String {
+    let stem = |path: &str| {
+        let file_name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path);
+        file_name.split('.').next().unwrap_or(file_name).to_lowercase()
+    };
+    match schema::TABLES.iter().find(|table| stem(table.export) == stem(path)) {
+        Some(table) => table.name.to_string(),
+        None => {
+            let stem = stem(path);
+            stem.strip_suffix("_export").unwrap_or(&stem).to_uppercase()
         }
     }
 }
 
-fn populate_mv_event(conn: &Connection) {
-    // Read mv_event_exported.csv
-    let mut file;
-    match File::open("./mv_event_export.csv") {
-        Ok(value) => {
-            file = value;
-        },
+fn infer_table(matches: &ArgMatches, subcommand: &ArgMatches) {
+    let path = subcommand.value_of("FILE").unwrap();
+    let table = match subcommand.value_of("TABLE") {
+        Some(table) => table.to_uppercase(),
+        None => guess_table(path),
+    };
+    if schema::TABLES.iter().chain(schema::DERIVED.iter()).any(|builtin| builtin.name == table) {
+        error!("{} is built in, its definition cannot be replaced; name the new table with --table", table);
+        std::process::exit(-1);
+    }
+    let sample = subcommand.value_of("SAMPLE").map(|_| parse_number(subcommand, "SAMPLE", 0));
+    let encoding = parse_encoding(matches);
+
+    let result = File::open(path)
+        .and_then(|file| sniff::sniff(BufReader::new(file)))
+        .and_then(|dialect| {
+            let file = File::open(path)?;
+            let input = Transcoder::new(BufReader::new(file), encoding, &dialect);
+            infer::infer(&table, input, &dialect, sample)
+        });
+    let mut definition = match result {
+        Ok(definition) => definition,
         Err(error) => {
-            error!{"{}",error};
+            error!("{}: {}", path, error);
             std::process::exit(-1);
         }
-    }
-    
-    log("POPULATING MV_EVENT...");
-
-    let stmt_result = conn.prepare("COPY mv_event FROM STDIN DELIMITER ',' CSV HEADER");
-    let stmt;
-    match stmt_result {
-        Ok(value) => {
-            stmt = value;
-        },
+    };
+
+    definition.settings.file = Some(path.to_string());
+    let mut config = Config::default();
+    config.tables.insert(table, definition.settings);
+    match toml::to_string(&config) {
+        Ok(text) => {
+            let scope = if definition.complete { "" } else { "the first " };
+            println!(
+                "# Inferred from {}{} rows of {}. Review the types, sizes and indexes before use.",
+                scope, definition.rows, path
+            );
+            print!("{}", text);
+        }
         Err(error) => {
-            error!("{}",error);
+            error!("{}", error);
             std::process::exit(-1);
         }
     }
+}
 
-    let copy_result = stmt.copy_in(&[], &mut file);
-    match copy_result {
-        Ok(_) => {
-            logln("DONE")
-        },
+fn profile_exports(matches: &ArgMatches, subcommand: &ArgMatches) {
+    let config = match load_config(matches) {
+        Ok(config) => config,
         Err(error) => {
-            error!("{}",error);
+            error!("invalid configuration - {}", error);
             std::process::exit(-1);
         }
+    };
+    let encoding = parse_encoding(matches);
+    let settings = profile::Settings {
+        top: parse_number(subcommand, "TOP", 5) as usize,
+        margin: parse_number(subcommand, "MARGIN", 10),
+    };
+    let tables: Vec<&Table> = match subcommand.values_of("TABLES") {
+        Some(names) => names
+            .map(|name| match config.table(name) {
+                Some(table) => table,
+                None => {
+                    error!("Unknown table {}", name);
+                    std::process::exit(-1);
+                }
+            })
+            .collect(),
+        None => config.tables(),
+    };
+
+    let mut profiles = Vec::new();
+    for table in tables {
+        let path = config.export(table);
+        let dialect = export_dialect(&config, table, &path);
+        debug!(table = table.name; "PROFILING {}...", table.name);
+        let result = File::open(&path).and_then(|file| {
+            let input = Transcoder::new(export_reader(file, table, &path), encoding, &dialect);
+            profile::profile(table, &path, input, &dialect, &settings)
+        });
+        let profile = match result {
+            Ok(profile) => profile,
+            Err(error) => {
+                error!("{}: {}", path, error);
+                std::process::exit(-1);
+            }
+        };
+        for column in &profile.columns {
+            if let Some(warning) = column.warning() {
+                warn!(table = table.name, column = column.name.as_str(); "{}.{}: {}", table.name, column.name, warning);
+            }
+        }
+        if profile.malformed > 0 {
+            warn!(table = table.name; "{}: {} malformed records left out", table.name, profile.malformed);
+        }
+        profiles.push(profile);
     }
-    
-}
-
-fn create_mv_efip(conn: &Connection) {
-    log("CREATING MV_EFIP...");
-    let create_mv_efip_result = conn.execute(
-        "CREATE TABLE IF NOT EXISTS MV_EFIP
-        (
-            PPI_EVENT_ID BIGINT,
-            PTM_EVENT_ID BIGINT,
-            IMPACT VARCHAR(50),
-            PPI_SUB_CODE VARCHAR(25),
-            PPI_SUB_TYPE VARCHAR(10),
-            PPI_SUB_SYMBOL VARCHAR(4000),
-            PPI_SUB_TAXON_CODE VARCHAR(25),
-            PPI_SUB_TAXON_COMMON VARCHAR(100),
-            PPI_SUB_SITES TEXT,
-            PPI_PR_CODE VARCHAR(25),
-            PPI_PR_TYPE VARCHAR(10),
-            PPI_PR_SYMBOL VARCHAR(4000),
-            PPI_PR_TAXON_CODE VARCHAR(25),
-            PPI_PR_TAXON_COMMON VARCHAR(100),
-            PPI_SOURCE_LABEL VARCHAR(10),
-            PPI_NOTE TEXT,
-            PPI_PMIDS TEXT,
-            PTM_SUB_CODE VARCHAR(25),
-            PTM_SUB_TYPE VARCHAR(10),
-            PTM_SUB_SYMBOL VARCHAR(4000),
-            PTM_SUB_TAXON_CODE VARCHAR(25),
-            PTM_SUB_TAXON_COMMON VARCHAR(100),
-            PTM_SUB_SITES TEXT,
-            PTM_ENZ_CODE VARCHAR(25),
-            PTM_ENZ_TYPE VARCHAR(10),
-            PTM_ENZ_SYMBOL VARCHAR(4000),
-            PTM_ENZ_TAXON_CODE VARCHAR(25),
-            PTM_ENZ_TAXON_COMMON VARCHAR(100),
-            PTM_EVENT_NAME VARCHAR(50),
-            PTM_EVENT_LABEL VARCHAR(10),
-            PTM_RESIDUE VARCHAR(1),
-            PTM_POSITION BIGINT,
-            PTM_SOURCE_LABEL VARCHAR(10),
-            PTM_NOTE TEXT,
-            PTM_PMIDS TEXT
-        )",
-        &[],
-    );
 
-    match create_mv_efip_result {
-        Ok(_) => logln("DONE"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
+    if subcommand.value_of("FORMAT") == Some("json") {
+        match serde_json::to_string_pretty(&profiles) {
+            Ok(text) => println!("{}", text),
+            Err(error) => {
+                error!("{}", error);
+                std::process::exit(-1);
+            }
+        }
+    } else {
+        for (index, profile) in profiles.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            print!("{}", profile);
         }
     }
 }
 
-fn populate_mv_efip(conn: &Connection) {
-    // Read mv_efip_exported.csv
-    let mut file;
-    match File::open("./mv_efip_export.csv") {
-        Ok(value) => {
-            file = value;
-        },
-        Err(error) => {
-            error!{"{}",error};
-            std::process::exit(-1);
-        }
+fn load_config(matches: &ArgMatches) -> Result<Config, String> {
+    match matches.value_of("CONFIG") {
+        Some(path) => Config::load(Path::new(path)),
+        None => Ok(Config::default()),
     }
- 
-    log("POPULATING MV_EFIP...");
+}
 
-    let stmt_result = conn.prepare("COPY mv_efip FROM STDIN DELIMITER ',' CSV HEADER");
-    let stmt;
-    match stmt_result {
-        Ok(value) => {
-            stmt = value;
+fn parse_encoding(matches: &ArgMatches) -> Encoding {
+    match matches.value_of("ENCODING") {
+        Some(value) => match Encoding::parse(value) {
+            Some(encoding) => encoding,
+            None => {
+                error!("ENCODING must be one of utf-8, latin1, windows-1252 or auto, got {}", value);
+                std::process::exit(-1);
+            }
         },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+        None => Encoding::Utf8,
     }
+}
 
-    let copy_result = stmt.copy_in(&[], &mut file);
-    match copy_result {
-        Ok(_) => {
-            logln("DONE")
+fn parse_reject_limit(matches: &ArgMatches) -> RejectLimit {
+    match matches.value_of("MAX_REJECTS") {
+        Some(value) => match RejectLimit::parse(value) {
+            Some(limit) => limit,
+            None => {
+                error!("MAX_REJECTS must be a count or a percentage, got {}", value);
+                std::process::exit(-1);
+            }
         },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+        None => RejectLimit::Unlimited,
     }
-
 }
 
-fn create_mv_proteo(conn: &Connection) {
-    log("CREATING MV_PROTEO...");
-    let create_mv_entry_result = conn.execute(
-        "CREATE TABLE IF NOT EXISTS MV_PROTEO
-        (
-            SUB_CODE VARCHAR(25),
-            SUB_TYPE VARCHAR(10),
-            SUB_SYMBOL VARCHAR(4000),
-            SUB_SITES VARCHAR(4000),
-            SUB_XREF VARCHAR(25),
-            ENZ_CODE VARCHAR(25),
-            ENZ_TYPE VARCHAR(10),
-            ENZ_SYMBOL VARCHAR(4000),
-            ENZ_SITES VARCHAR(4000),
-            ENZ_XREF VARCHAR(25),
-            SITES VARCHAR(4000),
-            EVENT_NAME VARCHAR(50) NOT NULL,
-            EVENT_LABEL VARCHAR(10) NOT NULL,
-            SOURCE_LABEL VARCHAR(10) NOT NULL,
-            IS_AUTO_GENERATED CHAR(1) NOT NULL,
-            MODIFIER VARCHAR(50),
-            PMIDS VARCHAR(4000)
-        )",
-        &[],
-    );
-
-    match create_mv_entry_result {
-        Ok(_) => logln("DONE"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
+fn parse_flank(matches: &ArgMatches) -> u32 {
+    match parse_number(matches, "WINDOW_FLANK", u64::from(windows::DEFAULT_FLANK)) {
+        flank @ 1..=100 => flank as u32,
+        flank => {
+            error!("WINDOW_FLANK must be between 1 and 100, got {}", flank);
+            std::process::exit(-1);
         }
     }
 }
 
-fn populate_mv_proteo(conn: &Connection) {
-    // Read mv_proteo_exported.csv
-    let mut file;
-    match File::open("./mv_proteo_export.csv") {
-        Ok(value) => {
-            file = value;
+fn parse_duration(matches: &ArgMatches, name: &str, default: Duration) -> Duration {
+    match matches.value_of(name) {
+        Some(value) => match timeouts::parse_duration(value) {
+            Some(duration) => duration,
+            None => {
+                error!("{} must be a duration such as 500ms, 10s or 2min, got {}", name, value);
+                std::process::exit(-1);
+            }
         },
-        Err(error) => {
-            error!{"{}",error};
-            std::process::exit(-1);
-        }
+        None => default,
     }
+}
 
-    log("POPULATING MV_PROTEO...");
-
-    let stmt_result = conn.prepare("COPY mv_proteo FROM STDIN DELIMITER ',' CSV HEADER");
-    let stmt;
-    match stmt_result {
-        Ok(value) => {
-            stmt = value;
+fn parse_number(matches: &ArgMatches, name: &str, default: u64) -> u64 {
+    match matches.value_of(name) {
+        Some(value) => match value.parse() {
+            Ok(number) => number,
+            Err(_) => {
+                error!("{} must be a non-negative number, got {}", name, value);
+                std::process::exit(-1);
+            }
         },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+        None => default,
     }
+}
 
-    let copy_result = stmt.copy_in(&[], &mut file);
-    match copy_result {
+/// Runs a single statement as one step of the import. On failure the
+/// open transaction is rolled back when the connection closes.
+fn execute(conn: &Connection, report: &mut Report, step: &str, target: &str, sql: &str) -> Result<(), ImportError> {
+    debug!(step = step, target = target; "{} {}...", step_label(step), target);
+    let timer = report.start(step, target);
+    match conn.execute(sql, &[]) {
         Ok(_) => {
-            logln("DONE")
+            let done = report.finish(timer, None, None);
+            info!(
+                step = step, target = target, duration_seconds = done.duration_seconds;
+                "{} {}...DONE", step_label(step), target
+            );
+            Ok(())
         },
         Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+            report.fail_step(timer);
+            Err(ImportError::from(error))
         }
     }
 }
 
-fn create_sequence(conn: &Connection) {
-    log("CREATING SEQUENCE...");
-    let create_mv_entry_result = conn.execute(
-        "CREATE TABLE IF NOT EXISTS SEQUENCE
-        (
-            ID VARCHAR(25),
-            SEQ TEXT
-        )",
-        &[],
-    );
-
-    match create_mv_entry_result {
-        Ok(_) => logln("DONE"),
-        Err(val) => {
-            error!("{}", val);
-            std::process::exit(1);
+/// Runs a DDL statement that locks an existing table, such as DROP TABLE.
+/// When the lock is not granted within the lock timeout, the statement is
+/// rolled back to a savepoint and tried again after a backoff.
+fn execute_ddl(
+    conn: &Connection,
+    report: &mut Report,
+    timeouts: &Timeouts,
+    step: &str,
+    target: &str,
+    sql: &str,
+) -> Result<(), ImportError> {
+    debug!(step = step, target = target; "{} {}...", step_label(step), target);
+    let timer = report.start(step, target);
+    let mut attempt = 0;
+    loop {
+        let result = conn.batch_execute("SAVEPOINT ddl;").and_then(|_| conn.execute(sql, &[]));
+        let error = match result {
+            Ok(_) => {
+                if let Err(error) = conn.batch_execute("RELEASE SAVEPOINT ddl;") {
+                    report.fail_step(timer);
+                    return Err(ImportError::from(error));
+                }
+                let done = report.finish(timer, None, None);
+                info!(
+                    step = step, target = target, duration_seconds = done.duration_seconds;
+                    "{} {}...DONE", step_label(step), target
+                );
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let blocked = timeouts::is_lock_timeout(&error);
+        let handled = conn.batch_execute("ROLLBACK TO SAVEPOINT ddl;").map_err(ImportError::from).and_then(|_| {
+            if blocked {
+                timeouts.handle_blockers(conn, target)
+            } else {
+                Ok(Vec::new())
+            }
+        });
+        let blockers = match handled {
+            Ok(blockers) => blockers,
+            Err(handling_error) => {
+                report.fail_step(timer);
+                return Err(handling_error);
+            }
+        };
+        if !blocked || attempt >= timeouts.retries {
+            report.fail_step(timer);
+            return Err(if blocked {
+                ImportError::Blocked(error, blockers)
+            } else {
+                ImportError::from(error)
+            });
         }
+
+        attempt += 1;
+        let delay = timeouts.delay(attempt);
+        warn!(
+            step = step, target = target, attempt = attempt;
+            "{} {}: lock not granted within the lock timeout, retrying in {}ms ({} of {})",
+            step_label(step), target, delay.as_millis(), attempt, timeouts.retries
+        );
+        thread::sleep(delay);
     }
 }
 
-fn populate_sequence(conn: &Connection) {
-    // Read sequences.csv
-    let mut file;
-    match File::open("./sequences.csv") {
-        Ok(value) => {
-            file = value;
-        },
+/// A table together with where and how its export is read.
+struct Source<'a> {
+    table: &'a Table,
+    path: String,
+    dialect: Dialect,
+}
+
+/// Streams a CSV export into `table` with COPY, transcoding it to UTF-8 on
+/// the way. In tolerant mode the rows are also checked and bad ones are set
+/// aside.
+fn populate(conn: &Connection, report: &mut Report, table: &'static Table, options: &ImportOptions) -> Result<(), ImportError> {
+    let path = options.config.export(table);
+    let source = Source {
+        table,
+        dialect: export_dialect(&options.config, table, &path),
+        path,
+    };
+    let timer = report.start("populate", table.name);
+    let bytes = Rc::new(Cell::new(0));
+    let result = File::open(&source.path)
+        .map_err(|error| ImportError::Input(source.path.clone(), error))
+        .and_then(|file| {
+            debug!(step = "populate", table = table.name; "POPULATING {}...", table.name);
+            let counting = CountingReader { inner: file, bytes: bytes.clone() };
+            let input = Transcoder::new(export_reader(counting, table, &source.path), options.encoding, &source.dialect);
+            match options.tolerance {
+                Some(limit) => load_tolerant(conn, &source, input, limit, options),
+                None => load(conn, &source, input, options),
+            }
+        });
+
+    let loaded = match result {
+        Ok(loaded) => loaded,
         Err(error) => {
-            error!{"{}",error};
-            std::process::exit(-1);
+            let step = report.fail_step(timer);
+            if let ImportError::TooManyRejects(_, rejected, _, _) = error {
+                step.rejected = Some(rejected);
+            }
+            return Err(error);
         }
+    };
+
+    if let Some(ref computed) = loaded.computed {
+        if computed.non_standard > 0 {
+            warn!(
+                step = "populate", table = table.name;
+                "{}: {} sequences with non-standard letters, e.g. {}", table.name, computed.non_standard, computed.examples.join(", ")
+            );
+        }
+    }
+    if loaded.converted > 0 {
+        info!(
+            table = table.name, converted = loaded.converted;
+            "{}: transcoded {} records from {:?} to UTF-8", table.name, loaded.converted, options.encoding
+        );
+    }
+    let bytes = bytes.get();
+    let done = report.finish(timer, Some(loaded.rows), Some(bytes));
+    done.rejected = loaded.rejected;
+    if options.encoding != Encoding::Utf8 {
+        done.converted = Some(loaded.converted);
     }
+    info!(
+        step = "populate", table = table.name, rows = loaded.rows, bytes = bytes,
+        duration_seconds = done.duration_seconds;
+        "POPULATING {}...DONE ({} rows)", table.name, loaded.rows
+    );
+    Ok(())
+}
 
-    log("POPULATING SEQUENCE...");
+/// What a populate step loaded.
+struct Loaded {
+    rows: u64,
+    /// Records that had to be transcoded to UTF-8.
+    converted: u64,
+    /// Records set aside, in tolerant mode only.
+    rejected: Option<u64>,
+    /// Attributes of the sequences, for SEQUENCE only.
+    computed: Option<Computed>,
+}
 
-    let stmt_result = conn.prepare("COPY sequence FROM STDIN DELIMITER ',' CSV HEADER");
-    let stmt;
-    match stmt_result {
-        Ok(value) => {
-            stmt = value;
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+type Input = Transcoder<Box<dyn BufRead>>;
 
-    let copy_result = stmt.copy_in(&[], &mut file);
-    match copy_result {
-        Ok(_) => {
-            logln("DONE")
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Reads an export as CSV. FASTA files are converted record by record and
+/// decompressed first when gzipped.
+fn export_reader<R: Read + 'static>(input: R, table: &'static Table, path: &str) -> Box<dyn BufRead> {
+    if !fasta::is_fasta(path) {
+        return Box::new(BufReader::new(input));
     }
+    let input: Box<dyn BufRead> = if fasta::is_gzip(path) {
+        Box::new(BufReader::new(MultiGzDecoder::new(input)))
+    } else {
+        Box::new(BufReader::new(input))
+    };
+    Box::new(BufReader::new(fasta::Records::new(input, table)))
 }
 
-fn drop_index(conn: &Connection) {
-    //uniprot_id index
-    log("DROPPING uniprot_id index..."); 
-    let uniprot_id_index_result = conn.execute("DROP INDEX if exists uniprot_id_idx",&[]);
-    match uniprot_id_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+fn export_dialect(config: &Config, table: &Table, path: &str) -> Dialect {
+    if fasta::is_fasta(path) {
+        fasta::dialect()
+    } else {
+        config.dialect(table)
     }
+}
 
-    //protein_name index
-    log("DROPPING protein_name index..."); 
-    let protein_name_index_result = conn.execute("DROP INDEX if exists protein_name_idx",&[]);
-    match protein_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+fn load(conn: &Connection, source: &Source, mut input: Input, options: &ImportOptions) -> Result<Loaded, ImportError> {
+    let (rows, computed) = copy(conn, source, &mut input).map_err(|error| match error {
+        CopyError::Server(error) => copy_failure(source, error, &[], options),
+        CopyError::Record(error) => ImportError::Input(source.path.clone(), error),
+    })?;
+    log_normalization(source.table, &input);
+    Ok(Loaded {
+        rows,
+        converted: input.converted,
+        rejected: None,
+        computed,
+    })
+}
 
-    //gene_name index
-    log("DROPPING gene_name index..."); 
-    let gene_name_index_result = conn.execute("DROP INDEX if exists gene_name_idx",&[]);
-    match gene_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+fn load_tolerant(
+    conn: &Connection,
+    source: &Source,
+    input: Input,
+    limit: RejectLimit,
+    options: &ImportOptions,
+) -> Result<Loaded, ImportError> {
+    let table = source.table;
+    let rejects_path = quarantine::rejects_path(table, Path::new(&source.path));
+    let mut quarantine = Quarantine::new(table, input, &source.dialect, &rejects_path)
+        .map_err(|error| ImportError::Input(rejects_path.display().to_string(), error))?;
+
+    let copy_result = copy(conn, source, &mut quarantine);
+    let flushed = quarantine.finish();
+    let (rows, computed) = match (copy_result, quarantine.error.take(), flushed) {
+        (_, Some(error), _) | (Err(CopyError::Record(error)), None, _) | (Ok(_), None, Err(error)) => {
+            return Err(ImportError::Input(source.path.clone(), error))
         }
+        (Err(CopyError::Server(error)), None, _) => return Err(copy_failure(source, error, &quarantine.skipped, options)),
+        (Ok(copied), None, Ok(())) => copied,
+    };
+
+    let (total, rejected) = (quarantine.total, quarantine.rejected);
+    if rejected > 0 {
+        warn!(
+            table = table.name, rejected = rejected;
+            "{}: {} of {} rows rejected, see {}", table.name, rejected, total, rejects_path.display()
+        );
     }
-
-    //role_as_enzyme  index
-    log("DROPPING role_as_enzyme index..."); 
-    let role_as_enzyme_index_result = conn.execute("DROP INDEX if exists role_as_enzyme_idx",&[]);
-    match role_as_enzyme_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+    if limit.exceeded(rejected, total) {
+        return Err(ImportError::TooManyRejects(table.name.to_string(), rejected, total, limit));
     }
 
-    //role_as_substrate index
-    log("DROPPING role_as_substrate index..."); 
-    let role_as_substrate_index_result = conn.execute("DROP INDEX if exists role_as_substrate_idx",&[]);
-    match role_as_substrate_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+    let input = quarantine.into_inner();
+    log_normalization(table, &input);
+    Ok(Loaded {
+        rows,
+        converted: input.converted,
+        rejected: Some(rejected),
+        computed,
+    })
+}
 
-    //taxon_code index
-    log("DROPPING taxon_code index..."); 
-    let taxon_code_index_result = conn.execute("DROP INDEX if exists taxon_code_idx",&[]);
-    match taxon_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+fn log_normalization(table: &Table, input: &Input) {
+    if input.stripped_bom {
+        debug!(table = table.name; "{}: removed byte order mark", table.name);
+    }
+    if input.crlf_lines > 0 {
+        debug!(table = table.name; "{}: replaced CRLF line endings on {} records", table.name, input.crlf_lines);
     }
+}
 
-    //iptm_entry_code index
-    log("DROPPING iptm_entry_code index..."); 
-    let iptm_entry_code_index_result = conn.execute("DROP INDEX if exists iptm_entry_code_idx",&[]);
-    match iptm_entry_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Adds the failing record of the export to a COPY error, when the server
+/// names its line.
+fn copy_failure(source: &Source, error: postgres::Error, skipped: &[(u64, u64)], options: &ImportOptions) -> ImportError {
+    let context = error
+        .as_db()
+        .and_then(|db| db.where_.as_ref())
+        .and_then(|context| locate::parse_context(context));
+    let context = match context {
+        Some(ref context) if context.table.eq_ignore_ascii_case(source.table.name) => context,
+        _ => return ImportError::from(error),
+    };
+    if fasta::is_fasta(&source.path) {
+        // The lines COPY counts are records, not lines of the file.
+        return ImportError::from(error);
     }
-    
-    //sub_code index
-    log("DROPPING sub_code index..."); 
-    let sub_code_index_result = conn.execute("DROP INDEX if exists sub_code_idx",&[]);
-    match sub_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
-    
-    //residue index
-    log("DROPPING residue index..."); 
-    let residue_index_result = conn.execute("DROP INDEX if exists residue_idx",&[]);
-    match residue_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    let line = locate::source_line(context.line, skipped);
+    let located = File::open(&source.path).and_then(|file| {
+        let input = Transcoder::new(BufReader::new(file), options.encoding, &source.dialect);
+        let column = context.column.as_deref();
+        locate::locate(source.table, input, &source.dialect, &source.path, line, column)
+    });
+    match located {
+        Ok(Some(location)) => ImportError::Copy(error, location),
+        Ok(None) => ImportError::from(error),
+        Err(read_error) => {
+            warn!("Could not read {} to find the failing record - {}", source.path, read_error);
+            ImportError::from(error)
         }
     }
+}
 
-    //position index
-    log("DROPPING position index..."); 
-    let position_index_result = conn.execute("DROP INDEX if exists position_idx",&[]);
-    match position_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Why COPY failed: the server rejected the data, or a record of the export
+/// was rejected on its way, before the server saw it.
+enum CopyError {
+    Server(postgres::Error),
+    Record(io::Error),
+}
+
+impl From<postgres::Error> for CopyError {
+    fn from(error: postgres::Error) -> CopyError {
+        CopyError::Server(error)
     }
+}
 
-    //enz_code null index
-    log("DROPPING enz_code_null index..."); 
-    let ptm_enzyme_index_result = conn.execute("DROP INDEX if exists enz_code_null_idx",&[]);
-    match ptm_enzyme_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Streams the export into COPY. Computed columns are appended to every
+/// record on the way, and what was computed is returned with the rows.
+fn copy<R: Read>(conn: &Connection, source: &Source, reader: &mut R) -> Result<(u64, Option<Computed>), CopyError> {
+    let columns: Vec<&str> = source.table.all_columns().map(|column| column.name).collect();
+    let sql = format!(
+        "COPY {} ({}) FROM STDIN WITH ({})",
+        source.table.name,
+        columns.join(", "),
+        source.dialect.copy_options()
+    );
+    let stmt = conn.prepare(&sql)?;
+    if source.table.computed.is_empty() {
+        return Ok((stmt.copy_in(&[], reader)?, None));
     }
+    // Only SEQUENCE has computed columns.
+    let mut appender = attributes::Appender::new(source.table, BufReader::new(reader), &source.dialect);
+    match (stmt.copy_in(&[], &mut appender), appender.error.take()) {
+        (_, Some(error)) => Err(CopyError::Record(error)),
+        (Err(error), None) => Err(CopyError::Server(error)),
+        (Ok(rows), None) => Ok((rows, Some(appender.computed))),
+    }
+}
 
-    //SUB_FORM_CODE index
-    log("DROPPING SUB_FORM_CODE index..."); 
-    let sub_form_index_result = conn.execute("DROP INDEX if exists sub_form_code_idx",&[]);
-    match sub_form_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Ends the run: records the outcome, writes the report and metrics, and
+/// exits with an error status if the import failed.
+fn finish(report: &mut Report, metrics: &MetricsTargets, error: Option<ImportError>) {
+    if let Some(ref error) = error {
+        error!(error_class = error.class(); "{}", error);
     }
+    report.complete(error.as_ref());
 
-    //EVENT_NAME index
-    log("DROPPING EVENT_NAME index..."); 
-    let event_name_index_result = conn.execute("DROP INDEX if exists event_name_idx",&[]);
-    match event_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+    if let Err(error) = report.write() {
+        error!("Could not write report - {}", error);
+    }
+    if !metrics.is_empty() {
+        if let Err(error) = metrics::export(report, metrics) {
+            error!("Could not export metrics - {}", error);
         }
     }
 
-    //SEQ_ID index
-    log("DROPPING SEQ_ID index...");
-    let event_name_index_result = conn.execute("DROP INDEX IF EXISTS seq_id_idx",&[]);
-    match event_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-            io::stdout().flush().unwrap();
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+    if error.is_some() {
+        std::process::exit(-1);
     }
 }
 
-fn create_index(conn: &Connection) {
-    //uniprot_id index
-    log("CREATING uniprot_id index..."); 
-    let uniprot_id_index_result = conn.execute("CREATE INDEX uniprot_id_idx on MV_ENTRY (uniprot_id)",&[]);
-    match uniprot_id_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
+/// Rows a step produced or checked, the rows it rejected and the warning to
+/// log about them.
+type Outcome = (u64, Option<u64>, Option<String>);
+
+/// Runs one of the steps that fill or check tables once they are populated.
+/// The step is timed and its counts go to the report, or it is marked as
+/// failed when `run` fails.
+fn run_step<F>(report: &mut Report, step: &str, target: &str, run: F) -> Result<(), ImportError>
+where
+    F: FnOnce() -> Result<Outcome, ImportError>,
+{
+    debug!(step = step, target = target; "{} {}...", step_label(step), target);
+    let timer = report.start(step, target);
+    let (rows, rejected, warning) = match run() {
+        Ok(outcome) => outcome,
         Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+            report.fail_step(timer);
+            return Err(error);
         }
+    };
+    if let Some(warning) = warning {
+        warn!(step = step, target = target, rejected = rejected.unwrap_or(0); "{}", warning);
     }
+    let done = report.finish(timer, Some(rows), None);
+    done.rejected = rejected;
+    info!(
+        step = step, target = target, rows = rows, duration_seconds = done.duration_seconds;
+        "{} {}...DONE", step_label(step), target
+    );
+    Ok(())
+}
 
-    //protein_name index
-    log("CREATING protein_name index..."); 
-    let protein_name_index_result = conn.execute("CREATE INDEX protein_name_idx on MV_ENTRY (protein_name)",&[]);
-    match protein_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Checks the identifiers of one column and reports the invalid ones.
+fn validate_identifiers(conn: &Connection, report: &mut Report, identifiers: &identifiers::Identifiers) -> Result<(), ImportError> {
+    let source = identifiers.source();
+    run_step(report, "validate", &source, || {
+        let validated = identifiers.validate(conn)?;
+        let warning = if validated.invalid > 0 {
+            Some(format!(
+                "{}: {} invalid values, expected {}, e.g. {}",
+                source, validated.invalid, identifiers.describe(), validated.examples.join(", ")
+            ))
+        } else {
+            None
+        };
+        Ok((validated.values, Some(validated.invalid), warning))
+    })
+}
 
-    //gene_name index
-    log("CREATING gene_name index..."); 
-    let gene_name_index_result = conn.execute("CREATE INDEX gene_name_idx on MV_ENTRY (gene_name)",&[]);
-    match gene_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// Fills the lookup table of a vocabulary and deals with the values of its
+/// columns that are not in it.
+fn check_vocabulary(conn: &Connection, report: &mut Report, vocabulary: &Vocabulary) -> Result<(), ImportError> {
+    let lookup = vocabulary.lookup.name;
+    conn.execute(&vocabulary.mark_statement(), &[])?;
+    execute(conn, report, "vocabulary", lookup, &vocabulary.fill_statement())?;
+    for member in &vocabulary.members {
+        let source = member.source();
+        run_step(report, "check", &source, || {
+            let checked = vocabulary.check(conn, member)?;
+            let examples = checked.examples.join(", ");
+            match vocabulary.on_unknown {
+                OnUnknown::Fail if checked.unknown > 0 => {
+                    Err(ImportError::Unknown(source.clone(), lookup, checked.unknown_rows, examples))
+                }
+                OnUnknown::Fail => Ok((checked.values, None, None)),
+                OnUnknown::Warn => {
+                    let warning = if checked.unknown > 0 {
+                        Some(format!(
+                            "{}: {} rows with values not in {}, e.g. {}",
+                            source, checked.unknown_rows, lookup, examples
+                        ))
+                    } else {
+                        None
+                    };
+                    Ok((checked.values, Some(checked.unknown_rows), warning))
+                }
+                OnUnknown::AutoAdd => {
+                    if checked.unknown > 0 {
+                        let added = vocabulary.add(conn, member)?;
+                        info!(
+                            step = "check", target = source.as_str();
+                            "{}: added {} values to {}, e.g. {}", source, added, lookup, examples
+                        );
+                    }
+                    Ok((checked.values, None, None))
+                }
+            }
+        })?;
     }
+    Ok(())
+}
 
-    //role_as_enzyme  index
-    log("CREATING role_as_enzyme index..."); 
-    let role_as_enzyme_index_result = conn.execute("CREATE INDEX role_as_enzyme_idx on MV_ENTRY (role_as_enzyme)",&[]);
-    match role_as_enzyme_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Fills EVENT_PMID from one PMID list and reports the tokens that are not
+/// PMIDs.
+fn link_pmids(conn: &Connection, report: &mut Report, list: &pmid::List) -> Result<(), ImportError> {
+    let source = list.source();
+    run_step(report, "link", &source, || {
+        let linked = list.link(conn)?;
+        let warning = if linked.malformed > 0 {
+            Some(format!(
+                "{}: {} malformed PMIDs left out, e.g. {}",
+                source, linked.malformed, linked.examples.join(", ")
+            ))
+        } else {
+            None
+        };
+        Ok((linked.links, Some(linked.malformed), warning))
+    })
+}
 
-    //role_as_substrate index
-    log("CREATING role_as_substrate index..."); 
-    let role_as_substrate_index_result = conn.execute("CREATE INDEX role_as_substrate_idx on MV_ENTRY (role_as_substrate)",&[]);
-    match role_as_substrate_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Fills SITE from one site list and reports the tokens that are not sites.
+fn parse_sites(conn: &Connection, report: &mut Report, list: &sites::List) -> Result<(), ImportError> {
+    let source = list.source();
+    run_step(report, "site", &source, || {
+        let parsed = list.parse(conn)?;
+        let warning = if parsed.unparseable > 0 {
+            Some(format!(
+                "{}: {} unparseable sites left out, e.g. {}",
+                source, parsed.unparseable, parsed.examples.join(", ")
+            ))
+        } else {
+            None
+        };
+        Ok((parsed.sites, Some(parsed.unparseable), warning))
+    })
+}
 
-    //taxon_code index
-    log("CREATING taxon_code index..."); 
-    let taxon_code_index_result = conn.execute("CREATE INDEX taxon_code_idx on MV_ENTRY (taxon_code)",&[]);
-    match taxon_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
+/// Checks MV_EVENT.RESIDUE and POSITION against SEQUENCE. Problems are
+/// reported per SOURCE_LABEL but never fail the import.
+fn check_residues(conn: &Connection, report: &mut Report) -> Result<(), ImportError> {
+    let mut sources = Vec::new();
+    run_step(report, "qa", "MV_EVENT", || {
+        let check = residues::check(conn)?;
+        for source in check.sources.iter() {
+            if source.checked == source.matched {
+                info!(step = "qa", target = "MV_EVENT"; "{}", source);
+            } else {
+                warn!(step = "qa", target = "MV_EVENT"; "{}", source);
+            }
         }
-    }
+        let warning = if check.examples.is_empty() {
+            None
+        } else {
+            Some(format!("Residues not matching SEQUENCE, e.g. {}", check.examples.join("; ")))
+        };
+        let outcome = (check.checked(), Some(check.problems()), warning);
+        sources = check.sources;
+        Ok(outcome)
+    })?;
+    report.residues = sources;
+    Ok(())
+}
 
-    //iptm_entry_code index
-    log("CREATING iptm_entry_code index..."); 
-    let iptm_entry_code_index_result = conn.execute("CREATE INDEX iptm_entry_code_idx on MV_ENTRY (iptm_entry_code)",&[]);
-    match iptm_entry_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
-    
-    //sub_code index
-    log("CREATING sub_code index..."); 
-    let sub_code_index_result = conn.execute("CREATE INDEX sub_code_idx on MV_EVENT (sub_code)",&[]);
-    match sub_code_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
-    
-    //residue index
-    log("CREATING residue index..."); 
-    let residue_index_result = conn.execute("CREATE INDEX residue_idx on MV_EVENT (residue)",&[]);
-    match residue_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Fills SITE_WINDOW with the window of every matched residue.
+fn build_windows(conn: &Connection, report: &mut Report, flank: u32) -> Result<(), ImportError> {
+    run_step(report, "window", schema::SITE_WINDOW.name, || {
+        Ok((windows::build(conn, flank)?, None, None))
+    })
+}
 
-    //position index
-    log("CREATING position index..."); 
-    let position_index_result = conn.execute("CREATE INDEX position_idx on MV_EVENT (position)",&[]);
-    match position_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Fills EVENT_TYPE_ONTOLOGY from a mapping or OBO file and reports the
+/// event types without a term.
+fn map_ontology(conn: &Connection, report: &mut Report, path: &str) -> Result<(), ImportError> {
+    let target = schema::EVENT_TYPE_ONTOLOGY.name;
+    run_step(report, "ontology", target, || {
+        let mappings = ontology::read(path).map_err(|error| ImportError::Input(path.to_string(), error))?;
+        let mapped = ontology::map(conn, &mappings)?;
+        let warning = if mapped.unmapped > 0 {
+            Some(format!(
+                "{}: {} EVENT_NAME and RESIDUE combinations without a term, e.g. {}",
+                target, mapped.unmapped, mapped.examples.join("; ")
+            ))
+        } else {
+            None
+        };
+        Ok((mapped.links, Some(mapped.unmapped), warning))
+    })
+}
 
-    //enz_code null index
-    log("CREATING enz_code_null index..."); 
-    let ptm_enzyme_index_result = conn.execute("CREATE INDEX enz_code_null_idx on MV_EVENT (enz_code)",&[]);
-    match ptm_enzyme_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+fn step_label(step: &str) -> &'static str {
+    match step {
+        "begin" => "STARTING",
+        "drop" => "DROPPING",
+        "create" | "index" | "search" => "CREATING",
+        "populate" => "POPULATING",
+        "commit" => "COMMITTING",
+        "validate" => "VALIDATING",
+        "vocabulary" => "FILLING",
+        "check" => "CHECKING",
+        "constrain" => "CONSTRAINING",
+        "link" => "LINKING",
+        "site" => "PARSING",
+        "qa" => "CHECKING",
+        "window" => "BUILDING",
+        "ontology" => "MAPPING",
+        "cluster" => "CLUSTERING",
+        "analyze" => "ANALYZING",
+        "vacuum" => "VACUUMING",
+        _ => "RUNNING",
     }
+}
 
-    //SUB_FORM_CODE index
-    log("CREATING SUB_FORM_CODE index..."); 
-    let sub_form_index_result = conn.execute("CREATE INDEX sub_form_code_idx on MV_EVENT (SUB_FORM_CODE)",&[]);
-    match sub_form_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
-    }
+/// Counts the bytes handed to COPY for the report.
+struct CountingReader<R> {
+    inner: R,
+    bytes: Rc<Cell<u64>>,
+}
 
-    //EVENT_NAME index
-    log("CREATING EVENT_NAME index..."); 
-    let event_name_index_result = conn.execute("CREATE INDEX event_name_idx on MV_EVENT (EVENT_NAME)",&[]);
-    match event_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.bytes.set(self.bytes.get() + count as u64);
+        Ok(count)
     }
+}
 
-    //SEQ_ID index
-    log("CREATING SEQ_ID index..."); 
-    let event_name_index_result = conn.execute("CREATE INDEX seq_id_idx on SEQUENCE (ID)",&[]);
-    match event_name_index_result {
-        Ok(_value) => {
-            logln("DONE");
-        },
-        Err(error) => {
-            error!("{}",error);
-            std::process::exit(-1);
-        }
+/// The tables read from exports followed by those built from them. Tables
+/// of optional steps that are not run are left out.
+fn built_tables(options: &ImportOptions) -> Vec<&'static Table> {
+    let mut tables = options.config.tables();
+    tables.extend(options.config.vocabularies().iter().map(|vocabulary| vocabulary.lookup));
+    tables.extend(
+        schema::DERIVED
+            .iter()
+            .cloned()
+            .filter(|table| table.name != schema::EVENT_PMID.name || options.pmids)
+            .filter(|table| table.name != schema::SITE.name || options.sites)
+            .filter(|table| table.name != schema::SITE_WINDOW.name || options.window_flank.is_some())
+            .filter(|table| table.name != schema::EVENT_TYPE_ONTOLOGY.name || options.ontology.is_some()),
+    );
+    tables
+}
+
+fn drop_tables(conn: &Connection, report: &mut Report, config: &Config, timeouts: &Timeouts) -> Result<(), ImportError> {
+    // Derived tables are dropped even when their step is not run, so that
+    // they are never left behind out of date.
+    // Lookup tables go last, once the tables referencing them are gone.
+    let lookups = config.vocabularies().iter().map(|vocabulary| vocabulary.lookup);
+    for table in config.tables().into_iter().chain(schema::DERIVED.iter().cloned()).chain(lookups) {
+        execute_ddl(conn, report, timeouts, "drop", table.name, &format!("DROP TABLE IF EXISTS {};", table.name))?;
     }
+    // So are those of vocabularies that were removed from the config.
+    for name in vocabulary::stale_lookups(conn, config.vocabularies())? {
+        execute_ddl(conn, report, timeouts, "drop", &name, &format!("DROP TABLE IF EXISTS {};", name))?;
+    }
+    // Without --search the function would be left reading a SEARCH column
+    // that no longer exists.
+    execute_ddl(conn, report, timeouts, "drop", "search_entries", &search::drop_statement())?;
+    Ok(())
 }
 
-fn log(msg: &str){
-    print!("{}",msg);
-    io::stdout().flush().unwrap(); 
+fn drop_index(conn: &Connection, report: &mut Report, config: &Config, timeouts: &Timeouts) -> Result<(), ImportError> {
+    // The foreign keys of the vocabularies depend on the indexes of the
+    // lookup tables, which go away with their tables once the referencing
+    // tables are dropped.
+    let lookups: Vec<&str> = config.vocabularies().iter().map(|vocabulary| vocabulary.lookup.name).collect();
+    for index in config.indexes().into_iter().filter(|index| !lookups.contains(&index.table)) {
+        execute_ddl(conn, report, timeouts, "drop", index.name, &index.drop_statement())?;
+    }
+    Ok(())
 }
 
-fn logln(msg: &str){
-    println!("{}",msg);
-    io::stdout().flush().unwrap(); 
-}
\ No newline at end of file
+fn create_index(conn: &Connection, report: &mut Report, config: &Config) -> Result<(), ImportError> {
+    for extension in &config.extensions {
+        execute(conn, report, "index", extension, &format!("CREATE EXTENSION IF NOT EXISTS {}", extension))?;
+    }
+    for index in config.indexes() {
+        execute(conn, report, "index", index.name, &index.create_statement())?;
+    }
+    Ok(())
+}
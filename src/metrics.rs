@@ -0,0 +1,310 @@
+//! Prometheus metrics for import runs.
+//!
+//! At the end of every run the importer can write a node-exporter textfile
+//! collector file and/or push the same metrics to a Pushgateway. Values that
+//! must survive across runs (the time of the last successful import, the row
+//! counts currently loaded and the failure counters) are read back from the
+//! previous textfile or from the Pushgateway before they are updated, so a
+//! failed run never hides when the data was last refreshed.
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use report::{Outcome, Report};
+
+/// Job label under which the metrics are pushed to a Pushgateway.
+pub const JOB: &str = "iptmnet_importer";
+
+const LAST_RUN: &str = "iptmnet_import_last_run_timestamp_seconds";
+const LAST_RUN_SUCCESS: &str = "iptmnet_import_last_run_success";
+const LAST_SUCCESS: &str = "iptmnet_import_last_success_timestamp_seconds";
+const DURATION: &str = "iptmnet_import_duration_seconds";
+const ROWS: &str = "iptmnet_import_rows";
+const BYTES: &str = "iptmnet_import_bytes_processed";
+const REJECTED: &str = "iptmnet_import_rejected_rows";
+const FAILURES: &str = "iptmnet_import_failures_total";
+
+// (name, type, help)
+const METRICS: [(&str, &str, &str); 8] = [
+    (LAST_RUN, "gauge", "Unix time at which the last import run ended."),
+    (LAST_RUN_SUCCESS, "gauge", "1 if the last import run succeeded, 0 if it failed."),
+    (LAST_SUCCESS, "gauge", "Unix time at which the last successful import run ended."),
+    (DURATION, "gauge", "Duration of the last import run in seconds."),
+    (ROWS, "gauge", "Rows loaded per table by the last successful import run."),
+    (BYTES, "gauge", "Bytes of CSV input read per table by the last import run."),
+    (REJECTED, "gauge", "Rows per table set aside by the last import run in tolerant mode."),
+    (FAILURES, "counter", "Failed import runs by error class."),
+];
+
+pub struct MetricsTargets {
+    pub file: Option<PathBuf>,
+    pub pushgateway: Option<String>,
+}
+
+impl MetricsTargets {
+    pub fn is_empty(&self) -> bool {
+        self.file.is_none() && self.pushgateway.is_none()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Sample {
+    name: String,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+impl Sample {
+    fn new(name: &str, labels: &[(&str, &str)], value: f64) -> Sample {
+        Sample {
+            name: name.to_string(),
+            labels: labels.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+            value,
+        }
+    }
+
+    fn label(&self, name: &str) -> Option<&str> {
+        self.labels.iter().find(|label| label.0 == name).map(|label| label.1.as_str())
+    }
+}
+
+/// Writes the metrics of a finished run to every configured target.
+pub fn export(report: &Report, targets: &MetricsTargets) -> io::Result<()> {
+    if let Some(ref path) = targets.file {
+        let previous = match fs::read_to_string(path) {
+            Ok(text) => parse(&text),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+        let text = render(&samples(report, &previous, now()));
+
+        // The collector may read the directory at any time, so never let it
+        // see a half written file.
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        File::create(&partial)?.write_all(text.as_bytes())?;
+        fs::rename(&partial, path)?;
+    }
+
+    if let Some(ref url) = targets.pushgateway {
+        let url = url.trim_end_matches('/');
+        let body = http("GET", &format!("{}/metrics", url), b"")?;
+        let previous: Vec<Sample> = parse(&body)
+            .into_iter()
+            .filter(|sample| sample.label("job") == Some(JOB))
+            .map(|mut sample| {
+                sample.labels.retain(|label| label.0 != "job" && label.0 != "instance");
+                sample
+            })
+            .collect();
+        let text = render(&samples(report, &previous, now()));
+        http("PUT", &format!("{}/metrics/job/{}", url, JOB), text.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn samples(report: &Report, previous: &[Sample], now: f64) -> Vec<Sample> {
+    let success = report.outcome == Outcome::Success;
+    let mut samples = vec![
+        Sample::new(LAST_RUN, &[], now),
+        Sample::new(LAST_RUN_SUCCESS, &[], if success { 1.0 } else { 0.0 }),
+    ];
+
+    if success {
+        samples.push(Sample::new(LAST_SUCCESS, &[], now));
+    } else {
+        samples.extend(previous.iter().filter(|sample| sample.name == LAST_SUCCESS).cloned());
+    }
+
+    samples.push(Sample::new(DURATION, &[], report.duration_seconds));
+
+    // A failed run is rolled back, so the tables still hold what the last
+    // successful run loaded.
+    if success {
+        for step in report.steps.iter().filter(|step| step.step == "populate") {
+            let table = step.target.to_lowercase();
+            samples.push(Sample::new(ROWS, &[("table", &table)], step.rows.unwrap_or(0) as f64));
+        }
+    } else {
+        samples.extend(previous.iter().filter(|sample| sample.name == ROWS).cloned());
+    }
+
+    for step in report.steps.iter().filter(|step| step.step == "populate") {
+        if let Some(bytes) = step.bytes {
+            let table = step.target.to_lowercase();
+            samples.push(Sample::new(BYTES, &[("table", &table)], bytes as f64));
+        }
+    }
+
+    for step in report.steps.iter().filter(|step| step.step == "populate") {
+        if let Some(rejected) = step.rejected {
+            let table = step.target.to_lowercase();
+            samples.push(Sample::new(REJECTED, &[("table", &table)], rejected as f64));
+        }
+    }
+
+    let mut failures: Vec<Sample> = previous.iter().filter(|sample| sample.name == FAILURES).cloned().collect();
+    if let Some(ref class) = report.error_class {
+        match failures.iter_mut().find(|sample| sample.label("class") == Some(class)) {
+            Some(sample) => sample.value += 1.0,
+            None => failures.push(Sample::new(FAILURES, &[("class", class)], 1.0)),
+        }
+    }
+    samples.extend(failures);
+
+    samples
+}
+
+/// Renders samples in the Prometheus text exposition format.
+fn render(samples: &[Sample]) -> String {
+    let mut text = String::new();
+    for &(name, kind, help) in METRICS.iter() {
+        let family: Vec<&Sample> = samples.iter().filter(|sample| sample.name == name).collect();
+        if family.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+        for sample in family {
+            text.push_str(name);
+            if !sample.labels.is_empty() {
+                let labels: Vec<String> = sample
+                    .labels
+                    .iter()
+                    .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                    .collect();
+                text.push_str(&format!("{{{}}}", labels.join(",")));
+            }
+            text.push_str(&format!(" {}\n", sample.value));
+        }
+    }
+    text
+}
+
+/// Parses the samples of a text exposition, skipping anything it does not
+/// understand. Only used to read back our own metrics.
+fn parse(text: &str) -> Vec<Sample> {
+    text.lines().filter_map(|line| parse_line(line.trim())).collect()
+}
+
+fn parse_line(line: &str) -> Option<Sample> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let name_end = line.find(['{', ' '])?;
+    let name = line[..name_end].to_string();
+    let mut rest = &line[name_end..];
+    let mut labels = Vec::new();
+
+    if rest.starts_with('{') {
+        rest = &rest[1..];
+        loop {
+            rest = rest.trim_start_matches([',', ' ']);
+            if rest.starts_with('}') {
+                rest = &rest[1..];
+                break;
+            }
+            let eq = rest.find('=')?;
+            let key = rest[..eq].trim().to_string();
+            rest = rest[eq + 1..].strip_prefix('"')?;
+            let mut value = String::new();
+            let mut chars = rest.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (_, '\\') => match chars.next()?.1 {
+                        'n' => value.push('\n'),
+                        other => value.push(other),
+                    },
+                    (index, '"') => break index,
+                    (_, other) => value.push(other),
+                }
+            };
+            labels.push((key, value));
+            rest = &rest[end + 1..];
+        }
+    }
+
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Sample { name, labels, value })
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn now() -> f64 {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1e3
+}
+
+/// Minimal HTTP/1.0 client, enough to talk to a Pushgateway on the local
+/// network without pulling in an HTTP stack. Returns the body of a 2xx
+/// response and an error for any other status.
+fn http(method: &str, url: &str, body: &[u8]) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported Pushgateway URL {}", url));
+    let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    let address = address.to_socket_addrs()?.next().ok_or_else(invalid)?;
+
+    let timeout = Duration::from_secs(10);
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n",
+        method,
+        path,
+        authority,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let malformed = || {
+        let line = response.lines().next().unwrap_or_default();
+        io::Error::new(io::ErrorKind::InvalidData, format!("malformed Pushgateway response: {:?}", line))
+    };
+    let (head, body) = match response.find("\r\n\r\n") {
+        Some(index) => (&response[..index], &response[index + 4..]),
+        None => return Err(malformed()),
+    };
+    let status = status(head.lines().next().unwrap_or_default()).ok_or_else(malformed)?;
+    if status / 100 != 2 {
+        return Err(io::Error::other(format!(
+            "Pushgateway {} failed with status {}: {}",
+            method,
+            status,
+            body.trim()
+        )));
+    }
+    Ok(body.to_string())
+}
+
+/// The code of a status line such as `HTTP/1.1 202 Accepted`.
+fn status(line: &str) -> Option<u16> {
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next()?;
+    let code = parts.next()?;
+    if !version.starts_with("HTTP/1.") || code.len() != 3 || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    code.parse().ok().filter(|code| (100..600).contains(code))
+}
@@ -0,0 +1,274 @@
+//! Optional transform, run with `--ontology <FILE>`, that maps the event
+//! types of MV_EVENT to PSI-MOD terms in the EVENT_TYPE_ONTOLOGY table.
+//!
+//! The events are named after iPTMnet's own vocabulary, e.g.
+//! `Phosphorylation` on `S`, while users query by ontology terms such as
+//! `MOD:00046` (O-phospho-L-serine). The mapping is read either from a
+//! curated CSV or TSV file with the columns EVENT_NAME, RESIDUE, MOD_ID and
+//! optionally MOD_NAME, or from a local copy of `PSI-MOD.obo`. Every
+//! distinct EVENT_NAME and RESIDUE of the events is linked to the terms it
+//! maps to, and the combinations without a term are reported.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+
+use postgres::Connection;
+
+use csv::{Dialect, Reader};
+use error::ImportError;
+use schema::{btree, key, Index, EVENT_TYPE_ONTOLOGY};
+
+/// Event names of the PSI-MS labels of PSI-MOD, e.g. `Phospho`. Terms with
+/// other labels are not mapped from an OBO file.
+pub const LABELS: [(&str, &str); 7] = [
+    ("Phospho", "Phosphorylation"),
+    ("Acetyl", "Acetylation"),
+    ("Methyl", "Methylation"),
+    ("GlyGly", "Ubiquitination"),
+    ("Nitrosyl", "S-Nitrosylation"),
+    ("Myristoyl", "Myristoylation"),
+    ("Palmitoyl", "Palmitoylation"),
+];
+
+/// Created once the table is filled. They go away with the table.
+pub const INDEXES: [Index; 2] = [
+    btree("event_type_ontology_event_idx", "EVENT_TYPE_ONTOLOGY", &[key("EVENT_NAME"), key("RESIDUE")]),
+    btree("event_type_ontology_mod_idx", "EVENT_TYPE_ONTOLOGY", &[key("MOD_ID")]),
+];
+
+/// Number of unmapped combinations quoted in the warning.
+const EXAMPLES: i64 = 5;
+
+pub struct Mapping {
+    pub event_name: String,
+    pub residue: String,
+    pub mod_id: String,
+    pub mod_name: Option<String>,
+}
+
+fn is_obo(path: &str) -> bool {
+    path.to_lowercase().ends_with(".obo")
+}
+
+/// Reads the mappings of a mapping file or an OBO file.
+pub fn read(path: &str) -> io::Result<Vec<Mapping>> {
+    let input = BufReader::new(File::open(path)?);
+    if is_obo(path) {
+        return read_obo(input);
+    }
+    let lower = path.to_lowercase();
+    let dialect = if lower.ends_with(".tsv") || lower.ends_with(".txt") {
+        Dialect {
+            delimiter: b'\t',
+            ..Dialect::default()
+        }
+    } else {
+        Dialect::default()
+    };
+    read_mapping(input, &dialect)
+}
+
+/// Reads a mapping file with a header naming its columns.
+pub fn read_mapping<R: BufRead>(input: R, dialect: &Dialect) -> io::Result<Vec<Mapping>> {
+    let mut reader = Reader::new(input, dialect);
+    let header = match reader.read_record()? {
+        Some(header) => header,
+        None => return Ok(Vec::new()),
+    };
+    let names: Vec<String> = header
+        .fields
+        .iter()
+        .map(|field| String::from_utf8_lossy(&field.value).trim().to_uppercase())
+        .collect();
+    let position = |name: &str| names.iter().position(|candidate| candidate == name);
+    let (event_name, residue, mod_id) = match (position("EVENT_NAME"), position("RESIDUE"), position("MOD_ID")) {
+        (Some(event_name), Some(residue), Some(mod_id)) => (event_name, residue, mod_id),
+        _ => return Err(invalid(1, "the header must name EVENT_NAME, RESIDUE and MOD_ID")),
+    };
+    let mod_name = position("MOD_NAME");
+
+    let mut mappings = Vec::new();
+    while let Some(record) = reader.read_record()? {
+        let value = |index: usize| {
+            record
+                .fields
+                .get(index)
+                .map(|field| String::from_utf8_lossy(&field.value).trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        if record.fields.iter().all(|field| field.value.iter().all(u8::is_ascii_whitespace)) {
+            continue;
+        }
+        let mapping = match (value(event_name), value(residue), value(mod_id)) {
+            (Some(event_name), Some(residue), Some(mod_id)) => Mapping {
+                event_name,
+                residue: residue.to_uppercase(),
+                mod_id,
+                mod_name: mod_name.and_then(value),
+            },
+            _ => return Err(invalid(record.line, "EVENT_NAME, RESIDUE and MOD_ID must not be empty")),
+        };
+        if !is_residue(&mapping.residue) {
+            return Err(invalid(record.line, &format!("RESIDUE must be a single letter, got {:?}", mapping.residue)));
+        }
+        mappings.push(mapping);
+    }
+    Ok(mappings)
+}
+
+/// Reads the terms of PSI-MOD that modify a single residue and carry one of
+/// the PSI-MS labels of `LABELS`. Obsolete and terminal modifications are
+/// left out.
+pub fn read_obo<R: BufRead>(input: R) -> io::Result<Vec<Mapping>> {
+    #[derive(Default)]
+    struct Term {
+        id: Option<String>,
+        name: Option<String>,
+        label: Option<String>,
+        origin: Option<String>,
+        terminal: bool,
+        obsolete: bool,
+    }
+
+    fn finish(term: Term, mappings: &mut Vec<Mapping>) {
+        if term.obsolete || term.terminal {
+            return;
+        }
+        let event_name = term
+            .label
+            .and_then(|label| LABELS.iter().find(|&&(candidate, _)| candidate == label))
+            .map(|&(_, event_name)| event_name);
+        if let (Some(id), Some(event_name), Some(origin)) = (term.id, event_name, term.origin) {
+            if is_residue(&origin) {
+                mappings.push(Mapping {
+                    event_name: event_name.to_string(),
+                    residue: origin,
+                    mod_id: id,
+                    mod_name: term.name,
+                });
+            }
+        }
+    }
+
+    let mut mappings = Vec::new();
+    // `None` outside of [Term] stanzas, e.g. in the header or [Typedef].
+    let mut term: Option<Term> = None;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.starts_with('[') {
+            if let Some(term) = term.take() {
+                finish(term, &mut mappings);
+            }
+            if line == "[Term]" {
+                term = Some(Term::default());
+            }
+            continue;
+        }
+        let term = match term.as_mut() {
+            Some(term) => term,
+            None => continue,
+        };
+        let (key, value) = match line.find(':') {
+            Some(colon) => (&line[..colon], line[colon + 1..].trim()),
+            None => continue,
+        };
+        match key {
+            "id" => term.id = Some(value.to_string()),
+            "name" => term.name = Some(value.to_string()),
+            "is_obsolete" => term.obsolete = value == "true",
+            // synonym: "Phospho" RELATED PSI-MS-label []
+            "synonym" if value.contains(" PSI-MS-label") => term.label = quoted(value),
+            "xref" => {
+                if let Some(origin) = value.strip_prefix("Origin:") {
+                    term.origin = quoted(origin);
+                } else if let Some(spec) = value.strip_prefix("TermSpec:") {
+                    term.terminal = quoted(spec).is_some_and(|spec| spec != "none");
+                }
+            }
+            _ => (),
+        }
+    }
+    if let Some(term) = term {
+        finish(term, &mut mappings);
+    }
+    Ok(mappings)
+}
+
+/// The first double-quoted string of a value.
+fn quoted(value: &str) -> Option<String> {
+    let start = value.find('"')? + 1;
+    let end = value[start..].find('"')? + start;
+    Some(value[start..end].to_string())
+}
+
+fn is_residue(value: &str) -> bool {
+    value.len() == 1 && value.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn invalid(line: u64, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+pub struct Mapped {
+    /// Rows of EVENT_TYPE_ONTOLOGY.
+    pub links: u64,
+    /// Distinct EVENT_NAME and RESIDUE combinations of the events without
+    /// a term.
+    pub unmapped: u64,
+    /// Some of the unmapped combinations, e.g. `Sumoylation K (12 events)`.
+    pub examples: Vec<String>,
+}
+
+/// Links the EVENT_NAME and RESIDUE combinations of MV_EVENT to their
+/// terms. Event names are matched regardless of case.
+pub fn map(conn: &Connection, mappings: &[Mapping]) -> Result<Mapped, ImportError> {
+    conn.batch_execute(
+        "CREATE TEMPORARY TABLE event_type_mapping (EVENT_NAME TEXT, RESIDUE TEXT, MOD_ID TEXT, MOD_NAME TEXT) ON COMMIT DROP",
+    )?;
+    let insert = conn.prepare("INSERT INTO event_type_mapping VALUES ($1, $2, $3, $4)")?;
+    for mapping in mappings {
+        insert.execute(&[&mapping.event_name, &mapping.residue, &mapping.mod_id, &mapping.mod_name])?;
+    }
+    let events = "(SELECT DISTINCT EVENT_NAME, upper(RESIDUE) AS RESIDUE FROM MV_EVENT \
+                  WHERE EVENT_NAME IS NOT NULL AND RESIDUE IS NOT NULL) e";
+    let matches = "lower(m.EVENT_NAME) = lower(e.EVENT_NAME) AND m.RESIDUE = e.RESIDUE";
+    let links = conn.execute(
+        &format!(
+            "INSERT INTO {} (EVENT_NAME, RESIDUE, MOD_ID, MOD_NAME) \
+             SELECT DISTINCT ON (e.EVENT_NAME, e.RESIDUE, m.MOD_ID) e.EVENT_NAME, e.RESIDUE, m.MOD_ID, m.MOD_NAME \
+             FROM {} JOIN event_type_mapping m ON {} ORDER BY e.EVENT_NAME, e.RESIDUE, m.MOD_ID",
+            EVENT_TYPE_ONTOLOGY.name, events, matches
+        ),
+        &[],
+    )?;
+
+    let unmapped = format!(
+        "FROM {} WHERE NOT EXISTS (SELECT 1 FROM event_type_mapping m WHERE {})",
+        events, matches
+    );
+    let rows = conn.query(&format!("SELECT count(*) {}", unmapped), &[])?;
+    let count: i64 = rows.get(0).get(0);
+    let rows = conn.query(
+        &format!(
+            "SELECT e.EVENT_NAME, e.RESIDUE, (SELECT count(*) FROM MV_EVENT v WHERE v.EVENT_NAME = e.EVENT_NAME \
+             AND upper(v.RESIDUE) = e.RESIDUE) AS events {} ORDER BY 3 DESC, 1, 2 LIMIT {}",
+            unmapped, EXAMPLES
+        ),
+        &[],
+    )?;
+    let examples = rows
+        .iter()
+        .map(|row| {
+            let (event_name, residue, events): (String, String, i64) = (row.get(0), row.get(1), row.get(2));
+            format!("{} {} ({} events)", event_name, residue, events)
+        })
+        .collect();
+    conn.batch_execute("DROP TABLE event_type_mapping")?;
+    Ok(Mapped {
+        links,
+        unmapped: count as u64,
+        examples,
+    })
+}
@@ -0,0 +1,118 @@
+//! Splits the PMID lists of the events into the EVENT_PMID link table when
+//! run with `--pmids`, so that the events of a publication are found by
+//! number instead of with `LIKE '%X%'`.
+
+use postgres::Connection;
+
+use error::ImportError;
+use schema::{btree, key, Index, EVENT_PMID};
+
+/// A column holding PMID lists.
+pub struct List {
+    pub table: &'static str,
+    /// Column of the event id the PMIDs are linked to. `None` for tables
+    /// without one, whose lists are only checked.
+    pub id: Option<&'static str>,
+    pub column: &'static str,
+}
+
+pub const LISTS: [List; 4] = [
+    List {
+        table: "MV_EVENT",
+        id: Some("IPTM_EVENT_ID"),
+        column: "PMIDS",
+    },
+    List {
+        table: "MV_EFIP",
+        id: Some("PPI_EVENT_ID"),
+        column: "PPI_PMIDS",
+    },
+    List {
+        table: "MV_EFIP",
+        id: Some("PTM_EVENT_ID"),
+        column: "PTM_PMIDS",
+    },
+    // MV_PROTEO has no key column: a link could not tell apart proteoforms
+    // that share SUB_CODE, ENZ_CODE and EVENT_NAME, so its PMIDs are only
+    // checked.
+    List {
+        table: "MV_PROTEO",
+        id: None,
+        column: "PMIDS",
+    },
+];
+
+/// Created once the table is filled. They go away with the table.
+pub const INDEXES: [Index; 2] = [
+    btree("event_pmid_event_idx", "EVENT_PMID", &[key("EVENT_ID"), key("PMID")]),
+    btree("event_pmid_pmid_idx", "EVENT_PMID", &[key("PMID"), key("EVENT_ID")]),
+];
+
+/// Number of malformed tokens quoted in the warning.
+const EXAMPLES: i64 = 5;
+
+/// A positive number that fits a BIGINT, without leading zeros.
+const PMID_PATTERN: &str = "^[1-9][0-9]{0,17}$";
+
+pub struct Linked {
+    pub links: u64,
+    pub malformed: u64,
+    /// Some of the malformed tokens, e.g. `"PMC1234" (IPTM_EVENT_ID 105)`.
+    pub examples: Vec<String>,
+}
+
+impl List {
+    /// `TABLE.COLUMN`, also stored as the `SOURCE` of the links.
+    pub fn source(&self) -> String {
+        format!("{}.{}", self.table, self.column)
+    }
+
+    /// Inserts the links of every PMID in the list and counts the tokens
+    /// that are not PMIDs. Lists are split at commas, semicolons, pipes and
+    /// whitespace, and a `PMID:` prefix is ignored.
+    pub fn link(&self, conn: &Connection) -> Result<Linked, ImportError> {
+        let tokens = format!(
+            "(SELECT id, regexp_replace(token, '^PMID:?', '', 'i') AS token \
+             FROM (SELECT {id} AS id, regexp_split_to_table({column}, '[[:space:],;|]+') AS token FROM {table}) AS split \
+             WHERE regexp_replace(token, '^PMID:?', '', 'i') <> '') AS tokens",
+            id = self.id.unwrap_or("NULL::BIGINT"),
+            column = self.column,
+            table = self.table
+        );
+
+        let links = match self.id {
+            Some(_) => conn.execute(
+                &format!(
+                    "INSERT INTO {} (EVENT_ID, PMID, SOURCE) SELECT DISTINCT id, token::BIGINT, $1 FROM {} \
+                     WHERE id IS NOT NULL AND token ~ '{}'",
+                    EVENT_PMID.name, tokens, PMID_PATTERN
+                ),
+                &[&self.source()],
+            )?,
+            None => 0,
+        };
+
+        let malformed = format!("FROM {} WHERE token !~ '{}'", tokens, PMID_PATTERN);
+        let rows = conn.query(&format!("SELECT count(*) {}", malformed), &[])?;
+        let count: i64 = rows.get(0).get(0);
+        let rows = conn.query(
+            &format!("SELECT id, token {} ORDER BY id, token LIMIT {}", malformed, EXAMPLES),
+            &[],
+        )?;
+        let examples = rows
+            .iter()
+            .map(|row| {
+                let token: String = row.get(1);
+                match (self.id, row.get::<_, Option<i64>>(0)) {
+                    (Some(id), Some(value)) => format!("{:?} ({} {})", token, id, value),
+                    _ => format!("{:?}", token),
+                }
+            })
+            .collect();
+        Ok(Linked {
+            links,
+            malformed: count as u64,
+            examples,
+        })
+    }
+}
@@ -0,0 +1,368 @@
+//! Column statistics of the exports, for the `profile` command. Flags
+//! VARCHAR columns whose longest value comes close to the declared size.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::io::BufRead;
+
+use csv;
+use csv::Dialect;
+use schema::{excerpt, Column, ColumnType, Table};
+
+/// Number of smallest hashes kept for the distinct estimate.
+const DISTINCT_SAMPLE: usize = 1024;
+
+/// Number of distinct values counted before rare ones are dropped.
+const TRACKED_VALUES: usize = 10_000;
+
+pub struct Settings {
+    /// Number of most frequent values reported per column.
+    pub top: usize,
+    /// A longest value within this percentage of the declared size is
+    /// flagged.
+    pub margin: u64,
+}
+
+#[derive(Serialize)]
+pub struct TableProfile {
+    pub table: String,
+    pub path: String,
+    pub rows: u64,
+    /// Records with the wrong number of fields or invalid UTF-8, left out
+    /// of the statistics.
+    pub malformed: u64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+#[derive(Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub nulls: u64,
+    /// Values that are not NULL.
+    pub values: u64,
+    /// Values that are empty or only whitespace, but not NULL.
+    pub empty: u64,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub mean_length: Option<f64>,
+    pub distinct: u64,
+    /// Whether `distinct` is a count rather than an estimate.
+    pub distinct_exact: bool,
+    /// Values that parse as a number.
+    pub numeric: u64,
+    pub min_number: Option<f64>,
+    pub max_number: Option<f64>,
+    pub top: Vec<TopValue>,
+    /// Whether the `top` counts are exact. Rare values are dropped from the
+    /// count when a column has too many distinct values.
+    pub top_exact: bool,
+    pub flag: Option<SizeFlag>,
+}
+
+#[derive(Serialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeFlag {
+    /// The longest value is within the margin of the declared size.
+    Near,
+    /// The longest value does not fit, COPY would fail on it.
+    Over,
+}
+
+impl ColumnProfile {
+    /// Describes the flag, e.g. for a warning.
+    pub fn warning(&self) -> Option<String> {
+        let longest = self.max_length.unwrap_or(0);
+        match self.flag {
+            Some(SizeFlag::Near) => Some(format!(
+                "longest value has {} characters, close to {}",
+                longest, self.kind
+            )),
+            Some(SizeFlag::Over) => Some(format!(
+                "longest value has {} characters, more than {}",
+                longest, self.kind
+            )),
+            None => None,
+        }
+    }
+}
+
+/// Reads an export and computes the statistics of every column of `table`.
+pub fn profile<R: BufRead>(
+    table: &Table,
+    path: &str,
+    input: R,
+    dialect: &Dialect,
+    settings: &Settings,
+) -> io::Result<TableProfile> {
+    let mut stats: Vec<ColumnStats> = table.columns.iter().map(|_| ColumnStats::new()).collect();
+    let mut reader = csv::Reader::new(input, dialect);
+    let mut header_seen = !dialect.header;
+    let (mut rows, mut malformed) = (0, 0);
+    while let Some(record) = reader.read_record()? {
+        if !header_seen {
+            header_seen = true;
+            continue;
+        }
+        rows += 1;
+        if record.fields.len() != table.columns.len() {
+            malformed += 1;
+            continue;
+        }
+        let values: Result<Vec<Option<&str>>, String> = table
+            .columns
+            .iter()
+            .zip(record.fields.iter())
+            .map(|(column, field)| dialect.value(field, column.name))
+            .collect();
+        match values {
+            Ok(values) => {
+                for (stats, value) in stats.iter_mut().zip(values) {
+                    stats.add(value);
+                }
+            }
+            Err(_) => malformed += 1,
+        }
+    }
+
+    let columns = table
+        .columns
+        .iter()
+        .zip(stats)
+        .map(|(column, stats)| stats.into_profile(column, settings))
+        .collect();
+    Ok(TableProfile {
+        table: table.name.to_string(),
+        path: path.to_string(),
+        rows,
+        malformed,
+        columns,
+    })
+}
+
+struct ColumnStats {
+    nulls: u64,
+    empty: u64,
+    values: u64,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    total_length: u64,
+    distinct: Distinct,
+    numeric: u64,
+    min_number: Option<f64>,
+    max_number: Option<f64>,
+    counts: HashMap<String, u64>,
+    counts_pruned: bool,
+}
+
+impl ColumnStats {
+    fn new() -> ColumnStats {
+        ColumnStats {
+            nulls: 0,
+            empty: 0,
+            values: 0,
+            min_length: None,
+            max_length: None,
+            total_length: 0,
+            distinct: Distinct::new(),
+            numeric: 0,
+            min_number: None,
+            max_number: None,
+            counts: HashMap::new(),
+            counts_pruned: false,
+        }
+    }
+
+    fn add(&mut self, value: Option<&str>) {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.nulls += 1;
+                return;
+            }
+        };
+        self.values += 1;
+        if value.trim().is_empty() {
+            self.empty += 1;
+        }
+
+        let length = Column::length(value);
+        self.min_length = Some(self.min_length.map_or(length, |min| min.min(length)));
+        self.max_length = Some(self.max_length.map_or(length, |max| max.max(length)));
+        self.total_length += length as u64;
+
+        if let Ok(number) = value.trim().parse::<f64>() {
+            if number.is_finite() {
+                self.numeric += 1;
+                self.min_number = Some(self.min_number.map_or(number, |min| min.min(number)));
+                self.max_number = Some(self.max_number.map_or(number, |max| max.max(number)));
+            }
+        }
+
+        self.distinct.add(value);
+        *self.counts.entry(value.to_string()).or_insert(0) += 1;
+        if self.counts.len() > TRACKED_VALUES {
+            self.prune();
+        }
+    }
+
+    /// Drops the rarest values until half of the slots are free again.
+    fn prune(&mut self) {
+        let mut threshold = 1;
+        while self.counts.len() > TRACKED_VALUES / 2 {
+            self.counts.retain(|_, count| *count > threshold);
+            threshold += 1;
+        }
+        self.counts_pruned = true;
+    }
+
+    fn into_profile(self, column: &Column, settings: &Settings) -> ColumnProfile {
+        let flag = self.max_length.and_then(|longest| size_flag(column.kind, longest, settings.margin));
+        let mut top: Vec<(String, u64)> = self.counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(settings.top);
+        let (distinct, distinct_exact) = self.distinct.estimate();
+        ColumnProfile {
+            name: column.name.to_string(),
+            kind: column.kind.to_string(),
+            nulls: self.nulls,
+            values: self.values,
+            empty: self.empty,
+            min_length: self.min_length,
+            max_length: self.max_length,
+            mean_length: if self.values > 0 {
+                Some(self.total_length as f64 / self.values as f64)
+            } else {
+                None
+            },
+            distinct,
+            distinct_exact,
+            numeric: self.numeric,
+            min_number: self.min_number,
+            max_number: self.max_number,
+            top: top.into_iter().map(|(value, count)| TopValue { value, count }).collect(),
+            top_exact: !self.counts_pruned,
+            flag,
+        }
+    }
+}
+
+/// CHAR columns, and VARCHAR columns so short that the margin is less than
+/// one character (e.g. RESIDUE VARCHAR(1)), are flagged only when a value
+/// does not fit, as their values are meant to fill them.
+fn size_flag(kind: ColumnType, longest: usize, margin: u64) -> Option<SizeFlag> {
+    let (size, near) = match kind {
+        ColumnType::Varchar(size) => (size, size as u64 * margin >= 100),
+        ColumnType::Char(size) => (size, false),
+        ColumnType::BigInt | ColumnType::Double | ColumnType::Date | ColumnType::Text => return None,
+    };
+    if longest > size {
+        Some(SizeFlag::Over)
+    } else if near && longest as u64 * 100 >= size as u64 * (100 - margin.min(100)) {
+        Some(SizeFlag::Near)
+    } else {
+        None
+    }
+}
+
+/// Estimates the number of distinct values from the smallest of their
+/// hashes (the k minimum values sketch). Exact up to `DISTINCT_SAMPLE`
+/// values.
+struct Distinct {
+    hashes: BTreeSet<u64>,
+}
+
+impl Distinct {
+    fn new() -> Distinct {
+        Distinct { hashes: BTreeSet::new() }
+    }
+
+    fn add(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.hashes.len() < DISTINCT_SAMPLE {
+            self.hashes.insert(hash);
+        } else if hash < *self.hashes.iter().next_back().unwrap() && self.hashes.insert(hash) {
+            let largest = *self.hashes.iter().next_back().unwrap();
+            self.hashes.remove(&largest);
+        }
+    }
+
+    fn estimate(&self) -> (u64, bool) {
+        match self.hashes.iter().next_back() {
+            Some(&largest) if self.hashes.len() == DISTINCT_SAMPLE => {
+                let fraction = (largest as f64 + 1.0) / (u64::MAX as f64 + 1.0);
+                (((DISTINCT_SAMPLE - 1) as f64 / fraction).round() as u64, false)
+            }
+            _ => (self.hashes.len() as u64, true),
+        }
+    }
+}
+
+impl fmt::Display for TableProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}, {} rows", self.table, self.path, self.rows)?;
+        if self.malformed > 0 {
+            write!(f, ", {} malformed and left out", self.malformed)?;
+        }
+        writeln!(f)?;
+        for column in &self.columns {
+            write!(f, "{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColumnProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |count: u64| {
+            let profiled = self.nulls + self.values;
+            if profiled > 0 {
+                count as f64 * 100.0 / profiled as f64
+            } else {
+                0.0
+            }
+        };
+        writeln!(f, "  {} {}", self.name, self.kind)?;
+        writeln!(
+            f,
+            "    null {} ({:.1}%), empty {} ({:.1}%)",
+            self.nulls,
+            percent(self.nulls),
+            self.empty,
+            percent(self.empty)
+        )?;
+        if let (Some(min), Some(max), Some(mean)) = (self.min_length, self.max_length, self.mean_length) {
+            writeln!(f, "    length {} to {}, mean {:.1}", min, max, mean)?;
+        }
+        let approximate = if self.distinct_exact { "" } else { "~" };
+        writeln!(f, "    distinct {}{}", approximate, self.distinct)?;
+        if let (Some(min), Some(max)) = (self.min_number, self.max_number) {
+            writeln!(f, "    numeric {} of {}, range {} to {}", self.numeric, self.values, min, max)?;
+        }
+        if !self.top.is_empty() {
+            let top: Vec<String> = self
+                .top
+                .iter()
+                .map(|top| format!("{:?} ({})", excerpt(&top.value, 40), top.count))
+                .collect();
+            let approximate = if self.top_exact { "" } else { " (approximate)" };
+            writeln!(f, "    top{} {}", approximate, top.join(", "))?;
+        }
+        if let Some(warning) = self.warning() {
+            writeln!(f, "    ! {}", warning)?;
+        }
+        Ok(())
+    }
+}
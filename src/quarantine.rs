@@ -0,0 +1,193 @@
+//! Tolerant mode: bad rows are set aside instead of failing the import.
+//!
+//! `Quarantine` sits between the CSV export and COPY. Every record is
+//! checked against the column definitions of the target table; valid
+//! records are passed on unchanged and rejected ones are written to
+//! `<table>.rejects.csv` together with their line number and the reason.
+//! After the COPY the number of rejects is compared with the configured
+//! limit to decide whether the run may still commit.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use csv;
+use csv::Dialect;
+use schema::Table;
+
+/// How many rejected rows a table may have before the run fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RejectLimit {
+    Unlimited,
+    Count(u64),
+    Percent(f64),
+}
+
+impl RejectLimit {
+    /// Parses `250` as a count and `0.5%` as a percentage of the records.
+    pub fn parse(value: &str) -> Option<RejectLimit> {
+        let value = value.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Some(RejectLimit::Percent(percent)),
+                _ => None,
+            }
+        } else {
+            value.parse().ok().map(RejectLimit::Count)
+        }
+    }
+
+    pub fn exceeded(&self, rejected: u64, records: u64) -> bool {
+        match *self {
+            RejectLimit::Unlimited => false,
+            RejectLimit::Count(limit) => rejected > limit,
+            RejectLimit::Percent(percent) => records > 0 && rejected as f64 * 100.0 / records as f64 > percent,
+        }
+    }
+}
+
+/// Path of the rejects file of `table`, next to its export.
+pub fn rejects_path(table: &Table, export: &Path) -> PathBuf {
+    export.with_file_name(format!("{}.rejects.csv", table.name.to_lowercase()))
+}
+
+pub struct Quarantine<'a, R> {
+    table: &'a Table,
+    records: csv::Reader<R>,
+    dialect: Dialect,
+    rejects: BufWriter<File>,
+    header_seen: bool,
+    pending: Vec<u8>,
+    position: usize,
+    /// Data records read so far, accepted or not.
+    pub total: u64,
+    pub rejected: u64,
+    /// Start line and length in lines of every rejected record, to map the
+    /// line numbers of COPY errors back to the export.
+    pub skipped: Vec<(u64, u64)>,
+    /// First error reading the export or writing the rejects. COPY only
+    /// sees a generic failure, so the caller reports this one instead.
+    pub error: Option<io::Error>,
+}
+
+impl<'a, R: BufRead> Quarantine<'a, R> {
+    pub fn new(table: &'a Table, input: R, dialect: &Dialect, rejects: &Path) -> io::Result<Quarantine<'a, R>> {
+        let mut writer = BufWriter::new(File::create(rejects)?);
+        writer.write_all(b"LINE,REASON,RECORD\n")?;
+        Ok(Quarantine {
+            table,
+            records: csv::Reader::new(input, dialect),
+            dialect: dialect.clone(),
+            rejects: writer,
+            header_seen: !dialect.header,
+            pending: Vec::new(),
+            position: 0,
+            total: 0,
+            rejected: 0,
+            skipped: Vec::new(),
+            error: None,
+        })
+    }
+
+    /// Flushes the rejects file. Must be called once COPY is done.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.rejects.flush()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.records.into_inner()
+    }
+
+    /// Reads records until one can be passed on to COPY. Returns `false` at
+    /// the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        loop {
+            let record = match self.records.read_record()? {
+                Some(record) => record,
+                None => return Ok(false),
+            };
+            if !self.header_seen {
+                self.header_seen = true;
+                self.pending = record.raw;
+                return Ok(true);
+            }
+            self.total += 1;
+            match check(self.table, &self.dialect, &record) {
+                Ok(()) => {
+                    self.pending = record.raw;
+                    return Ok(true);
+                }
+                Err(reason) => {
+                    debug!(table = self.table.name, line = record.line; "Rejected line {}: {}", record.line, reason);
+                    self.rejected += 1;
+                    self.skipped.push((record.line, record.lines));
+                    write_reject(&mut self.rejects, &record, &reason)?;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, R: BufRead> Read for Quarantine<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.pending.len() {
+            self.pending.clear();
+            self.position = 0;
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return Ok(0),
+                Err(error) => {
+                    let copy = io::Error::new(error.kind(), error.to_string());
+                    self.error = Some(error);
+                    return Err(copy);
+                }
+            }
+        }
+        let count = buf.len().min(self.pending.len() - self.position);
+        buf[..count].copy_from_slice(&self.pending[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn check(table: &Table, dialect: &Dialect, record: &csv::Record) -> Result<(), String> {
+    if record.fields.len() != table.columns.len() {
+        return Err(format!(
+            "expected {} fields, found {}",
+            table.columns.len(),
+            record.fields.len()
+        ));
+    }
+    for (column, field) in table.columns.iter().zip(record.fields.iter()) {
+        let value = dialect.value(field, column.name).map_err(|error| format!("{} has {}", column.name, error))?;
+        if value.is_some_and(|value| value.contains('\0')) {
+            return Err(format!("{} contains a NUL byte", column.name));
+        }
+        column.check(value)?;
+    }
+    Ok(())
+}
+
+fn write_reject<W: Write>(out: &mut W, record: &csv::Record, reason: &str) -> io::Result<()> {
+    let mut raw = &record.raw[..];
+    while raw.last().is_some_and(|&byte| byte == b'\n' || byte == b'\r') {
+        raw = &raw[..raw.len() - 1];
+    }
+    write!(out, "{},", record.line)?;
+    write_quoted(out, reason.as_bytes())?;
+    out.write_all(b",")?;
+    write_quoted(out, raw)?;
+    out.write_all(b"\n")
+}
+
+fn write_quoted<W: Write>(out: &mut W, value: &[u8]) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for (index, part) in value.split(|&byte| byte == b'"').enumerate() {
+        if index > 0 {
+            out.write_all(b"\"\"")?;
+        }
+        out.write_all(part)?;
+    }
+    out.write_all(b"\"")
+}
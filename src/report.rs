@@ -0,0 +1,158 @@
+//! Machine-readable summary of an import run.
+//!
+//! Every step of the import (lock, begin, drop, create, populate, validate,
+//! vocabulary, check, link, site, index, constrain, qa, window, ontology,
+//! search, cluster, commit, analyze, vacuum) is timed and recorded together
+//! with the rows and bytes it processed. When a report path is configured the
+//! summary is written as JSON once the run ends, whether it succeeded or not.
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde_json;
+
+use error::ImportError;
+use residues::SourceCheck;
+
+#[derive(Serialize)]
+pub struct Step {
+    pub step: String,
+    pub target: String,
+    pub started_at: String,
+    pub duration_seconds: f64,
+    pub rows: Option<u64>,
+    pub bytes: Option<u64>,
+    /// Rows set aside in tolerant mode for populate steps, tokens left out
+    /// for link and site steps, invalid values for validate steps, rows with
+    /// unknown values for check steps and events with a problem for the qa
+    /// step.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<u64>,
+    /// Records transcoded to UTF-8, only when an encoding was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub converted: Option<u64>,
+    pub outcome: Outcome,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Running,
+    Success,
+    Failure,
+}
+
+/// A step that has been started but not finished yet.
+pub struct StepTimer {
+    step: String,
+    target: String,
+    started_at: DateTime<Utc>,
+    start: Instant,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    start: Instant,
+    pub version: &'static str,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub duration_seconds: f64,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+    pub error_class: Option<String>,
+    pub steps: Vec<Step>,
+    /// Results of the residue check per SOURCE_LABEL of MV_EVENT.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub residues: Vec<SourceCheck>,
+}
+
+impl Report {
+    pub fn new(path: Option<PathBuf>) -> Report {
+        Report {
+            path,
+            start: Instant::now(),
+            version: env!("CARGO_PKG_VERSION"),
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+            duration_seconds: 0.0,
+            outcome: Outcome::Running,
+            error: None,
+            error_class: None,
+            steps: Vec::new(),
+            residues: Vec::new(),
+        }
+    }
+
+    pub fn start(&self, step: &str, target: &str) -> StepTimer {
+        StepTimer {
+            step: step.to_string(),
+            target: target.to_string(),
+            started_at: Utc::now(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn finish(&mut self, timer: StepTimer, rows: Option<u64>, bytes: Option<u64>) -> &mut Step {
+        self.record(timer, rows, bytes, Outcome::Success)
+    }
+
+    pub fn fail_step(&mut self, timer: StepTimer) -> &mut Step {
+        self.record(timer, None, None, Outcome::Failure)
+    }
+
+    /// Marks the whole run as finished. `error` is `None` for a successful
+    /// run and the reason of the failure otherwise.
+    pub fn complete(&mut self, error: Option<&ImportError>) {
+        self.outcome = match error {
+            Some(_) => Outcome::Failure,
+            None => Outcome::Success,
+        };
+        self.error = error.map(|error| error.to_string());
+        self.error_class = error.map(|error| error.class().to_string());
+        self.finished_at = Some(Utc::now().to_rfc3339());
+        self.duration_seconds = seconds(self.start);
+    }
+
+    /// Writes the report to the configured path, if any. The file is
+    /// replaced atomically so that a scheduler never reads half a report.
+    pub fn write(&self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let file = File::create(&partial)?;
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::from)?;
+        fs::rename(&partial, path)
+    }
+
+    fn record(&mut self, timer: StepTimer, rows: Option<u64>, bytes: Option<u64>, outcome: Outcome) -> &mut Step {
+        self.steps.push(Step {
+            step: timer.step,
+            target: timer.target,
+            started_at: timer.started_at.to_rfc3339(),
+            duration_seconds: seconds(timer.start),
+            rows,
+            bytes,
+            rejected: None,
+            converted: None,
+            outcome,
+        });
+        self.steps.last_mut().unwrap()
+    }
+}
+
+fn seconds(start: Instant) -> f64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9
+}
@@ -0,0 +1,143 @@
+//! Checks the residues of the events against the sequences, once both are
+//! loaded.
+//!
+//! MV_EVENT gives the residue and position of every modification on the
+//! substrate, SEQUENCE the sequences of the proteins. When a sequence is
+//! revised upstream the positions of older events no longer match, which
+//! nothing else notices. The check counts, per SOURCE_LABEL, the events
+//! whose residue differs from the sequence, whose position lies outside of
+//! it, and whose substrate has no sequence at all. It only reports, the
+//! import goes ahead either way.
+
+use std::fmt;
+
+use postgres::Connection;
+
+use error::ImportError;
+
+/// Number of events quoted per kind of problem.
+const EXAMPLES: i64 = 5;
+
+/// The events to check, with the sequence of their substrate. Isoforms are
+/// matched by their own ID, e.g. P31749-2.
+pub const CHECKED: &str = "\
+    FROM MV_EVENT e LEFT JOIN LATERAL (SELECT SEQ FROM SEQUENCE s WHERE s.ID = e.SUB_UNIPROT_ID LIMIT 1) s ON true \
+    WHERE e.RESIDUE IS NOT NULL AND e.POSITION IS NOT NULL AND e.SUB_UNIPROT_ID IS NOT NULL";
+
+const MISSING: &str = "s.SEQ IS NULL";
+const OUT_OF_RANGE: &str = "s.SEQ IS NOT NULL AND (e.POSITION < 1 OR e.POSITION > length(s.SEQ))";
+/// The residue of the sequence at the position of the event, NULL when the
+/// position lies outside of it. The CASE keeps positions beyond the INTEGER
+/// range away from the cast.
+macro_rules! at_position {
+    () => {
+        "CASE WHEN e.POSITION BETWEEN 1 AND length(s.SEQ) THEN substr(s.SEQ, e.POSITION::INTEGER, 1) END"
+    };
+}
+
+const MISMATCHED: &str = concat!(at_position!(), " <> upper(e.RESIDUE)");
+/// Events whose residue is found at their position.
+pub const MATCHED: &str = concat!(at_position!(), " = upper(e.RESIDUE)");
+
+/// Results for the events of one SOURCE_LABEL.
+#[derive(Serialize)]
+pub struct SourceCheck {
+    pub source_label: Option<String>,
+    pub checked: u64,
+    pub matched: u64,
+    pub mismatched: u64,
+    pub out_of_range: u64,
+    pub missing_sequence: u64,
+}
+
+pub struct Check {
+    pub sources: Vec<SourceCheck>,
+    /// Some of the events with a problem, e.g. `IPTM_EVENT_ID 101: S15 of
+    /// P04637 is T in the sequence`.
+    pub examples: Vec<String>,
+}
+
+impl Check {
+    pub fn checked(&self) -> u64 {
+        self.sources.iter().map(|source| source.checked).sum()
+    }
+
+    pub fn problems(&self) -> u64 {
+        self.sources
+            .iter()
+            .map(|source| source.mismatched + source.out_of_range + source.missing_sequence)
+            .sum()
+    }
+}
+
+pub fn check(conn: &Connection) -> Result<Check, ImportError> {
+    let rows = conn.query(
+        &format!(
+            "SELECT e.SOURCE_LABEL, count(*), count(*) FILTER (WHERE {}), count(*) FILTER (WHERE {}), \
+             count(*) FILTER (WHERE {}) {} GROUP BY 1 ORDER BY 1",
+            MISMATCHED, OUT_OF_RANGE, MISSING, CHECKED
+        ),
+        &[],
+    )?;
+    let sources = rows
+        .iter()
+        .map(|row| {
+            let count = |index: usize| row.get::<_, i64>(index) as u64;
+            let checked = count(1);
+            let (mismatched, out_of_range, missing_sequence) = (count(2), count(3), count(4));
+            SourceCheck {
+                source_label: row.get(0),
+                checked,
+                matched: checked - mismatched - out_of_range - missing_sequence,
+                mismatched,
+                out_of_range,
+                missing_sequence,
+            }
+        })
+        .collect();
+
+    let mut examples = Vec::new();
+    for &(kind, condition) in [("mismatched", MISMATCHED), ("out of range", OUT_OF_RANGE), ("missing", MISSING)].iter() {
+        // Only the mismatched events have a residue at their position.
+        let found = if kind == "mismatched" { at_position!() } else { "NULL::TEXT" };
+        let rows = conn.query(
+            &format!(
+                "SELECT e.IPTM_EVENT_ID, e.SUB_UNIPROT_ID, upper(e.RESIDUE), e.POSITION, length(s.SEQ), {} {} AND {} \
+                 ORDER BY 1 LIMIT {}",
+                found, CHECKED, condition, EXAMPLES
+            ),
+            &[],
+        )?;
+        for row in rows.iter() {
+            let (id, protein, residue, position): (i64, String, String, i64) = (row.get(0), row.get(1), row.get(2), row.get(3));
+            let problem = match kind {
+                "mismatched" => format!("{}{} of {} is {} in the sequence", residue, position, protein, row.get::<_, String>(5)),
+                "out of range" => format!(
+                    "{}{} is outside of {}, which has {} residues",
+                    residue,
+                    position,
+                    protein,
+                    row.get::<_, i32>(4)
+                ),
+                _ => format!("{} has no sequence", protein),
+            };
+            examples.push(format!("IPTM_EVENT_ID {}: {}", id, problem));
+        }
+    }
+    Ok(Check { sources, examples })
+}
+
+impl fmt::Display for SourceCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} residues checked, {} matched, {} mismatched, {} out of range, {} without a sequence",
+            self.source_label.as_deref().unwrap_or("no SOURCE_LABEL"),
+            self.checked,
+            self.matched,
+            self.mismatched,
+            self.out_of_range,
+            self.missing_sequence
+        )
+    }
+}
@@ -0,0 +1,520 @@
+//! Tables and indexes created by the importer.
+//!
+//! The column list of every table is kept as data so that the same
+//! definition drives the `CREATE TABLE` statement and the client-side checks
+//! of the tolerant mode. Indexes are kept as data too, so that the indexes
+//! that are dropped are always the ones that are created.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use attributes;
+
+use self::ColumnType::{BigInt, Char, Text, Varchar};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    BigInt,
+    Double,
+    /// Only ISO 8601 dates, e.g. 2018-04-27, pass the client-side checks.
+    Date,
+    Varchar(usize),
+    Char(usize),
+    Text,
+}
+
+impl ColumnType {
+    /// Parses a type as written in the configuration file, e.g. `VARCHAR(25)`.
+    pub fn parse(value: &str) -> Option<ColumnType> {
+        let value = value.trim().to_uppercase();
+        let sized = |prefix: &str| {
+            value
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.trim_start().strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|size| size.trim().parse().ok())
+                .filter(|&size| size > 0)
+        };
+        match value.as_str() {
+            "BIGINT" => Some(BigInt),
+            "DOUBLE PRECISION" => Some(ColumnType::Double),
+            "DATE" => Some(ColumnType::Date),
+            "TEXT" => Some(Text),
+            _ => sized("VARCHAR").map(Varchar).or_else(|| sized("CHAR").map(Char)),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ColumnType::BigInt => write!(f, "BIGINT"),
+            ColumnType::Double => write!(f, "DOUBLE PRECISION"),
+            ColumnType::Date => write!(f, "DATE"),
+            ColumnType::Varchar(length) => write!(f, "VARCHAR({})", length),
+            ColumnType::Char(length) => write!(f, "CHAR({})", length),
+            ColumnType::Text => write!(f, "TEXT"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnType,
+    pub not_null: bool,
+}
+
+pub struct Table {
+    pub name: &'static str,
+    /// CSV export the table is populated from, relative to the working
+    /// directory. Empty for tables built from the other tables.
+    pub export: &'static str,
+    pub columns: &'static [Column],
+    /// Columns filled while the export is streamed into COPY rather than
+    /// read from it, after those of the export.
+    pub computed: &'static [Column],
+}
+
+impl Table {
+    /// The columns of the export followed by the computed ones.
+    pub fn all_columns(&self) -> impl Iterator<Item = &'static Column> {
+        self.columns.iter().chain(self.computed.iter())
+    }
+
+    pub fn create_statement(&self) -> String {
+        let columns: Vec<String> = self
+            .all_columns()
+            .map(|column| {
+                let not_null = if column.not_null { " NOT NULL" } else { "" };
+                format!("    {} {}{}", column.name, column.kind, not_null)
+            })
+            .collect();
+        format!("CREATE TABLE IF NOT EXISTS {}\n(\n{}\n)", self.name, columns.join(",\n"))
+    }
+}
+
+impl Column {
+    /// Checks a CSV field the way COPY would. `None` is a NULL field.
+    pub fn check(&self, value: Option<&str>) -> Result<(), String> {
+        let value = match value {
+            Some(value) => value,
+            None if self.not_null => return Err(format!("{} is NULL but the column is NOT NULL", self.name)),
+            None => return Ok(()),
+        };
+        match self.kind {
+            ColumnType::BigInt => match value.trim().parse::<i64>() {
+                Ok(_) => Ok(()),
+                Err(_) => Err(format!("{} is not a valid BIGINT: {:?}", self.name, excerpt(value, 40))),
+            },
+            ColumnType::Double => match value.trim().parse::<f64>() {
+                Ok(_) => Ok(()),
+                Err(_) => Err(format!("{} is not a valid DOUBLE PRECISION: {:?}", self.name, excerpt(value, 40))),
+            },
+            ColumnType::Date => match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+                Ok(_) => Ok(()),
+                Err(_) => Err(format!("{} is not a valid DATE: {:?}", self.name, excerpt(value, 40))),
+            },
+            ColumnType::Varchar(length) | ColumnType::Char(length) => {
+                let characters = Column::length(value);
+                if characters > length {
+                    Err(format!(
+                        "{} is {} characters long, longer than {}",
+                        self.name, characters, self.kind
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            ColumnType::Text => Ok(()),
+        }
+    }
+
+    /// Length of a value as checked against VARCHAR and CHAR sizes.
+    pub fn length(value: &str) -> usize {
+        // Postgres silently truncates trailing spaces beyond the limit.
+        value.trim_end_matches(' ').chars().count()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexMethod {
+    BTree,
+    Hash,
+    Gin,
+    Gist,
+}
+
+impl IndexMethod {
+    pub fn parse(value: &str) -> Option<IndexMethod> {
+        match value.to_lowercase().as_str() {
+            "btree" => Some(IndexMethod::BTree),
+            "hash" => Some(IndexMethod::Hash),
+            "gin" => Some(IndexMethod::Gin),
+            "gist" => Some(IndexMethod::Gist),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IndexMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IndexMethod::BTree => write!(f, "btree"),
+            IndexMethod::Hash => write!(f, "hash"),
+            IndexMethod::Gin => write!(f, "gin"),
+            IndexMethod::Gist => write!(f, "gist"),
+        }
+    }
+}
+
+/// A column or expression of an index, e.g. `GENE_NAME` or
+/// `lower(GENE_NAME)`, with an optional operator class such as
+/// `text_pattern_ops` or `gin_trgm_ops`.
+pub struct IndexKey {
+    pub expression: &'static str,
+    pub opclass: Option<&'static str>,
+}
+
+pub struct Index {
+    pub name: &'static str,
+    pub table: &'static str,
+    pub method: IndexMethod,
+    pub unique: bool,
+    pub keys: &'static [IndexKey],
+    /// Condition of a partial index, e.g. `POSITION IS NOT NULL`.
+    pub predicate: Option<&'static str>,
+}
+
+impl Index {
+    pub fn create_statement(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| {
+                // Expressions other than plain columns must be parenthesized.
+                let mut sql = if is_column(key.expression) {
+                    key.expression.to_string()
+                } else {
+                    format!("({})", key.expression)
+                };
+                if let Some(opclass) = key.opclass {
+                    sql.push(' ');
+                    sql.push_str(opclass);
+                }
+                sql
+            })
+            .collect();
+        let unique = if self.unique { "UNIQUE " } else { "" };
+        let mut sql = format!(
+            "CREATE {}INDEX {} ON {} USING {} ({})",
+            unique,
+            self.name,
+            self.table,
+            self.method,
+            keys.join(", ")
+        );
+        if let Some(predicate) = self.predicate {
+            sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        sql
+    }
+
+    pub fn drop_statement(&self) -> String {
+        format!("DROP INDEX IF EXISTS {}", self.name)
+    }
+}
+
+/// Whether an index key is a plain column name rather than an expression.
+pub fn is_column(expression: &str) -> bool {
+    !expression.is_empty() && expression.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Shortens a value for error messages.
+pub fn excerpt(value: &str, characters: usize) -> String {
+    match value.char_indices().nth(characters) {
+        Some((index, _)) => format!("{}...", &value[..index]),
+        None => value.to_string(),
+    }
+}
+
+const fn column(name: &'static str, kind: ColumnType) -> Column {
+    Column { name, kind, not_null: false }
+}
+
+const fn not_null(name: &'static str, kind: ColumnType) -> Column {
+    Column { name, kind, not_null: true }
+}
+
+pub const MV_ENTRY: Table = Table {
+    name: "MV_ENTRY",
+    export: "./mv_entry_export.csv",
+    columns: &[
+        not_null("IPTM_ENTRY_ID", BigInt),
+        not_null("IPTM_ENTRY_CODE", Varchar(25)),
+        not_null("IPTM_ENTRY_TYPE", Varchar(10)),
+        column("IPTM_ENTRY_SYMBOL", Varchar(4000)),
+        column("UNIPROT_ID", Varchar(50)),
+        column("PROTEIN_NAME", Varchar(200)),
+        column("GENE_NAME", Varchar(50)),
+        column("PROTEIN_SYNONYMS", Text),
+        column("GENE_SYNONYMS", Text),
+        column("DEFINITION", Text),
+        column("CATEGORY", Varchar(25)),
+        column("IS_REVIEWED", Char(1)),
+        column("TAXON_CODE", Varchar(25)),
+        column("TAXON_SPECIES", Varchar(200)),
+        column("TAXON_COMMON", Varchar(100)),
+        column("NOTE", Text),
+        column("SITES", Text),
+        column("XREF", Varchar(25)),
+        column("NUM_ENZYME", BigInt),
+        column("NUM_SUBSTRATE", BigInt),
+        column("NUM_PPI", BigInt),
+        column("NUM_SITE", BigInt),
+        column("NUM_FORM", BigInt),
+        column("ROLE_AS_ENZYME", Char(1)),
+        column("ROLE_AS_SUBSTRATE", Char(1)),
+        column("ROLE_AS_PPI", Char(1)),
+        column("WEIGHT", BigInt),
+        column("LIST_AS_SUBSTRATE", Varchar(25)),
+        column("LIST_AS_ENZYME", Varchar(25)),
+        column("HAS_OVERLAP_PTM", Char(1)),
+        column("PROTEIN_SYN", Varchar(4000)),
+        column("GENE_SYN", Varchar(4000)),
+    ],
+    computed: &[],
+};
+
+pub const MV_EVENT: Table = Table {
+    name: "MV_EVENT",
+    export: "./mv_event_export.csv",
+    columns: &[
+        not_null("IPTM_EVENT_ID", BigInt),
+        column("SUB_FORM_CODE", Varchar(25)),
+        column("SUB_CODE", Varchar(25)),
+        column("SUB_TYPE", Varchar(10)),
+        column("SUB_UNIPROT_ID", Varchar(50)),
+        column("SUB_SYMBOL", Varchar(4000)),
+        column("SUB_TAXON_CODE", Varchar(25)),
+        column("SUB_TAXON_COMMON", Varchar(100)),
+        column("SUB_SITES", Text),
+        column("SUB_XREF", Varchar(25)),
+        column("ENZ_FORM_CODE", Varchar(25)),
+        column("ENZ_CODE", Varchar(25)),
+        column("ENZ_TYPE", Varchar(10)),
+        column("ENZ_UNIPROT_ID", Varchar(50)),
+        column("ENZ_SYMBOL", Varchar(4000)),
+        column("ENZ_TAXON_CODE", Varchar(25)),
+        column("ENZ_TAXON_COMMON", Varchar(100)),
+        column("ENZ_SITES", Text),
+        column("ENZ_XREF", Varchar(25)),
+        column("EVENT_NAME", Varchar(50)),
+        column("EVENT_LABEL", Varchar(10)),
+        column("SOURCE_LABEL", Varchar(10)),
+        column("IS_AUTO_GENERATED", Char(1)),
+        column("RESIDUE", Varchar(1)),
+        column("POSITION", BigInt),
+        column("MODIFIER", Varchar(50)),
+        column("NOTE", Text),
+        column("PMIDS", Text),
+        column("NUM_SUBSTRATES", Varchar(4000)),
+    ],
+    computed: &[],
+};
+
+pub const MV_EFIP: Table = Table {
+    name: "MV_EFIP",
+    export: "./mv_efip_export.csv",
+    columns: &[
+        column("PPI_EVENT_ID", BigInt),
+        column("PTM_EVENT_ID", BigInt),
+        column("IMPACT", Varchar(50)),
+        column("PPI_SUB_CODE", Varchar(25)),
+        column("PPI_SUB_TYPE", Varchar(10)),
+        column("PPI_SUB_SYMBOL", Varchar(4000)),
+        column("PPI_SUB_TAXON_CODE", Varchar(25)),
+        column("PPI_SUB_TAXON_COMMON", Varchar(100)),
+        column("PPI_SUB_SITES", Text),
+        column("PPI_PR_CODE", Varchar(25)),
+        column("PPI_PR_TYPE", Varchar(10)),
+        column("PPI_PR_SYMBOL", Varchar(4000)),
+        column("PPI_PR_TAXON_CODE", Varchar(25)),
+        column("PPI_PR_TAXON_COMMON", Varchar(100)),
+        column("PPI_SOURCE_LABEL", Varchar(10)),
+        column("PPI_NOTE", Text),
+        column("PPI_PMIDS", Text),
+        column("PTM_SUB_CODE", Varchar(25)),
+        column("PTM_SUB_TYPE", Varchar(10)),
+        column("PTM_SUB_SYMBOL", Varchar(4000)),
+        column("PTM_SUB_TAXON_CODE", Varchar(25)),
+        column("PTM_SUB_TAXON_COMMON", Varchar(100)),
+        column("PTM_SUB_SITES", Text),
+        column("PTM_ENZ_CODE", Varchar(25)),
+        column("PTM_ENZ_TYPE", Varchar(10)),
+        column("PTM_ENZ_SYMBOL", Varchar(4000)),
+        column("PTM_ENZ_TAXON_CODE", Varchar(25)),
+        column("PTM_ENZ_TAXON_COMMON", Varchar(100)),
+        column("PTM_EVENT_NAME", Varchar(50)),
+        column("PTM_EVENT_LABEL", Varchar(10)),
+        column("PTM_RESIDUE", Varchar(1)),
+        column("PTM_POSITION", BigInt),
+        column("PTM_SOURCE_LABEL", Varchar(10)),
+        column("PTM_NOTE", Text),
+        column("PTM_PMIDS", Text),
+    ],
+    computed: &[],
+};
+
+pub const MV_PROTEO: Table = Table {
+    name: "MV_PROTEO",
+    export: "./mv_proteo_export.csv",
+    columns: &[
+        column("SUB_CODE", Varchar(25)),
+        column("SUB_TYPE", Varchar(10)),
+        column("SUB_SYMBOL", Varchar(4000)),
+        column("SUB_SITES", Varchar(4000)),
+        column("SUB_XREF", Varchar(25)),
+        column("ENZ_CODE", Varchar(25)),
+        column("ENZ_TYPE", Varchar(10)),
+        column("ENZ_SYMBOL", Varchar(4000)),
+        column("ENZ_SITES", Varchar(4000)),
+        column("ENZ_XREF", Varchar(25)),
+        column("SITES", Varchar(4000)),
+        not_null("EVENT_NAME", Varchar(50)),
+        not_null("EVENT_LABEL", Varchar(10)),
+        not_null("SOURCE_LABEL", Varchar(10)),
+        not_null("IS_AUTO_GENERATED", Char(1)),
+        column("MODIFIER", Varchar(50)),
+        column("PMIDS", Varchar(4000)),
+    ],
+    computed: &[],
+};
+
+pub const SEQUENCE: Table = Table {
+    name: "SEQUENCE",
+    export: "./sequences.csv",
+    columns: &[column("ID", Varchar(25)), column("SEQ", Text)],
+    computed: &attributes::COLUMNS,
+};
+
+/// Links between events and the publications supporting them, split from
+/// the PMID lists of MV_EVENT and MV_EFIP. `SOURCE` names the list, e.g.
+/// `MV_EFIP.PPI_PMIDS`, as PPI and PTM events are numbered separately.
+pub const EVENT_PMID: Table = Table {
+    name: "EVENT_PMID",
+    export: "",
+    columns: &[
+        not_null("EVENT_ID", BigInt),
+        not_null("PMID", BigInt),
+        not_null("SOURCE", Varchar(25)),
+    ],
+    computed: &[],
+};
+
+/// Sites split from the site lists, e.g. `p-S15, ac-K120`, built with
+/// `--sites`. `PROTEIN` is the code of the protein the site is on and
+/// `OWNER_ID` the id of the row listing it, NULL for MV_PROTEO.
+pub const SITE: Table = Table {
+    name: "SITE",
+    export: "",
+    columns: &[
+        not_null("OWNER_TABLE", Varchar(25)),
+        not_null("OWNER_COLUMN", Varchar(25)),
+        column("OWNER_ID", BigInt),
+        column("PROTEIN", Varchar(25)),
+        not_null("RESIDUE", Varchar(1)),
+        not_null("POSITION", BigInt),
+        not_null("PTM_TYPE", Varchar(10)),
+    ],
+    computed: &[],
+};
+
+/// Flanking peptides of the modified positions of MV_EVENT, built with
+/// `--site-windows`. `PEPTIDE` has the modified residue in its middle.
+pub const SITE_WINDOW: Table = Table {
+    name: "SITE_WINDOW",
+    export: "",
+    columns: &[
+        not_null("IPTM_EVENT_ID", BigInt),
+        not_null("UNIPROT_ID", Varchar(50)),
+        not_null("POSITION", BigInt),
+        not_null("RESIDUE", Varchar(1)),
+        not_null("PEPTIDE", Text),
+    ],
+    computed: &[],
+};
+
+/// PSI-MOD terms of the EVENT_NAME and RESIDUE combinations of MV_EVENT,
+/// built with `--ontology`. A combination may map to several terms.
+pub const EVENT_TYPE_ONTOLOGY: Table = Table {
+    name: "EVENT_TYPE_ONTOLOGY",
+    export: "",
+    columns: &[
+        not_null("EVENT_NAME", Varchar(50)),
+        not_null("RESIDUE", Varchar(1)),
+        not_null("MOD_ID", Varchar(20)),
+        column("MOD_NAME", Text),
+    ],
+    computed: &[],
+};
+
+pub const fn key(expression: &'static str) -> IndexKey {
+    IndexKey { expression, opclass: None }
+}
+
+/// A key for trigram matching, which needs the `pg_trgm` extension.
+pub const fn trigram_key(expression: &'static str) -> IndexKey {
+    IndexKey {
+        expression,
+        opclass: Some("gin_trgm_ops"),
+    }
+}
+
+pub const fn btree(name: &'static str, table: &'static str, keys: &'static [IndexKey]) -> Index {
+    Index {
+        name,
+        table,
+        method: IndexMethod::BTree,
+        unique: false,
+        keys,
+        predicate: None,
+    }
+}
+
+pub const fn gin(name: &'static str, table: &'static str, keys: &'static [IndexKey]) -> Index {
+    Index {
+        name,
+        table,
+        method: IndexMethod::Gin,
+        unique: false,
+        keys,
+        predicate: None,
+    }
+}
+
+/// Every built-in index in the order it is created.
+pub const INDEXES: [Index; 14] = [
+    btree("uniprot_id_idx", "MV_ENTRY", &[key("UNIPROT_ID")]),
+    btree("protein_name_idx", "MV_ENTRY", &[key("PROTEIN_NAME")]),
+    btree("gene_name_idx", "MV_ENTRY", &[key("GENE_NAME")]),
+    btree("role_as_enzyme_idx", "MV_ENTRY", &[key("ROLE_AS_ENZYME")]),
+    btree("role_as_substrate_idx", "MV_ENTRY", &[key("ROLE_AS_SUBSTRATE")]),
+    btree("taxon_code_idx", "MV_ENTRY", &[key("TAXON_CODE")]),
+    btree("iptm_entry_code_idx", "MV_ENTRY", &[key("IPTM_ENTRY_CODE")]),
+    btree("sub_code_idx", "MV_EVENT", &[key("SUB_CODE")]),
+    btree("residue_idx", "MV_EVENT", &[key("RESIDUE")]),
+    btree("position_idx", "MV_EVENT", &[key("POSITION")]),
+    btree("enz_code_null_idx", "MV_EVENT", &[key("ENZ_CODE")]),
+    btree("sub_form_code_idx", "MV_EVENT", &[key("SUB_FORM_CODE")]),
+    btree("event_name_idx", "MV_EVENT", &[key("EVENT_NAME")]),
+    btree("seq_id_idx", "SEQUENCE", &[key("ID")]),
+];
+
+/// Every table in the order it is created and populated.
+pub const TABLES: [&Table; 5] = [&MV_ENTRY, &MV_EVENT, &MV_EFIP, &MV_PROTEO, &SEQUENCE];
+
+/// Tables built from the others once they are loaded, in the order they are
+/// built.
+pub const DERIVED: [&Table; 4] = [&EVENT_PMID, &SITE, &SITE_WINDOW, &EVENT_TYPE_ONTOLOGY];
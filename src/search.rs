@@ -0,0 +1,107 @@
+//! Optional search build step, run with `--search` once MV_ENTRY is loaded.
+//!
+//! The search box of the API matches protein and gene names and their
+//! synonyms. Plain B-tree indexes only help with exact and prefix matches, so
+//! this step adds
+//!
+//! * a generated `SEARCH` column with the `tsvector` of the names (weight A)
+//!   and synonyms (weight B), with a GIN index for full-text queries,
+//! * GIN trigram indexes on the four columns for substring and typo-tolerant
+//!   matches, and
+//! * the `search_entries(query, max_results)` function the API calls.
+//!
+//! The column and indexes go away with MV_ENTRY when the next run drops it,
+//! and the function is dropped with them, so that it never outlives the
+//! column it reads.
+
+use schema::{gin, key, trigram_key, Index};
+
+/// Columns searched, with the weight of their words in the ranking.
+const COLUMNS: [(&str, char); 4] = [
+    ("GENE_NAME", 'A'),
+    ("PROTEIN_NAME", 'A'),
+    ("GENE_SYNONYMS", 'B'),
+    ("PROTEIN_SYNONYMS", 'B'),
+];
+
+/// Text search configuration of the `SEARCH` column. Names are not stemmed.
+const TEXT_SEARCH_CONFIG: &str = "simple";
+
+pub const INDEXES: [Index; 5] = [
+    gin("entry_search_idx", "MV_ENTRY", &[key("SEARCH")]),
+    gin("entry_protein_name_trgm_idx", "MV_ENTRY", &[trigram_key("PROTEIN_NAME")]),
+    gin("entry_gene_name_trgm_idx", "MV_ENTRY", &[trigram_key("GENE_NAME")]),
+    gin("entry_protein_synonyms_trgm_idx", "MV_ENTRY", &[trigram_key("PROTEIN_SYNONYMS")]),
+    gin("entry_gene_synonyms_trgm_idx", "MV_ENTRY", &[trigram_key("GENE_SYNONYMS")]),
+];
+
+/// Signature of the function, as DROP FUNCTION needs it.
+pub const FUNCTION: &str = "search_entries(text, integer)";
+
+/// Run on every import, whether `--search` is given or not.
+pub fn drop_statement() -> String {
+    format!("DROP FUNCTION IF EXISTS {}", FUNCTION)
+}
+
+/// Returns the statements of the step in order, each with the name of the
+/// object it creates.
+pub fn statements() -> Vec<(&'static str, String)> {
+    let mut statements = vec![
+        ("pg_trgm", "CREATE EXTENSION IF NOT EXISTS pg_trgm".to_string()),
+        ("MV_ENTRY.SEARCH", add_column()),
+    ];
+    for index in INDEXES.iter() {
+        statements.push((index.name, index.create_statement()));
+    }
+    statements.push(("search_entries", function()));
+    statements
+}
+
+fn add_column() -> String {
+    let vectors: Vec<String> = COLUMNS
+        .iter()
+        .map(|&(column, weight)| {
+            format!(
+                "setweight(to_tsvector('{}', coalesce({}, '')), '{}')",
+                TEXT_SEARCH_CONFIG, column, weight
+            )
+        })
+        .collect();
+    format!(
+        "ALTER TABLE MV_ENTRY ADD COLUMN SEARCH tsvector GENERATED ALWAYS AS ({}) STORED",
+        vectors.join(" || ")
+    )
+}
+
+/// Entries whose names or synonyms match `query` as words, or are similar
+/// to it, best matches first. The score is the best of the full-text rank
+/// and the trigram similarities, between 0 and 1.
+///
+/// The body is kept as a string, so the function does not depend on
+/// MV_ENTRY, which is dropped before the function.
+fn function() -> String {
+    format!(
+        "\
+CREATE OR REPLACE FUNCTION search_entries(query text, max_results integer DEFAULT 50)
+RETURNS TABLE (iptm_entry_code varchar, uniprot_id varchar, protein_name varchar, gene_name varchar, score real)
+LANGUAGE sql STABLE AS $search$
+    SELECT e.IPTM_ENTRY_CODE, e.UNIPROT_ID, e.PROTEIN_NAME, e.GENE_NAME,
+           greatest(
+               ts_rank(e.SEARCH, plainto_tsquery('{config}', $1)),
+               similarity(e.PROTEIN_NAME, $1),
+               similarity(e.GENE_NAME, $1),
+               word_similarity($1, e.PROTEIN_SYNONYMS),
+               word_similarity($1, e.GENE_SYNONYMS)
+           )
+    FROM MV_ENTRY e
+    WHERE e.SEARCH @@ plainto_tsquery('{config}', $1)
+       OR e.PROTEIN_NAME % $1
+       OR e.GENE_NAME % $1
+       OR $1 <% e.PROTEIN_SYNONYMS
+       OR $1 <% e.GENE_SYNONYMS
+    ORDER BY 5 DESC, 1
+    LIMIT $2
+$search$",
+        config = TEXT_SEARCH_CONFIG
+    )
+}
@@ -0,0 +1,119 @@
+//! Parses the site lists, e.g. `p-S15, ac-K120`, into the SITE table when
+//! run with `--sites`.
+
+use postgres::Connection;
+
+use error::ImportError;
+use schema::{btree, key, Index, SITE};
+
+/// A column holding site lists.
+pub struct List {
+    pub table: &'static str,
+    /// Column of the row id, `None` for tables without one.
+    pub id: Option<&'static str>,
+    /// Column of the code of the protein the sites are on.
+    pub protein: &'static str,
+    pub column: &'static str,
+}
+
+pub const LISTS: [List; 4] = [
+    List {
+        table: "MV_ENTRY",
+        id: Some("IPTM_ENTRY_ID"),
+        protein: "IPTM_ENTRY_CODE",
+        column: "SITES",
+    },
+    List {
+        table: "MV_EVENT",
+        id: Some("IPTM_EVENT_ID"),
+        protein: "SUB_CODE",
+        column: "SUB_SITES",
+    },
+    List {
+        table: "MV_EVENT",
+        id: Some("IPTM_EVENT_ID"),
+        protein: "ENZ_CODE",
+        column: "ENZ_SITES",
+    },
+    List {
+        table: "MV_PROTEO",
+        id: None,
+        protein: "SUB_CODE",
+        column: "SITES",
+    },
+];
+
+/// Created once the table is filled. They go away with the table.
+pub const INDEXES: [Index; 3] = [
+    btree("site_protein_position_idx", "SITE", &[key("PROTEIN"), key("POSITION")]),
+    btree("site_type_position_idx", "SITE", &[key("PTM_TYPE"), key("POSITION")]),
+    btree("site_owner_idx", "SITE", &[key("OWNER_TABLE"), key("OWNER_ID")]),
+];
+
+/// Number of unparseable tokens quoted in the warning.
+const EXAMPLES: i64 = 5;
+
+/// The label of the modification, the residue and a position that fits a
+/// BIGINT, e.g. `p-S15`.
+const SITE_PATTERN: &str = "^([A-Za-z][A-Za-z0-9]{0,9})-([A-Za-z])([1-9][0-9]{0,17})$";
+
+pub struct Parsed {
+    pub sites: u64,
+    pub unparseable: u64,
+    /// Some of the unparseable tokens, e.g. `"S15" (IPTM_EVENT_ID 101)`.
+    pub examples: Vec<String>,
+}
+
+impl List {
+    /// `TABLE.COLUMN`.
+    pub fn source(&self) -> String {
+        format!("{}.{}", self.table, self.column)
+    }
+
+    /// Inserts every site of the list and counts the tokens that are not
+    /// sites. Lists are split at commas, semicolons and whitespace.
+    pub fn parse(&self, conn: &Connection) -> Result<Parsed, ImportError> {
+        let tokens = format!(
+            "(SELECT id, protein, token \
+             FROM (SELECT {id} AS id, {protein} AS protein, regexp_split_to_table({column}, '[[:space:],;]+') AS token \
+             FROM {table}) AS split WHERE token <> '') AS tokens",
+            id = self.id.unwrap_or("NULL::BIGINT"),
+            protein = self.protein,
+            column = self.column,
+            table = self.table
+        );
+
+        let sites = conn.execute(
+            &format!(
+                "INSERT INTO {} (OWNER_TABLE, OWNER_COLUMN, OWNER_ID, PROTEIN, RESIDUE, POSITION, PTM_TYPE) \
+                 SELECT DISTINCT $1, $2, id, protein, upper(site[2]), site[3]::BIGINT, site[1] \
+                 FROM (SELECT id, protein, regexp_match(token, '{}') AS site FROM {}) AS sites WHERE site IS NOT NULL",
+                SITE.name, SITE_PATTERN, tokens
+            ),
+            &[&self.table, &self.column],
+        )?;
+
+        let unparseable = format!("FROM {} WHERE token !~ '{}'", tokens, SITE_PATTERN);
+        let rows = conn.query(&format!("SELECT count(*) {}", unparseable), &[])?;
+        let count: i64 = rows.get(0).get(0);
+        let rows = conn.query(
+            &format!("SELECT id, token {} ORDER BY id, token LIMIT {}", unparseable, EXAMPLES),
+            &[],
+        )?;
+        let examples = rows
+            .iter()
+            .map(|row| {
+                let token: String = row.get(1);
+                match (self.id, row.get::<_, Option<i64>>(0)) {
+                    (Some(id), Some(value)) => format!("{:?} ({} {})", token, id, value),
+                    _ => format!("{:?}", token),
+                }
+            })
+            .collect();
+        Ok(Parsed {
+            sites,
+            unparseable: count as u64,
+            examples,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! Guesses the CSV dialect of an export from its first lines, for the
+//! `sniff` command.
+
+use std::io;
+use std::io::BufRead;
+
+use csv;
+use csv::Dialect;
+
+/// Number of lines looked at.
+const SAMPLE_LINES: usize = 200;
+
+const DELIMITERS: [u8; 4] = [b',', b'\t', b';', b'|'];
+
+const NULL_MARKERS: [&str; 2] = ["\\N", "NULL"];
+
+pub fn sniff<R: BufRead>(mut input: R) -> io::Result<Dialect> {
+    let mut sample = Vec::new();
+    for _ in 0..SAMPLE_LINES {
+        if input.read_until(b'\n', &mut sample)? == 0 {
+            break;
+        }
+    }
+    let complete = input.fill_buf()?.is_empty();
+    if sample.starts_with(b"\xEF\xBB\xBF") {
+        sample.drain(..3);
+    }
+
+    let mut dialect = Dialect::default();
+    if !contains(&sample, b"\"") && contains(&sample, b"'") {
+        dialect.quote = b'\'';
+        dialect.escape = b'\'';
+    }
+    let quote = char::from(dialect.quote);
+    if contains(&sample, format!("\\{}", quote).as_bytes()) && !contains(&sample, format!("{}{}", quote, quote).as_bytes()) {
+        dialect.escape = b'\\';
+    }
+
+    let mut best = (0, 0);
+    for &delimiter in DELIMITERS.iter() {
+        dialect.delimiter = delimiter;
+        let records = records(&sample, &dialect, complete)?;
+        let score = consistency(&records);
+        if score > best.1 {
+            best = (delimiter, score);
+        }
+    }
+    dialect.delimiter = if best.1 > 0 { best.0 } else { b',' };
+
+    let records = records(&sample, &dialect, complete)?;
+    dialect.header = looks_like_header(&records);
+
+    let body = if dialect.header { &records[1.min(records.len())..] } else { &records[..] };
+    let is_marker = |field: &csv::Field, marker: &str| !field.quoted && field.value == marker.as_bytes();
+    if let Some(marker) = NULL_MARKERS.iter().find(|marker| {
+        body.iter()
+            .flat_map(|record| record.fields.iter())
+            .any(|field| is_marker(field, marker))
+    }) {
+        dialect.null = marker.to_string();
+    }
+
+    // Quoted empty strings are NULL only with FORCE_NULL, which needs the
+    // column names from the header.
+    if dialect.header && dialect.null.is_empty() {
+        if let Some(header) = records.first() {
+            for (index, name) in header.fields.iter().enumerate() {
+                let quoted_empty = body.iter().any(|record| {
+                    record
+                        .fields
+                        .get(index)
+                        .is_some_and(|field| field.quoted && field.value.is_empty())
+                });
+                if quoted_empty {
+                    dialect.force_null.push(String::from_utf8_lossy(&name.value).into_owned());
+                }
+            }
+        }
+    }
+
+    Ok(dialect)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Parses the sample, leaving out a last record that may have been cut off.
+fn records(sample: &[u8], dialect: &Dialect, complete: bool) -> io::Result<Vec<csv::Record>> {
+    let mut reader = csv::Reader::new(sample, dialect);
+    let mut records = Vec::new();
+    while let Some(record) = reader.read_record()? {
+        records.push(record);
+    }
+    if !complete && records.len() > 1 {
+        records.pop();
+    }
+    Ok(records)
+}
+
+/// Number of records with the most common field count, or 0 when that
+/// count is 1, i.e. the delimiter does not split anything.
+fn consistency(records: &[csv::Record]) -> usize {
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    for record in records {
+        let fields = record.fields.len();
+        match counts.iter_mut().find(|entry| entry.0 == fields) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((fields, 1)),
+        }
+    }
+    match counts.iter().max_by_key(|entry| entry.1) {
+        Some(&(fields, records)) if fields > 1 => records,
+        _ => 0,
+    }
+}
+
+/// A header has only non-empty, distinct, non-numeric names, and either
+/// looks like identifiers or sits above a column of numbers.
+fn looks_like_header(records: &[csv::Record]) -> bool {
+    let header = match records.first() {
+        Some(header) => header,
+        None => return false,
+    };
+    let names: Vec<String> = header
+        .fields
+        .iter()
+        .map(|field| String::from_utf8_lossy(&field.value).into_owned())
+        .collect();
+    let numeric = |value: &str| value.trim().parse::<f64>().is_ok();
+    if names.iter().any(|name| name.is_empty() || numeric(name)) {
+        return false;
+    }
+    let mut distinct = names.clone();
+    distinct.sort();
+    distinct.dedup();
+    if distinct.len() != names.len() {
+        return false;
+    }
+
+    let identifiers = names.iter().all(|name| {
+        name.chars().next().is_some_and(|first| first.is_alphabetic() || first == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ' ')
+    });
+    let numbers_below = (0..names.len()).any(|index| {
+        records[1..].iter().any(|record| {
+            record
+                .fields
+                .get(index)
+                .is_some_and(|field| numeric(&String::from_utf8_lossy(&field.value)))
+        })
+    });
+    identifiers || numbers_below
+}
@@ -0,0 +1,122 @@
+//! Lock and statement timeouts, so that an import never hangs behind the
+//! queries of the API.
+//!
+//! DROP TABLE and DROP INDEX need an ACCESS EXCLUSIVE lock on tables that
+//! the API may be reading. While such a lock request waits, every new query
+//! on the table queues behind it, so the importer gives up after
+//! `lock_timeout`, reports or terminates the sessions in its way, and tries
+//! again after a growing backoff.
+
+use std::time::Duration;
+
+use postgres;
+use postgres::Connection;
+
+use error::ImportError;
+use lock;
+
+/// The backoff stops growing at this delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// What to do about sessions holding locks the import is waiting for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blockers {
+    Ignore,
+    /// Log them as warnings and in the error of the run.
+    Report,
+    /// Log and terminate them, so that the next attempt gets the lock.
+    Terminate,
+}
+
+pub struct Timeouts {
+    /// `lock_timeout` of the session, zero to wait forever.
+    pub lock: Duration,
+    /// `statement_timeout` of the session, zero for no limit.
+    pub statement: Duration,
+    /// Attempts after the first one to take the lock of a DDL statement.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one.
+    pub backoff: Duration,
+    pub blockers: Blockers,
+}
+
+impl Timeouts {
+    /// Sets the timeouts for the rest of the session.
+    pub fn apply(&self, conn: &Connection) -> Result<(), ImportError> {
+        conn.batch_execute(&format!(
+            "SET lock_timeout = {}; SET statement_timeout = {};",
+            self.lock.as_millis(),
+            self.statement.as_millis()
+        ))?;
+        Ok(())
+    }
+
+    /// Delay before retry number `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff.checked_mul(factor).map_or(MAX_BACKOFF, |delay| delay.min(MAX_BACKOFF))
+    }
+
+    /// Reports or terminates the sessions holding a lock on `target`, a
+    /// table or index, and returns their descriptions.
+    pub fn handle_blockers(&self, conn: &Connection, target: &str) -> Result<Vec<String>, ImportError> {
+        if self.blockers == Blockers::Ignore {
+            return Ok(Vec::new());
+        }
+        let condition = blocking(target);
+        let sessions = lock::sessions(conn, &condition)?;
+        for session in &sessions {
+            warn!(target = target; "{} is blocked by {}", target, session);
+        }
+        if self.blockers == Blockers::Terminate && !sessions.is_empty() {
+            conn.execute(
+                &format!("SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE {}", condition),
+                &[],
+            )?;
+            warn!(target = target; "Terminated {} sessions blocking {}", sessions.len(), target);
+        }
+        Ok(sessions)
+    }
+}
+
+impl Blockers {
+    pub fn parse(value: &str) -> Option<Blockers> {
+        match value {
+            "ignore" => Some(Blockers::Ignore),
+            "report" => Some(Blockers::Report),
+            "terminate" => Some(Blockers::Terminate),
+            _ => None,
+        }
+    }
+}
+
+/// Sessions other than this one holding a lock on the relation `target` or,
+/// for an index, on its table.
+fn blocking(target: &str) -> String {
+    let name = target.replace('\'', "''");
+    format!(
+        "pid <> pg_backend_pid() AND pid IN (SELECT pid FROM pg_locks WHERE granted AND relation IN (\
+         SELECT to_regclass('{name}')::oid UNION SELECT indrelid FROM pg_index WHERE indexrelid = to_regclass('{name}')))",
+        name = name
+    )
+}
+
+/// Parses `500ms`, `5s` or `2min`; a plain number is in seconds. Returns
+/// `None` for anything else, including minutes that overflow.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(number)),
+        "" | "s" => Some(Duration::from_secs(number)),
+        "min" => number.checked_mul(60).map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+/// Whether a statement failed because a lock was not granted in time.
+pub fn is_lock_timeout(error: &postgres::Error) -> bool {
+    error.code().is_some_and(|state| state.code() == "55P03")
+}
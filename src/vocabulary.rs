@@ -0,0 +1,182 @@
+//! Controlled vocabularies, such as the event names or the impacts, kept as
+//! lookup tables and checked against the loaded columns.
+//!
+//! Columns such as `MV_EVENT.EVENT_NAME` are free text, so a new spelling
+//! like `phosphorylation` next to `Phosphorylation` goes unnoticed. A
+//! vocabulary of the configuration file lists the allowed values and the
+//! columns that take them. Every import rebuilds its lookup table from the
+//! list and checks the columns against it; unknown values fail the import,
+//! are reported or are added to the lookup table, as configured. Foreign
+//! keys can tie the columns to the lookup table.
+
+use postgres::Connection;
+
+use config::OnUnknown;
+use error::ImportError;
+use schema::{Column, ColumnType, Table};
+
+/// The only column of a lookup table.
+pub const VALUE: Column = Column {
+    name: "VALUE",
+    kind: ColumnType::Text,
+    not_null: true,
+};
+
+/// Comment of every lookup table, by which the lookup tables of vocabularies
+/// no longer in the configuration are found and dropped.
+const MARK: &str = "lookup table of a vocabulary of the iptmnet importer";
+
+/// Number of unknown values quoted in the warning or error.
+const EXAMPLES: i64 = 5;
+
+/// A column that takes the values of a vocabulary.
+pub struct Member {
+    pub table: String,
+    pub column: String,
+}
+
+impl Member {
+    /// `TABLE.COLUMN`.
+    pub fn source(&self) -> String {
+        format!("{}.{}", self.table, self.column)
+    }
+}
+
+pub struct Vocabulary {
+    pub lookup: &'static Table,
+    pub values: Vec<String>,
+    pub members: Vec<Member>,
+    pub on_unknown: OnUnknown,
+    pub foreign_keys: bool,
+}
+
+pub struct Checked {
+    /// Non-NULL values of the column.
+    pub values: u64,
+    /// Rows with a value that is not in the vocabulary.
+    pub unknown_rows: u64,
+    /// Distinct values that are not in the vocabulary.
+    pub unknown: u64,
+    /// The most frequent unknown values, e.g. `"phosphorylation" (12 rows,
+    /// listed as "Phosphorylation")`.
+    pub examples: Vec<String>,
+}
+
+impl Vocabulary {
+    pub fn mark_statement(&self) -> String {
+        format!("COMMENT ON TABLE {} IS {}", self.lookup.name, literal(MARK))
+    }
+
+    pub fn fill_statement(&self) -> String {
+        let values: Vec<String> = self.values.iter().map(|value| format!("({})", literal(value))).collect();
+        format!("INSERT INTO {} ({}) VALUES {}", self.lookup.name, VALUE.name, values.join(", "))
+    }
+
+    /// Condition of the rows of `member`, as `t`, whose value is not in the
+    /// lookup table.
+    fn unknown(&self, member: &Member) -> String {
+        format!(
+            "t.{column} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {lookup} l WHERE l.{value} = t.{column})",
+            column = member.column,
+            lookup = self.lookup.name,
+            value = VALUE.name
+        )
+    }
+
+    /// Counts the values of a column that are not in the vocabulary.
+    pub fn check(&self, conn: &Connection, member: &Member) -> Result<Checked, ImportError> {
+        let unknown = self.unknown(member);
+        let rows = conn.query(
+            &format!(
+                "SELECT count(t.{column}), count(*) FILTER (WHERE {unknown}), count(DISTINCT t.{column}) FILTER (WHERE {unknown}) \
+                 FROM {table} t",
+                column = member.column,
+                unknown = unknown,
+                table = member.table
+            ),
+            &[],
+        )?;
+        let row = rows.get(0);
+        let (values, unknown_rows, distinct): (i64, i64, i64) = (row.get(0), row.get(1), row.get(2));
+        let rows = conn.query(
+            &format!(
+                "SELECT t.{column}, count(*), (SELECT min(l.{value}) FROM {lookup} l WHERE lower(l.{value}) = lower(t.{column})) \
+                 FROM {table} t WHERE {unknown} GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT {limit}",
+                column = member.column,
+                value = VALUE.name,
+                lookup = self.lookup.name,
+                table = member.table,
+                unknown = unknown,
+                limit = EXAMPLES
+            ),
+            &[],
+        )?;
+        let examples = rows
+            .iter()
+            .map(|row| {
+                let (value, count, listed): (String, i64, Option<String>) = (row.get(0), row.get(1), row.get(2));
+                match listed {
+                    Some(listed) => format!("{:?} ({} rows, listed as {:?})", value, count, listed),
+                    None => format!("{:?} ({} rows)", value, count),
+                }
+            })
+            .collect();
+        Ok(Checked {
+            values: values as u64,
+            unknown_rows: unknown_rows as u64,
+            unknown: distinct as u64,
+            examples,
+        })
+    }
+
+    /// Adds the values of a column that are not in the vocabulary yet.
+    pub fn add(&self, conn: &Connection, member: &Member) -> Result<u64, ImportError> {
+        let added = conn.execute(
+            &format!(
+                "INSERT INTO {lookup} ({value}) SELECT DISTINCT t.{column} FROM {table} t WHERE {unknown}",
+                lookup = self.lookup.name,
+                value = VALUE.name,
+                column = member.column,
+                table = member.table,
+                unknown = self.unknown(member)
+            ),
+            &[],
+        )?;
+        Ok(added)
+    }
+
+    /// Ties a column to the lookup table. When unknown values are only
+    /// reported the existing rows are not validated, as they may hold some.
+    pub fn foreign_key_statement(&self, member: &Member) -> String {
+        format!(
+            "ALTER TABLE {table} ADD CONSTRAINT {name} FOREIGN KEY ({column}) REFERENCES {lookup} ({value}){not_valid}",
+            table = member.table,
+            name = format!("{}_{}_fkey", member.table, member.column).to_lowercase(),
+            column = member.column,
+            lookup = self.lookup.name,
+            value = VALUE.name,
+            not_valid = if self.on_unknown == OnUnknown::Warn { " NOT VALID" } else { "" }
+        )
+    }
+}
+
+/// A string as an SQL literal.
+/// The lookup tables of the schema that are not among `vocabularies`, left
+/// behind by vocabularies that were removed from the configuration.
+pub fn stale_lookups(conn: &Connection, vocabularies: &[Vocabulary]) -> Result<Vec<String>, ImportError> {
+    let rows = conn.query(
+        "SELECT upper(relname) FROM pg_class \
+         WHERE relnamespace = current_schema()::regnamespace AND relkind = 'r' AND obj_description(oid, 'pg_class') = $1 \
+         ORDER BY 1",
+        &[&MARK],
+    )?;
+    Ok(rows
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .filter(|name| !vocabularies.iter().any(|vocabulary| vocabulary.lookup.name == name.as_str()))
+        .collect())
+}
+
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
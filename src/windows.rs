@@ -0,0 +1,38 @@
+//! Cuts the flanking peptide of every modified position from SEQUENCE into
+//! the SITE_WINDOW table when run with `--site-windows`.
+
+use postgres::Connection;
+
+use error::ImportError;
+use residues;
+use schema::{btree, key, Index, SITE_WINDOW};
+
+/// Residues on either side of the site unless `--window-flank` is given.
+pub const DEFAULT_FLANK: u32 = 7;
+
+/// Fills the part of a window that lies outside of the sequence.
+pub const PADDING: char = '_';
+
+/// Created once the table is filled. They go away with the table.
+pub const INDEXES: [Index; 2] = [
+    btree("site_window_peptide_idx", "SITE_WINDOW", &[key("PEPTIDE")]),
+    btree("site_window_protein_position_idx", "SITE_WINDOW", &[key("UNIPROT_ID"), key("POSITION")]),
+];
+
+/// Inserts the window of every matched event and returns their number.
+pub fn build(conn: &Connection, flank: u32) -> Result<u64, ImportError> {
+    let padding = PADDING.to_string().repeat(flank as usize);
+    let windows = conn.execute(
+        &format!(
+            "INSERT INTO {} (IPTM_EVENT_ID, UNIPROT_ID, POSITION, RESIDUE, PEPTIDE) \
+             SELECT e.IPTM_EVENT_ID, e.SUB_UNIPROT_ID, e.POSITION, upper(e.RESIDUE), \
+             substr($1 || s.SEQ || $1, e.POSITION::INTEGER, {}) {} AND {}",
+            SITE_WINDOW.name,
+            2 * flank + 1,
+            residues::CHECKED,
+            residues::MATCHED
+        ),
+        &[&padding],
+    )?;
+    Ok(windows)
+}
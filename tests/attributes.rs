@@ -0,0 +1,127 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+
+use postgres::Connection;
+
+use common::{append, assert_success, setup, stderr};
+
+/// LENGTH, MONOISOTOPIC_MASS, AVERAGE_MASS, CRC64 and NON_STANDARD of a
+/// sequence.
+type Attributes = (i64, Option<f64>, Option<f64>, String, String);
+
+fn attributes(conn: &Connection, id: &str) -> Attributes {
+    let rows = conn
+        .query(
+            "SELECT LENGTH, MONOISOTOPIC_MASS, AVERAGE_MASS, CRC64, NON_STANDARD FROM SEQUENCE WHERE ID = $1",
+            &[&id],
+        )
+        .unwrap();
+    let row = rows.get(0);
+    (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4))
+}
+
+#[test]
+fn stores_length_masses_and_crc64() {
+    let (cluster, data) = setup();
+    append(data.path(), "sequences.csv", &["GLYGLY,GG".to_string()]);
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        attributes(&conn, "GLYGLY"),
+        (2, Some(132.05348), Some(132.1191), "6878100000000000".to_string(), "N".to_string())
+    );
+    let (length, _, _, crc64, non_standard) = attributes(&conn, "P04637");
+    assert_eq!((length, crc64.as_str(), non_standard.as_str()), (393, "711F0E657A6661BA", "N"));
+    // The canonical sequence and the isoform are identical.
+    assert_eq!(attributes(&conn, "P31749").3, attributes(&conn, "P31749-1").3);
+}
+
+#[test]
+fn flags_non_standard_letters() {
+    let (cluster, data) = setup();
+    append(data.path(), "sequences.csv", &["SELENO,MKUL".to_string(), "UNKNOWN,MKXLB".to_string()]);
+    let output = cluster.import(data.path());
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(
+        log.contains("SEQUENCE: 2 sequences with non-standard letters, e.g. "),
+        "{}",
+        log
+    );
+    assert!(log.contains("UNKNOWN (X, B)"), "{}", log);
+
+    let conn = cluster.connect("iptmnet");
+    let (length, monoisotopic, average, _, non_standard) = attributes(&conn, "SELENO");
+    assert_eq!((length, monoisotopic, average, non_standard.as_str()), (4, Some(541.18371), Some(540.5802), "Y"));
+    let (length, monoisotopic, average, _, non_standard) = attributes(&conn, "UNKNOWN");
+    assert_eq!((length, monoisotopic, average, non_standard.as_str()), (5, None, None, "Y"));
+}
+
+#[test]
+fn computes_attributes_of_fasta_records_and_tolerant_loads() {
+    let (cluster, data) = setup();
+    fs::write(
+        data.path().join("sequences.fasta"),
+        ">sp|P04637|P53_HUMAN Cellular tumor antigen p53 OS=Homo sapiens OX=9606 GN=TP53 PE=1 SV=4\nMEEPQSDPSV\n>GLYGLY\nGG\n",
+    )
+    .unwrap();
+    let config = data.path().join("importer.toml");
+    fs::write(
+        &config,
+        "[tables.SEQUENCE]\nfile = \"./sequences.fasta\"\nheader_columns = [\"ENTRY_NAME\"]\n",
+    )
+    .unwrap();
+    // In tolerant mode the records pass the quarantine before they get their
+    // attributes, after the header columns.
+    assert_success(&cluster.import_with(data.path(), &["--config", config.to_str().unwrap(), "--tolerant"]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        attributes(&conn, "GLYGLY"),
+        (2, Some(132.05348), Some(132.1191), "6878100000000000".to_string(), "N".to_string())
+    );
+    assert_eq!(attributes(&conn, "P04637").0, 10);
+    let rows = conn.query("SELECT ENTRY_NAME FROM SEQUENCE WHERE ID = 'P04637'", &[]).unwrap();
+    assert_eq!(rows.get(0).get::<_, String>(0), "P53_HUMAN");
+}
+
+#[test]
+fn rejects_malformed_sequence_records_before_copy() {
+    let (cluster, data) = setup();
+    let line = append(data.path(), "sequences.csv", &["EXTRA,GG,GG".to_string()]);
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    let expected = format!("./sequences.csv: line {}: expected 2 fields, found 3", line);
+    assert!(log.contains(&expected), "{}", log);
+    assert_eq!(report["error_class"], "input");
+
+    let mut sequences = fs::read(data.path().join("sequences.csv")).unwrap();
+    let length = sequences.len();
+    sequences.truncate(length - "EXTRA,GG,GG\n".len());
+    sequences.extend_from_slice(b"LATIN1,GG\xe9\n");
+    fs::write(data.path().join("sequences.csv"), sequences).unwrap();
+    let output = cluster.import(data.path());
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    let expected = format!("./sequences.csv: line {}: SEQ has invalid UTF-8 at byte 2", line);
+    assert!(log.contains(&expected), "{}", log);
+}
+
+#[test]
+fn computed_columns_cannot_be_forced() {
+    let (cluster, data) = setup();
+    let config = data.path().join("importer.toml");
+    for list in ["force_null", "force_not_null"].iter() {
+        fs::write(&config, format!("[tables.SEQUENCE]\n{} = [\"LENGTH\"]\n", list)).unwrap();
+        let output = cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]);
+        assert!(!output.status.success());
+        let log = stderr(&output);
+        assert!(log.contains("tables.SEQUENCE: LENGTH is computed during the import, not read from the export, and cannot be forced"), "{}", log);
+    }
+}
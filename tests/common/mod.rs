@@ -0,0 +1,274 @@
+//! Test harness that runs the importer against a throwaway Postgres cluster.
+//!
+//! Every `TestCluster` owns a fresh data directory created with `initdb`,
+//! listens on a free local port and is shut down with `pg_ctl stop` when
+//! dropped. The Postgres binaries are looked up in `PG_BIN` if set, then on
+//! `PATH`. When the tests run as root (as they do in most containers),
+//! `initdb` and `pg_ctl` are run as `PG_TEST_OS_USER` (default `postgres`),
+//! because Postgres refuses to run as root.
+
+#![allow(dead_code)]
+
+extern crate postgres;
+extern crate serde_json;
+extern crate tempfile;
+
+use std::env;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use self::postgres::{Connection, TlsMode};
+use self::serde_json::Value;
+use self::tempfile::TempDir;
+
+pub const TABLES: [&str; 5] = ["mv_entry", "mv_event", "mv_efip", "mv_proteo", "sequence"];
+
+pub const EXPORTS: [&str; 5] = [
+    "mv_entry_export.csv",
+    "mv_event_export.csv",
+    "mv_efip_export.csv",
+    "mv_proteo_export.csv",
+    "sequences.csv",
+];
+
+pub struct TestCluster {
+    dir: TempDir,
+    port: u16,
+    os_user: Option<String>,
+}
+
+impl TestCluster {
+    /// Creates and starts a new cluster with an empty `iptmnet` database.
+    /// Panics when no Postgres installation can be found, so that a machine
+    /// without one never reports the database tests as passed.
+    pub fn start() -> TestCluster {
+        assert!(
+            pg_command("initdb").is_some(),
+            "initdb not found on PATH or in PG_BIN, the database tests need a Postgres installation"
+        );
+
+        let dir = TempDir::new().expect("could not create cluster directory");
+        let os_user = if is_root() {
+            let user = env::var("PG_TEST_OS_USER").unwrap_or_else(|_| "postgres".to_string());
+            let status = Command::new("chown")
+                .arg(&user)
+                .arg(dir.path())
+                .status()
+                .expect("could not run chown");
+            assert!(status.success(), "could not hand cluster directory to {}", user);
+            Some(user)
+        } else {
+            None
+        };
+
+        let cluster = TestCluster {
+            dir,
+            port: free_port(),
+            os_user,
+        };
+
+        let data = cluster.data_dir();
+        cluster.pg(
+            "initdb",
+            &["-D", &data, "-U", "postgres", "-A", "trust", "-E", "UTF8", "--no-sync"],
+        );
+
+        let options = format!(
+            "-F -p {} -k {} -c listen_addresses=127.0.0.1",
+            cluster.port,
+            cluster.dir.path().display()
+        );
+        let log_file = cluster.dir.path().join("postgres.log");
+        cluster.pg(
+            "pg_ctl",
+            &["-D", &data, "-l", log_file.to_str().unwrap(), "-o", &options, "-w", "start"],
+        );
+
+        cluster
+            .connect("postgres")
+            .execute("CREATE DATABASE iptmnet", &[])
+            .expect("could not create iptmnet database");
+
+        cluster
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn connect(&self, database: &str) -> Connection {
+        let url = format!("postgres://postgres@127.0.0.1:{}/{}", self.port, database);
+        Connection::connect(url.as_str(), TlsMode::None).expect("could not connect to test cluster")
+    }
+
+    /// Runs the importer binary with `data_dir` as its working directory,
+    /// which is where it looks for the CSV exports.
+    pub fn import(&self, data_dir: &Path) -> Output {
+        self.import_with(data_dir, &[])
+    }
+
+    pub fn import_with(&self, data_dir: &Path, args: &[&str]) -> Output {
+        let port = self.port.to_string();
+        Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+            .current_dir(data_dir)
+            .args(["--host", "127.0.0.1", "--port", &port, "--user", "postgres"])
+            .args(args)
+            .output()
+            .expect("could not run importer")
+    }
+
+    /// Runs the importer with `--report` and returns its output together
+    /// with the report, which is written whether the run succeeds or not.
+    pub fn import_reported(&self, data_dir: &Path, args: &[&str]) -> (Output, Value) {
+        let path = data_dir.join("report.json");
+        let mut args = args.to_vec();
+        args.extend_from_slice(&["--report", path.to_str().unwrap()]);
+        let output = self.import_with(data_dir, &args);
+        (output, read_report(&path))
+    }
+
+    fn data_dir(&self) -> String {
+        self.dir.path().join("data").to_str().unwrap().to_string()
+    }
+
+    fn pg(&self, program: &str, args: &[&str]) {
+        let output = self.pg_command(program, args).output().expect("could not run postgres binary");
+        assert!(
+            output.status.success(),
+            "{} failed:\n{}{}",
+            program,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn pg_command(&self, program: &str, args: &[&str]) -> Command {
+        let binary = pg_command(program).expect("postgres binary disappeared");
+        let mut command = match self.os_user {
+            Some(ref user) => {
+                let mut command = Command::new("runuser");
+                command.args(["-u", user, "--"]).arg(binary);
+                command
+            }
+            None => Command::new(binary),
+        };
+        command.args(args);
+        command
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        // Never panic here: a failing test is already unwinding.
+        let data = self.data_dir();
+        let _ = self
+            .pg_command("pg_ctl", &["-D", &data, "-m", "immediate", "-w", "stop"])
+            .output();
+    }
+}
+
+/// Starts a cluster and copies the fixture exports, the setup of most tests.
+pub fn setup() -> (TestCluster, TempDir) {
+    (TestCluster::start(), fixture_dir())
+}
+
+/// Copies the fixture exports into a scratch directory so that a test can
+/// break individual files without touching the checked-in fixtures.
+pub fn fixture_dir() -> TempDir {
+    let dir = TempDir::new().expect("could not create fixture directory");
+    for export in EXPORTS.iter() {
+        fs::copy(fixtures_path().join(export), dir.path().join(export)).expect("could not copy fixture");
+    }
+    dir
+}
+
+pub fn fixtures_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+/// Appends rows to an export and returns the line the first one starts on.
+pub fn append(dir: &Path, export: &str, rows: &[String]) -> usize {
+    let path = dir.join(export);
+    let line = fs::read_to_string(&path).unwrap().lines().count() + 1;
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    for row in rows {
+        writeln!(file, "{}", row).unwrap();
+    }
+    line
+}
+
+/// A copy of the first data row of an export with `from` replaced by `to`.
+pub fn variant(dir: &Path, export: &str, from: &str, to: &str) -> String {
+    let content = fs::read_to_string(dir.join(export)).unwrap();
+    let row = content.lines().nth(1).unwrap();
+    assert!(row.contains(from), "{} does not contain {}", export, from);
+    row.replacen(from, to, 1)
+}
+
+/// Number of data records in a fixture, i.e. lines minus the header.
+pub fn fixture_rows(dir: &Path, export: &str) -> i64 {
+    let content = fs::read_to_string(dir.join(export)).expect("could not read fixture");
+    content.lines().count() as i64 - 1
+}
+
+pub fn count(conn: &Connection, table: &str) -> i64 {
+    let rows = conn
+        .query(&format!("SELECT count(*) FROM {}", table), &[])
+        .expect("count query failed");
+    rows.get(0).get(0)
+}
+
+pub fn read_report(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).expect("report was not written")).expect("report is not JSON")
+}
+
+/// The steps of a report of one kind, e.g. `populate`.
+pub fn steps<'a>(report: &'a Value, kind: &str) -> Vec<&'a Value> {
+    report["steps"].as_array().unwrap().iter().filter(|step| step["step"] == kind).collect()
+}
+
+/// The first step of a report of one kind and, if given, for one target.
+pub fn step<'a>(report: &'a Value, kind: &str, target: Option<&str>) -> &'a Value {
+    steps(report, kind)
+        .into_iter()
+        .find(|step| target.is_none_or(|target| step["target"] == target))
+        .unwrap_or_else(|| panic!("no {} step for {:?} in {}", kind, target, report))
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+pub fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "importer failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn pg_command(program: &str) -> Option<PathBuf> {
+    let dirs = env::var_os("PG_BIN")
+        .map(|dir| vec![PathBuf::from(dir)])
+        .or_else(|| env::var_os("PATH").map(|path| env::split_paths(&path).collect()))
+        .unwrap_or_default();
+    dirs.into_iter().map(|dir| dir.join(program)).find(|path| path.is_file())
+}
+
+fn is_root() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+        .unwrap_or(false)
+}
+
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("could not find a free port");
+    listener.local_addr().unwrap().port()
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{append, setup, stderr, variant};
+
+#[test]
+fn names_line_and_column_of_rejected_record() {
+    let (cluster, data) = setup();
+    let export = "mv_event_export.csv";
+    let multiline = variant(data.path(), export, "101,", "105,").replacen(",,,\"", ",,\"two\nlines\",\"", 1);
+    let position = variant(data.path(), export, "101,", "106,").replacen(",S,15,", ",S,abc,", 1);
+    let line = append(data.path(), export, &[multiline, position]) + 2;
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert!(!output.status.success());
+
+    let expected = format!("at ./mv_event_export.csv line {}, column POSITION = \"abc\": 106,", line);
+    let log = stderr(&output);
+    assert!(log.contains(&expected), "{}", log);
+
+    assert!(report["error"].as_str().unwrap().contains(&expected));
+    assert_eq!(report["error_class"], "data");
+}
+
+#[test]
+fn accounts_for_rows_left_out_in_tolerant_mode() {
+    let (cluster, data) = setup();
+    let export = "mv_event_export.csv";
+    // The client-side checks pass an unterminated quote in the last field,
+    // COPY does not.
+    let rejected = variant(data.path(), export, "101,", "106,")
+        .replacen(",S,15,", ",ST,15,", 1)
+        .replacen(",,,\"", ",,\"two\nlines\",\"", 1);
+    let good = variant(data.path(), export, "101,", "105,");
+    let unterminated = format!("{}\"oops", variant(data.path(), export, "101,", "107,"));
+    let line = append(data.path(), export, &[rejected, good, unterminated]) + 3;
+
+    let output = cluster.import_with(data.path(), &["--tolerant"]);
+    assert!(!output.status.success());
+
+    let log = stderr(&output);
+    assert!(log.contains("unterminated CSV quoted field"), "{}", log);
+    let expected = format!("at ./mv_event_export.csv line {}: 107,", line);
+    assert!(log.contains(&expected), "{}", log);
+}
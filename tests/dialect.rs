@@ -0,0 +1,154 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{assert_success, count, setup, stderr};
+
+/// An MV_EVENT row with `\N` for NULL in every column that is not set.
+fn event_row(id: u32, residue: &str, position: &str, note: &str) -> String {
+    let mut fields = vec!["\\N"; 29];
+    let id = id.to_string();
+    fields[0] = &id;
+    fields[2] = "P04637";
+    fields[19] = "Phosphorylation";
+    fields[23] = residue;
+    fields[24] = position;
+    fields[26] = note;
+    fields.join("\t")
+}
+
+/// Writes a headerless TSV export of MV_EVENT and a config that points to it.
+fn write_tsv(dir: &Path, rows: &[String]) -> String {
+    let mut content = rows.join("\n");
+    content.push('\n');
+    fs::write(dir.join("mv_event.tsv"), content).unwrap();
+    fs::remove_file(dir.join("mv_event_export.csv")).unwrap();
+
+    let config = dir.join("importer.toml");
+    fs::write(
+        &config,
+        "[tables.MV_EVENT]\nfile = \"./mv_event.tsv\"\ndelimiter = \"\\t\"\nnull = \"\\\\N\"\nheader = false\n\n\
+         [tables.mv_entry]\nforce_null = [\"GENE_NAME\"]\nforce_not_null = [\"NOTE\"]\n",
+    )
+    .unwrap();
+    config.to_str().unwrap().to_string()
+}
+
+#[test]
+fn reads_exports_in_configured_dialect() {
+    let (cluster, data) = setup();
+    let rows = [
+        event_row(101, "S", "15", "\"quoted, with tab\t inside\""),
+        event_row(102, "K", "120", "\\N"),
+    ];
+    let config = write_tsv(data.path(), &rows);
+    // An Oracle style export with an empty string instead of NULL.
+    let entries = fs::read_to_string(data.path().join("mv_entry_export.csv")).unwrap();
+    fs::write(
+        data.path().join("mv_entry_export.csv"),
+        entries.replacen("Cellular tumor antigen p53,TP53,", "Cellular tumor antigen p53,\"\",", 1),
+    )
+    .unwrap();
+
+    assert_success(&cluster.import_with(data.path(), &["--config", &config]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_event"), 2);
+    let rows = conn
+        .query("SELECT note, enz_code, position FROM mv_event ORDER BY iptm_event_id", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, Option<String>>(0), Some("quoted, with tab\t inside".to_string()));
+    assert_eq!(rows.get(0).get::<_, Option<String>>(1), None);
+    assert_eq!(rows.get(1).get::<_, Option<String>>(0), None);
+    assert_eq!(rows.get(1).get::<_, i64>(2), 120);
+
+    let rows = conn
+        .query("SELECT gene_name, note FROM mv_entry WHERE iptm_entry_id = 1", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, Option<String>>(0), None);
+    assert_eq!(rows.get(0).get::<_, Option<String>>(1), Some(String::new()));
+}
+
+#[test]
+fn tolerant_mode_uses_configured_dialect() {
+    let (cluster, data) = setup();
+    let rows = [
+        event_row(101, "S", "15", "\\N"),
+        event_row(102, "ST", "120", "\\N"),
+        event_row(103, "T", "308", "\\N"),
+    ];
+    let config = write_tsv(data.path(), &rows);
+
+    assert_success(&cluster.import_with(data.path(), &["--config", &config, "--tolerant"]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_event"), 2);
+    let rejects = fs::read_to_string(data.path().join("mv_event.rejects.csv")).unwrap();
+    assert!(rejects.lines().nth(1).unwrap().starts_with("2,\"RESIDUE"), "{}", rejects);
+}
+
+#[test]
+fn invalid_config_fails_before_importing() {
+    let (cluster, data) = setup();
+    let config = data.path().join("importer.toml");
+
+    fs::write(&config, "[tables.MV_EVENT]\ndelimiter = \"::\"\n").unwrap();
+    let (output, report) = cluster.import_reported(data.path(), &["--config", config.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("delimiter must be a single ASCII character"));
+    assert_eq!(report["error_class"], "config");
+
+    fs::write(&config, "[tables.MV_EVENTS]\nheader = false\n").unwrap();
+    let output = cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]);
+    assert!(stderr(&output).contains("unknown table MV_EVENTS"));
+
+    fs::write(&config, "[tables.MV_EVENT]\nforce_null = [\"NOTES\"]\n").unwrap();
+    let output = cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]);
+    assert!(stderr(&output).contains("unknown column NOTES"));
+}
+
+#[test]
+fn sniffed_dialect_can_be_used_as_config() {
+    let (cluster, data) = setup();
+    let csv = fs::read_to_string(data.path().join("mv_event_export.csv")).unwrap();
+    let header = csv.lines().next().unwrap().replace(',', "\t");
+    let rows = [header, event_row(101, "S", "15", "\\N"), event_row(102, "K", "120", "\\N")];
+    fs::write(data.path().join("mv_event_export.tsv"), rows.join("\n") + "\n").unwrap();
+    fs::remove_file(data.path().join("mv_event_export.csv")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .current_dir(data.path())
+        .args(["sniff", "./mv_event_export.tsv"])
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let proposal = String::from_utf8(output.stdout).unwrap();
+    assert!(proposal.contains("[tables.MV_EVENT]"), "{}", proposal);
+    assert!(proposal.contains("delimiter = \"\\t\""), "{}", proposal);
+    assert!(proposal.contains("null = \"\\\\N\""), "{}", proposal);
+    assert!(proposal.contains("header = true"), "{}", proposal);
+
+    let config = data.path().join("importer.toml");
+    fs::write(&config, proposal).unwrap();
+    assert_success(&cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]));
+    assert_eq!(count(&cluster.connect("iptmnet"), "mv_event"), 2);
+}
+
+#[test]
+fn sniff_detects_comma_separated_exports() {
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .arg("sniff")
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mv_entry_export.csv"))
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let proposal = String::from_utf8(output.stdout).unwrap();
+    assert!(proposal.contains("[tables.MV_ENTRY]"), "{}", proposal);
+    assert!(proposal.contains("delimiter = \",\""), "{}", proposal);
+    assert!(proposal.contains("header = true"), "{}", proposal);
+    assert!(!proposal.contains("null ="), "{}", proposal);
+}
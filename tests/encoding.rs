@@ -0,0 +1,115 @@
+extern crate serde_json;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{assert_success, count, setup, step, TestCluster};
+use serde_json::Value;
+
+const EXPORT: &str = "mv_entry_export.csv";
+const NAME: &str = "Cellular tumor antigen p53";
+
+/// Rewrites the MV_ENTRY export with `name` (raw bytes) as the protein name
+/// of its first entry, optionally with a BOM and CRLF line endings.
+fn rewrite_entry(dir: &Path, name: &[u8], bom: bool, crlf: bool) {
+    let content = fs::read_to_string(dir.join(EXPORT)).unwrap();
+    let mut bytes = Vec::new();
+    if bom {
+        bytes.extend_from_slice(b"\xEF\xBB\xBF");
+    }
+    for line in content.lines() {
+        match line.find(NAME) {
+            Some(index) => {
+                bytes.extend_from_slice(&line.as_bytes()[..index]);
+                bytes.extend_from_slice(name);
+                bytes.extend_from_slice(&line.as_bytes()[index + NAME.len()..]);
+            }
+            None => bytes.extend_from_slice(line.as_bytes()),
+        }
+        bytes.extend_from_slice(if crlf { b"\r\n" } else { b"\n" });
+    }
+    fs::write(dir.join(EXPORT), bytes).unwrap();
+}
+
+fn protein_name(cluster: &TestCluster) -> String {
+    let conn = cluster.connect("iptmnet");
+    let rows = conn.query("SELECT protein_name FROM mv_entry WHERE iptm_entry_id = 1", &[]).unwrap();
+    rows.get(0).get(0)
+}
+
+fn converted(report: &Value) -> Value {
+    step(report, "populate", Some("MV_ENTRY"))["converted"].clone()
+}
+
+#[test]
+fn transcodes_windows_1252_with_bom_and_crlf() {
+    let (cluster, data) = setup();
+    rewrite_entry(data.path(), b"Prot\xe9ine \x96 10 \xb5M", true, true);
+
+    let (output, report) = cluster.import_reported(data.path(), &["--encoding", "windows-1252"]);
+    assert_success(&output);
+
+    assert_eq!(protein_name(&cluster), "Protéine – 10 µM");
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_entry"), 4);
+    let rows = conn.query("SELECT count(*) FROM mv_entry WHERE gene_syn LIKE '%\r'", &[]).unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0, "CR left at the end of the last column");
+    assert_eq!(converted(&report), 1);
+}
+
+#[test]
+fn latin1_maps_every_byte_to_its_code_point() {
+    let (cluster, data) = setup();
+    rewrite_entry(data.path(), b"\xb5-crystallin \xe0", false, false);
+
+    assert_success(&cluster.import_with(data.path(), &["--encoding", "latin1"]));
+    assert_eq!(protein_name(&cluster), "µ-crystallin à");
+}
+
+#[test]
+fn auto_detects_encoding_per_line() {
+    let (cluster, data) = setup();
+    rewrite_entry(data.path(), b"Caf\xe9", false, false);
+    // The second entry is already UTF-8 and must be left alone.
+    let bytes = fs::read(data.path().join(EXPORT)).unwrap();
+    let index = bytes.windows(9).position(|window| window == b"RAC-alpha").unwrap();
+    let mut rewritten = bytes[..index].to_vec();
+    rewritten.extend_from_slice("RAC-α".as_bytes());
+    rewritten.extend_from_slice(&bytes[index + 9..]);
+    fs::write(data.path().join(EXPORT), rewritten).unwrap();
+
+    let (output, report) = cluster.import_reported(data.path(), &["--encoding", "auto"]);
+    assert_success(&output);
+
+    assert_eq!(protein_name(&cluster), "Café");
+    let conn = cluster.connect("iptmnet");
+    let rows = conn.query("SELECT protein_name FROM mv_entry WHERE iptm_entry_id = 2", &[]).unwrap();
+    let name: String = rows.get(0).get(0);
+    assert!(name.starts_with("RAC-α"));
+    assert_eq!(converted(&report), 1);
+}
+
+#[test]
+fn utf8_exports_with_bom_and_crlf_import_by_default() {
+    let (cluster, data) = setup();
+    rewrite_entry(data.path(), "Protéine p53".as_bytes(), true, true);
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    assert_eq!(protein_name(&cluster), "Protéine p53");
+    assert!(converted(&report).is_null());
+}
+
+#[test]
+fn keeps_crlf_inside_quoted_fields() {
+    let (cluster, data) = setup();
+    rewrite_entry(data.path(), b"\"Cellular tumor\r\nantigen p53\"", false, true);
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    assert_eq!(protein_name(&cluster), "Cellular tumor\r\nantigen p53");
+    assert_eq!(count(&cluster.connect("iptmnet"), "mv_entry"), 4);
+    assert!(converted(&report).is_null());
+}
@@ -0,0 +1,106 @@
+extern crate flate2;
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use common::{assert_success, count, setup, stderr, step};
+
+const FASTA: &str = ">sp|P04637|P53_HUMAN Cellular tumor antigen p53 OS=Homo sapiens OX=9606 GN=TP53 PE=1 SV=4\n\
+                     MEEPQSDPSV\n\
+                     EPPLSQETFS\n\
+                     DLWKLLPEN\n\
+                     >sp|P31749-2|AKT1_HUMAN Isoform 2 of RAC-alpha serine/threonine-protein kinase OS=Homo sapiens OX=9606 GN=AKT1\n\
+                     MSDVAIVKEG\n\
+                     >tr|A0A024R161|A0A024R161_HUMAN Guanine nucleotide-binding protein subunit gamma OS=Escherichia coli O157:H7 OX=83334 PE=3 SV=1\n\
+                     MKKLL\n\
+                     >custom-1 made up\n\
+                     MAAA*\n";
+
+fn write_config(dir: &Path, content: &str) -> String {
+    let config = dir.join("importer.toml");
+    fs::write(&config, content).unwrap();
+    config.to_str().unwrap().to_string()
+}
+
+/// ID, SEQ, ENTRY_NAME, ORGANISM and SEQUENCE_VERSION of a row of SEQUENCE.
+type Sequence = (String, String, Option<String>, Option<String>, Option<i64>);
+
+#[test]
+fn imports_gzipped_uniprot_fasta_with_header_columns() {
+    let (cluster, data) = setup();
+    let path = data.path().join("uniprot.fasta.gz");
+    let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+    encoder.write_all(FASTA.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    let config = write_config(
+        data.path(),
+        "[tables.SEQUENCE]\nfile = \"./uniprot.fasta.gz\"\nheader_columns = [\"ENTRY_NAME\", \"ORGANISM\", \"SEQUENCE_VERSION\"]\n",
+    );
+    let (output, report) = cluster.import_reported(data.path(), &["--config", &config]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query("SELECT ID, SEQ, ENTRY_NAME, ORGANISM, SEQUENCE_VERSION FROM SEQUENCE ORDER BY ID", &[])
+        .unwrap();
+    let sequences: Vec<Sequence> = rows
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4)))
+        .collect();
+    let text = |value: &str| Some(value.to_string());
+    assert_eq!(
+        sequences,
+        [
+            ("A0A024R161".to_string(), "MKKLL".to_string(), text("A0A024R161_HUMAN"), text("Escherichia coli O157:H7"), Some(1)),
+            ("P04637".to_string(), "MEEPQSDPSVEPPLSQETFSDLWKLLPEN".to_string(), text("P53_HUMAN"), text("Homo sapiens"), Some(4)),
+            ("P31749-2".to_string(), "MSDVAIVKEG".to_string(), text("AKT1_HUMAN"), text("Homo sapiens"), None),
+            ("custom-1".to_string(), "MAAA".to_string(), None, None, None),
+        ]
+    );
+
+    let populate = step(&report, "populate", Some("SEQUENCE"));
+    assert_eq!(populate["rows"], 4);
+    assert_eq!(populate["bytes"].as_u64().unwrap(), fs::metadata(&path).unwrap().len());
+}
+
+#[test]
+fn imports_plain_fasta_into_id_and_seq() {
+    let (cluster, data) = setup();
+    fs::write(data.path().join("sequences.fasta"), FASTA).unwrap();
+    let config = write_config(data.path(), "[tables.SEQUENCE]\nfile = \"./sequences.fasta\"\n");
+    assert_success(&cluster.import_with(data.path(), &["--config", &config]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "SEQUENCE"), 4);
+    let rows = conn
+        .query("SELECT count(*) FROM information_schema.columns WHERE table_name = 'sequence'", &[])
+        .unwrap();
+    // ID and SEQ, followed by the computed attributes.
+    assert_eq!(rows.get(0).get::<_, i64>(0), 7);
+}
+
+#[test]
+fn rejects_malformed_fasta_and_misplaced_header_columns() {
+    let (cluster, data) = setup();
+    fs::write(data.path().join("sequences.fasta"), format!("MKKLL\n{}", FASTA)).unwrap();
+    let config = write_config(data.path(), "[tables.SEQUENCE]\nfile = \"./sequences.fasta\"\n");
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    assert!(log.contains("line 1: sequence data before the first header"), "{}", log);
+
+    let config = write_config(data.path(), "[tables.SEQUENCE]\nheader_columns = [\"ORGANISM\"]\n");
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    assert!(log.contains("header_columns can only be given for SEQUENCE read from a FASTA file"), "{}", log);
+}
@@ -0,0 +1,99 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{assert_success, count, fixture_rows, stderr, TestCluster, EXPORTS, TABLES};
+use tempfile::TempDir;
+
+fn generate(dir: &Path, seed: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .args(["generate-fixtures", "--entries", "60", "--events", "300", "--efip", "40", "--proteo", "30"])
+        .arg("--seed")
+        .arg(seed)
+        .arg("--out-dir")
+        .arg(dir)
+        .output()
+        .expect("could not run importer");
+    assert_success(&output);
+}
+
+#[test]
+fn same_seed_gives_identical_files() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    let other = TempDir::new().unwrap();
+    generate(first.path(), "42");
+    generate(second.path(), "42");
+    generate(other.path(), "43");
+
+    for export in EXPORTS.iter() {
+        let first = fs::read(first.path().join(export)).unwrap();
+        assert_eq!(first, fs::read(second.path().join(export)).unwrap(), "{} differs", export);
+        assert_ne!(first, fs::read(other.path().join(export)).unwrap(), "{} ignores the seed", export);
+    }
+    assert_eq!(fixture_rows(first.path(), "mv_entry_export.csv"), 60);
+    assert_eq!(fixture_rows(first.path(), "mv_event_export.csv"), 300);
+    assert_eq!(fixture_rows(first.path(), "mv_efip_export.csv"), 40);
+    assert_eq!(fixture_rows(first.path(), "mv_proteo_export.csv"), 30);
+}
+
+#[test]
+fn generated_files_import_and_are_consistent() {
+    let cluster = TestCluster::start();
+    let data = TempDir::new().unwrap();
+    generate(data.path(), "7");
+
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    for (table, export) in TABLES.iter().zip(EXPORTS.iter()) {
+        assert_eq!(count(&conn, table), fixture_rows(data.path(), export), "row count of {}", table);
+    }
+
+    let checks = [
+        // every substrate and enzyme is a generated entry
+        "SELECT count(*) FROM mv_event e
+         WHERE NOT EXISTS (SELECT 1 FROM mv_entry m WHERE m.iptm_entry_code = e.sub_code)",
+        "SELECT count(*) FROM mv_event e
+         WHERE e.enz_code IS NOT NULL
+         AND NOT EXISTS (SELECT 1 FROM mv_entry m WHERE m.iptm_entry_code = e.enz_code)",
+        // every site lies inside the substrate sequence and matches its residue
+        "SELECT count(*) FROM mv_event e
+         LEFT JOIN sequence s ON s.id = e.sub_uniprot_id
+         WHERE s.id IS NULL OR e.position > length(s.seq) OR substr(s.seq, e.position::int, 1) <> e.residue",
+        // eFIP rows describe generated PTM events
+        "SELECT count(*) FROM mv_efip f
+         WHERE NOT EXISTS (SELECT 1 FROM mv_event e WHERE e.iptm_event_id = f.ptm_event_id
+                           AND e.residue = f.ptm_residue AND e.position = f.ptm_position)",
+        // proteoform rows describe generated PRO entries
+        "SELECT count(*) FROM mv_proteo p
+         WHERE NOT EXISTS (SELECT 1 FROM mv_entry m WHERE m.iptm_entry_code = p.sub_code AND m.iptm_entry_type = 'pro')",
+    ];
+    for check in checks.iter() {
+        let rows = conn.query(check, &[]).unwrap();
+        let violations: i64 = rows.get(0).get(0);
+        assert_eq!(violations, 0, "{}", check);
+    }
+}
+
+#[test]
+fn proteoform_rows_without_pro_forms_fail() {
+    let dir = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .args(["generate-fixtures", "--entries", "1", "--events", "5", "--proteo", "3"])
+        .arg("--out-dir")
+        .arg(dir.path())
+        .output()
+        .expect("could not run importer");
+    assert!(!output.status.success(), "generating proteoform rows without PRO forms succeeded");
+    let log = stderr(&output);
+    assert!(
+        log.contains("3 proteoform rows requested but 0 written: none of the 5 events is on an entry with a PRO form"),
+        "{}",
+        log
+    );
+}
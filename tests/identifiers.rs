@@ -0,0 +1,79 @@
+extern crate tempfile;
+
+mod common;
+
+use common::{append, assert_success, setup, stderr, step, steps, variant};
+
+#[test]
+fn fixtures_have_valid_identifiers() {
+    let (cluster, data) = setup();
+    // Isoforms and UniProt-based PRO IDs are valid too.
+    let row = variant(data.path(), "mv_event_export.csv", "101,,P04637,uniprot,P04637,", "105,PR:P31749-2,P31749-2,uniprot,P31749-2,");
+    append(data.path(), "mv_event_export.csv", &[row]);
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(!log.contains("invalid values"), "{}", log);
+
+    assert_eq!(steps(&report, "validate").len(), 21);
+    let sub_code = step(&report, "validate", Some("MV_EVENT.SUB_CODE"));
+    assert_eq!(sub_code["rows"], 5);
+    assert_eq!(sub_code["rejected"], 0);
+}
+
+#[test]
+fn reports_invalid_identifiers_per_column() {
+    let (cluster, data) = setup();
+    let entry = variant(data.path(), "mv_entry_export.csv", "1,P04637,uniprot,TP53,P04637,", "4,P0463,uniprot,TP53, P04637,")
+        .replacen(",Y,9606,", ",Y,96O6,", 1);
+    append(data.path(), "mv_entry_export.csv", &[entry]);
+    let event = variant(data.path(), "mv_event_export.csv", "101,,P04637,", "105,PR:12345,p04637,");
+    append(data.path(), "mv_event_export.csv", &[event]);
+    let interaction = variant(data.path(), "mv_efip_export.csv", "201,101,", "203,105,").replacen(",15053880,P04637,", ",15053880,XP04637,", 1);
+    append(data.path(), "mv_efip_export.csv", &[interaction]);
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    let log = stderr(&output);
+    for expected in [
+        "MV_ENTRY.IPTM_ENTRY_CODE: 1 invalid values, expected a UniProt accession or a PRO ID, e.g. \"P0463\" (IPTM_ENTRY_ID 4)",
+        "MV_ENTRY.UNIPROT_ID: 1 invalid values, expected a UniProt accession, e.g. \" P04637\" (IPTM_ENTRY_ID 4)",
+        "MV_ENTRY.TAXON_CODE: 1 invalid values, expected an NCBI taxon ID, e.g. \"96O6\" (IPTM_ENTRY_ID 4)",
+        "MV_EVENT.SUB_FORM_CODE: 1 invalid values, expected a PRO ID, e.g. \"PR:12345\" (IPTM_EVENT_ID 105)",
+        "MV_EVENT.SUB_CODE: 1 invalid values, expected a UniProt accession or a PRO ID, e.g. \"p04637\" (IPTM_EVENT_ID 105)",
+        "MV_EFIP.PTM_SUB_CODE: 1 invalid values, expected a UniProt accession or a PRO ID, e.g. \"XP04637\" (PTM_EVENT_ID 105)",
+    ]
+    .iter()
+    {
+        assert!(log.contains(expected), "{} not in {}", expected, log);
+    }
+
+    let rejected: u64 = steps(&report, "validate")
+        .iter()
+        .map(|step| step["rejected"].as_u64().unwrap())
+        .sum();
+    assert_eq!(rejected, 6);
+}
+
+#[test]
+fn interactions_are_named_by_the_event_of_the_column() {
+    let (cluster, data) = setup();
+    // PPI_PR_CODE is Q00987 and PTM_ENZ_TAXON_CODE the second 9606 after P06493.
+    let interaction = variant(data.path(), "mv_efip_export.csv", "201,101,", "203,105,")
+        .replacen(",Q00987,", ",Q0098,", 1)
+        .replacen(",P06493,uniprot,CDK1,9606,", ",P06493,uniprot,CDK1,96x6,", 1);
+    append(data.path(), "mv_efip_export.csv", &[interaction]);
+
+    let output = cluster.import(data.path());
+    assert_success(&output);
+    let log = stderr(&output);
+    for expected in [
+        "MV_EFIP.PPI_PR_CODE: 1 invalid values, expected a UniProt accession or a PRO ID, e.g. \"Q0098\" (PPI_EVENT_ID 203)",
+        "MV_EFIP.PTM_ENZ_TAXON_CODE: 1 invalid values, expected an NCBI taxon ID, e.g. \"96x6\" (PTM_EVENT_ID 105)",
+    ]
+    .iter()
+    {
+        assert!(log.contains(expected), "{} not in {}", expected, log);
+    }
+    assert!(!log.contains("(PPI_EVENT_ID 105)") && !log.contains("(PTM_EVENT_ID 203)"), "{}", log);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::fs;
+
+use common::{assert_success, count, fixture_rows, setup, EXPORTS, TABLES};
+
+#[test]
+fn imports_every_table() {
+    let (cluster, data) = setup();
+
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    for (table, export) in TABLES.iter().zip(EXPORTS.iter()) {
+        assert_eq!(
+            count(&conn, table),
+            fixture_rows(data.path(), export),
+            "row count of {}",
+            table
+        );
+    }
+}
+
+#[test]
+fn creates_expected_schema() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    let expected_columns = [("mv_entry", 32), ("mv_event", 29), ("mv_efip", 35), ("mv_proteo", 17), ("sequence", 7)];
+    for &(table, columns) in expected_columns.iter() {
+        let rows = conn
+            .query(
+                "SELECT count(*) FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1",
+                &[&table],
+            )
+            .unwrap();
+        let actual: i64 = rows.get(0).get(0);
+        assert_eq!(actual, columns, "column count of {}", table);
+    }
+
+    let rows = conn
+        .query(
+            "SELECT data_type, character_maximum_length, is_nullable
+             FROM information_schema.columns
+             WHERE table_name = 'mv_event' AND column_name = 'residue'",
+            &[],
+        )
+        .unwrap();
+    let row = rows.get(0);
+    let data_type: String = row.get(0);
+    let length: Option<i32> = row.get(1);
+    let nullable: String = row.get(2);
+    assert_eq!(data_type, "character varying");
+    assert_eq!(length, Some(1));
+    assert_eq!(nullable, "YES");
+
+    let rows = conn
+        .query(
+            "SELECT is_nullable FROM information_schema.columns
+             WHERE table_name = 'mv_entry' AND column_name = 'iptm_entry_id'",
+            &[],
+        )
+        .unwrap();
+    let nullable: String = rows.get(0).get(0);
+    assert_eq!(nullable, "NO");
+}
+
+#[test]
+fn creates_indexes() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query(
+            "SELECT tablename, indexname FROM pg_indexes WHERE schemaname = 'public' ORDER BY indexname",
+            &[],
+        )
+        .unwrap();
+    let indexes: Vec<(String, String)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+    let expected = vec![
+        ("mv_event", "enz_code_null_idx"),
+        ("mv_event", "event_name_idx"),
+        ("mv_entry", "gene_name_idx"),
+        ("mv_entry", "iptm_entry_code_idx"),
+        ("mv_event", "position_idx"),
+        ("mv_entry", "protein_name_idx"),
+        ("mv_event", "residue_idx"),
+        ("mv_entry", "role_as_enzyme_idx"),
+        ("mv_entry", "role_as_substrate_idx"),
+        ("sequence", "seq_id_idx"),
+        ("mv_event", "sub_code_idx"),
+        ("mv_event", "sub_form_code_idx"),
+        ("mv_entry", "taxon_code_idx"),
+        ("mv_entry", "uniprot_id_idx"),
+    ];
+    let expected: Vec<(String, String)> = expected
+        .into_iter()
+        .map(|(table, index)| (table.to_string(), index.to_string()))
+        .collect();
+    assert_eq!(indexes, expected);
+}
+
+#[test]
+fn reimport_replaces_previous_data() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_event"), fixture_rows(data.path(), "mv_event_export.csv"));
+}
+
+#[test]
+fn failed_copy_rolls_back_everything() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+
+    // A two character RESIDUE does not fit MV_EVENT.RESIDUE VARCHAR(1).
+    let events = fs::read_to_string(data.path().join("mv_event_export.csv")).unwrap();
+    let broken = events.replacen("Acetylation,ac,HPRD,N,K,120", "Acetylation,ac,HPRD,N,KR,120", 1);
+    assert_ne!(events, broken);
+    fs::write(data.path().join("mv_event_export.csv"), broken).unwrap();
+    let entries = fs::read_to_string(data.path().join("mv_entry_export.csv")).unwrap();
+    let first_record = entries.lines().nth(1).unwrap().to_string();
+    fs::write(
+        data.path().join("mv_entry_export.csv"),
+        format!("{}{}\n", entries, first_record.replacen("1,", "999,", 1)),
+    )
+    .unwrap();
+
+    let output = cluster.import(data.path());
+    assert!(!output.status.success(), "import of broken data succeeded");
+
+    // The earlier, successful import must still be in place: neither the
+    // drops nor the extra MV_ENTRY row from the failed run are visible.
+    let conn = cluster.connect("iptmnet");
+    let pristine = common::fixtures_path();
+    for (table, export) in TABLES.iter().zip(EXPORTS.iter()) {
+        assert_eq!(count(&conn, table), fixture_rows(&pristine, export), "row count of {}", table);
+    }
+    let rows = conn.query("SELECT count(*) FROM pg_indexes WHERE schemaname = 'public'", &[]).unwrap();
+    let indexes: i64 = rows.get(0).get(0);
+    assert_eq!(indexes, 14);
+}
+
+#[test]
+fn missing_export_fails_without_changes() {
+    let (cluster, data) = setup();
+    fs::remove_file(data.path().join("sequences.csv")).unwrap();
+
+    let output = cluster.import(data.path());
+    assert!(!output.status.success(), "import without sequences.csv succeeded");
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query("SELECT count(*) FROM information_schema.tables WHERE table_schema = 'public'", &[])
+        .unwrap();
+    let tables: i64 = rows.get(0).get(0);
+    assert_eq!(tables, 0);
+}
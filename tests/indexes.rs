@@ -0,0 +1,95 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use postgres::Connection;
+
+use common::{assert_success, setup, stderr};
+
+fn write_config(dir: &Path, content: &str) -> String {
+    let config = dir.join("importer.toml");
+    fs::write(&config, content).unwrap();
+    config.to_str().unwrap().to_string()
+}
+
+fn definition(conn: &Connection, index: &str) -> String {
+    let rows = conn
+        .query("SELECT indexdef FROM pg_indexes WHERE indexname = $1", &[&index])
+        .unwrap();
+    assert_eq!(rows.len(), 1, "{} does not exist", index);
+    rows.get(0).get(0)
+}
+
+#[test]
+fn enz_code_null_index_covers_every_event() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+
+    let conn = cluster.connect("iptmnet");
+    let definition = definition(&conn, "enz_code_null_idx");
+    assert!(definition.ends_with("USING btree (enz_code)"), "{}", definition);
+    let rows = conn
+        .query("SELECT count(*) FROM pg_indexes WHERE schemaname = 'public'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 14);
+}
+
+#[test]
+fn creates_expression_unique_partial_and_trigram_indexes() {
+    let (cluster, data) = setup();
+    let config = write_config(
+        data.path(),
+        "extensions = [\"pg_trgm\"]\n\n\
+         [tables.MV_ENTRY]\n\
+         indexes = [\n\
+         { name = \"gene_name_lower_idx\", columns = [\"lower(GENE_NAME)\"] },\n\
+         { name = \"gene_name_pattern_idx\", columns = [\"GENE_NAME\"], opclass = \"text_pattern_ops\" },\n\
+         { name = \"protein_name_trgm_idx\", columns = [\"PROTEIN_NAME\"], method = \"gin\", opclass = \"gin_trgm_ops\" },\n\
+         ]\n\n\
+         [tables.MV_EVENT]\n\
+         indexes = [{ name = \"event_site_idx\", columns = [\"IPTM_EVENT_ID\", \"RESIDUE\"], unique = true, where = \"POSITION IS NOT NULL\" }]\n",
+    );
+    assert_success(&cluster.import_with(data.path(), &["--config", &config]));
+
+    let conn = cluster.connect("iptmnet");
+    assert!(definition(&conn, "gene_name_lower_idx").contains("(lower((gene_name)::text))"));
+    assert!(definition(&conn, "gene_name_pattern_idx").contains("(gene_name text_pattern_ops)"));
+    assert!(definition(&conn, "protein_name_trgm_idx").contains("USING gin (protein_name gin_trgm_ops)"));
+    let site = definition(&conn, "event_site_idx");
+    assert!(site.starts_with("CREATE UNIQUE INDEX"), "{}", site);
+    assert!(site.contains("WHERE (\"position\" IS NOT NULL)"), "{}", site);
+
+    // The unique index is enforced, but only where it applies.
+    let duplicate = "INSERT INTO mv_event (iptm_event_id, residue, position) \
+                     SELECT iptm_event_id, residue, position FROM mv_event WHERE position IS NOT NULL LIMIT 1";
+    assert!(conn.execute(duplicate, &[]).is_err());
+    conn.execute("INSERT INTO mv_event (iptm_event_id, residue) VALUES (101, 'S'), (101, 'S')", &[]).unwrap();
+
+    // Configured indexes are dropped and created again like the built-in ones.
+    assert_success(&cluster.import_with(data.path(), &["--config", &config]));
+    for index in ["gene_name_lower_idx", "gene_name_pattern_idx", "protein_name_trgm_idx", "event_site_idx"].iter() {
+        definition(&conn, index);
+    }
+}
+
+#[test]
+fn rejects_invalid_index_definitions() {
+    let (cluster, data) = setup();
+    for (content, error) in [
+        ("[tables.MV_ENTRY]\nindexes = [{ name = \"x_idx\", columns = [\"NO_SUCH\"] }]\n", "unknown column NO_SUCH"),
+        ("[tables.MV_ENTRY]\nindexes = [{ name = \"x_idx\", columns = [\"GENE_NAME\"], method = \"rtree\" }]\n", "unknown method"),
+        ("[tables.MV_ENTRY]\nindexes = [{ name = \"gene_name_idx\", columns = [\"GENE_NAME\"] }]\n", "defined twice"),
+    ]
+    .iter()
+    {
+        let config = write_config(data.path(), content);
+        let output = cluster.import_with(data.path(), &["--config", &config]);
+        assert!(!output.status.success());
+        let log = stderr(&output);
+        assert!(log.contains(error), "{}", log);
+    }
+}
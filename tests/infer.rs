@@ -0,0 +1,150 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::{assert_success, count, fixture_dir, setup, stderr};
+
+const SITES: &str = "Site ID,sub_code,Residue,position,ptm type,score,added,note\n\
+                     1,P04637,S,15,phosphorylation,0.93,2018-04-27,\n\
+                     2,P04637,S,20,phosphorylation,1,2018-04-27,\"seen in \"\"vivo\"\"\"\n\
+                     3,P31749,T,308,phosphorylation,0.5,2019-01-02,\"\"\n\
+                     4,P31749,K,14,acetylation,,2019-01-02,x\n";
+
+fn infer(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .current_dir(dir)
+        .arg("infer")
+        .args(args)
+        .output()
+        .expect("could not run importer")
+}
+
+#[test]
+fn proposes_columns_and_indexes() {
+    let data = fixture_dir();
+    fs::write(data.path().join("curated_site_export.csv"), SITES).unwrap();
+
+    let output = infer(data.path(), &["./curated_site_export.csv"]);
+    assert_success(&output);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("# Inferred from 4 rows of ./curated_site_export.csv."), "{}", text);
+    assert!(text.contains("[tables.CURATED_SITE]\nfile = \"./curated_site_export.csv\"\n"), "{}", text);
+    assert!(text.contains("force_null = [\"NOTE\"]\n"), "{}", text);
+    let expected = [
+        "name = \"SITE_ID\"\ntype = \"BIGINT\"\nnot_null = true\n",
+        "name = \"SUB_CODE\"\ntype = \"VARCHAR(10)\"\nnot_null = true\n",
+        "name = \"RESIDUE\"\ntype = \"CHAR(1)\"\nnot_null = true\n",
+        "name = \"PTM_TYPE\"\ntype = \"VARCHAR(25)\"\nnot_null = true\n",
+        "name = \"SCORE\"\ntype = \"DOUBLE PRECISION\"\n\n",
+        "name = \"ADDED\"\ntype = \"DATE\"\nnot_null = true\n",
+        "name = \"NOTE\"\ntype = \"VARCHAR(25)\"\n",
+        "name = \"curated_site_sub_code_idx\"\ncolumns = [\"SUB_CODE\"]\n",
+    ];
+    for expected in expected.iter() {
+        assert!(text.contains(expected), "{} not in {}", expected, text);
+    }
+    assert!(!text.contains("curated_site_note_idx"), "{}", text);
+
+    let output = infer(data.path(), &["./curated_site_export.csv", "--sample", "2", "--table", "ptm_site"]);
+    assert_success(&output);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("# Inferred from the first 2 rows of ./curated_site_export.csv."), "{}", text);
+    assert!(text.contains("[tables.PTM_SITE]"), "{}", text);
+    assert!(text.contains("name = \"ptm_site_sub_code_idx\""), "{}", text);
+}
+
+#[test]
+fn refuses_to_redefine_built_in_tables() {
+    let data = fixture_dir();
+    let output = infer(data.path(), &["./mv_event_export.csv"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("MV_EVENT is built in"));
+}
+
+#[test]
+fn imports_inferred_tables() {
+    let (cluster, data) = setup();
+    fs::write(data.path().join("curated_site_export.csv"), SITES).unwrap();
+    let output = infer(data.path(), &["./curated_site_export.csv"]);
+    assert_success(&output);
+    let config = data.path().join("importer.toml");
+    fs::write(&config, output.stdout).unwrap();
+
+    assert_success(&cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "curated_site"), 4);
+    assert_eq!(count(&conn, "mv_event"), 4);
+    let rows = conn
+        .query(
+            "SELECT data_type, is_nullable FROM information_schema.columns \
+             WHERE table_name = 'curated_site' AND column_name = 'added'",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, String>(0), "date");
+    assert_eq!(rows.get(0).get::<_, String>(1), "NO");
+    let rows = conn
+        .query("SELECT count(*) FROM pg_indexes WHERE tablename = 'curated_site' AND indexname = 'curated_site_sub_code_idx'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 1);
+    let rows = conn.query("SELECT count(*) FROM curated_site WHERE note IS NULL", &[]).unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 2);
+}
+
+#[test]
+fn checks_defined_columns_in_tolerant_mode() {
+    let (cluster, data) = setup();
+    let sites = format!("{}5,P31749,S,473,phosphorylation,high,2019-01-02,\n6,P31749,S,474,phosphorylation,1,2019-02-30,\n", SITES);
+    fs::write(data.path().join("curated_site_export.csv"), sites).unwrap();
+    let config = data.path().join("importer.toml");
+    fs::write(
+        &config,
+        "[tables.CURATED_SITE]\nfile = \"./curated_site_export.csv\"\ncolumns = [\n\
+         { name = \"SITE_ID\", type = \"BIGINT\", not_null = true },\n\
+         { name = \"SUB_CODE\", type = \"VARCHAR(25)\" },\n\
+         { name = \"RESIDUE\", type = \"CHAR(1)\" },\n\
+         { name = \"POSITION\", type = \"BIGINT\" },\n\
+         { name = \"PTM_TYPE\", type = \"VARCHAR(50)\" },\n\
+         { name = \"SCORE\", type = \"DOUBLE PRECISION\" },\n\
+         { name = \"ADDED\", type = \"DATE\" },\n\
+         { name = \"NOTE\", type = \"TEXT\" },\n]\n",
+    )
+    .unwrap();
+
+    assert_success(&cluster.import_with(data.path(), &["--config", config.to_str().unwrap(), "--tolerant"]));
+
+    assert_eq!(count(&cluster.connect("iptmnet"), "curated_site"), 4);
+    let rejects = fs::read_to_string(data.path().join("curated_site.rejects.csv")).unwrap();
+    assert!(rejects.contains("6,\"SCORE is not a valid DOUBLE PRECISION: \"\"high\"\"\""), "{}", rejects);
+    assert!(rejects.contains("7,\"ADDED is not a valid DATE: \"\"2019-02-30\"\"\""), "{}", rejects);
+}
+
+#[test]
+fn rejects_invalid_table_definitions() {
+    let (cluster, data) = setup();
+    let config = data.path().join("importer.toml");
+    let cases = [
+        (
+            "[tables.MV_EVENT]\ncolumns = [{ name = \"ID\", type = \"BIGINT\" }]\n",
+            "columns can only be given for tables that are not built in",
+        ),
+        ("[tables.CURATED_SITE]\ncolumns = [{ name = \"ID\", type = \"INT4\" }]\n", "column ID has an unknown type \"INT4\""),
+        ("[tables.CURATED_SITE]\ncolumns = [{ name = \"ID; DROP\", type = \"TEXT\" }]\n", "\"ID; DROP\" is not a valid column name"),
+        (
+            "[tables.CURATED_SITE]\ncolumns = [{ name = \"ID\", type = \"TEXT\" }]\nindexes = [{ name = \"site_idx\", columns = [\"CODE\"] }]\n",
+            "tables.CURATED_SITE: unknown column CODE",
+        ),
+    ];
+    for &(content, message) in cases.iter() {
+        fs::write(&config, content).unwrap();
+        let output = cluster.import_with(data.path(), &["--config", config.to_str().unwrap()]);
+        assert!(!output.status.success());
+        let log = stderr(&output);
+        assert!(log.contains(message), "{} not in {}", message, log);
+    }
+}
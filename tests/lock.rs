@@ -0,0 +1,71 @@
+extern crate tempfile;
+
+mod common;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{assert_success, count, setup, stderr};
+
+/// The key the importer locks for the database and schema of the connection.
+const LOCK: &str = "pg_advisory_lock(1230001229, hashtext(current_database() || '.' || current_schema()))";
+
+#[test]
+fn second_run_fails_and_names_the_holder() {
+    let (cluster, data) = setup();
+    let holder = cluster.connect("iptmnet");
+    holder.execute("SET application_name = 'nightly import'", &[]).unwrap();
+    holder.execute(&format!("SELECT {}", LOCK), &[]).unwrap();
+    let pid: i32 = holder.query("SELECT pg_backend_pid()", &[]).unwrap().get(0).get(0);
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    let expected = format!("another run holds the import lock on iptmnet.public: pid {} (postgres", pid);
+    assert!(log.contains(&expected), "{}", log);
+    assert!(log.contains("nightly import) connected since 20"), "{}", log);
+
+    assert_eq!(report["error_class"], "lock");
+    assert_eq!(report["steps"].as_array().unwrap().len(), 1);
+    // Nothing was touched.
+    let tables = holder
+        .query("SELECT count(*) FROM pg_tables WHERE tablename = 'mv_event'", &[])
+        .unwrap();
+    assert_eq!(tables.get(0).get::<_, i64>(0), 0);
+}
+
+#[test]
+fn waits_for_the_lock_with_a_timeout() {
+    let (cluster, data) = setup();
+    let holder = cluster.connect("iptmnet");
+    holder.execute(&format!("SELECT {}", LOCK), &[]).unwrap();
+
+    let started = Instant::now();
+    let output = cluster.import_with(data.path(), &["--lock-wait", "1"]);
+    assert!(!output.status.success());
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    let log = stderr(&output);
+    assert!(log.contains("gave up after waiting 1s"), "{}", log);
+
+    let release = thread::spawn(move || {
+        thread::sleep(Duration::from_secs(1));
+        drop(holder);
+    });
+    assert_success(&cluster.import_with(data.path(), &["--lock-wait", "30"]));
+    release.join().unwrap();
+    assert_eq!(count(&cluster.connect("iptmnet"), "mv_event"), 4);
+}
+
+#[test]
+fn lock_is_released_after_commit() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query(
+            "SELECT pg_try_advisory_lock(1230001229, hashtext(current_database() || '.' || current_schema()))",
+            &[],
+        )
+        .unwrap();
+    assert!(rows.get(0).get::<_, bool>(0));
+}
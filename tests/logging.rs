@@ -0,0 +1,86 @@
+extern crate serde_json;
+extern crate tempfile;
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::{assert_success, setup, stderr};
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// Runs the importer against a port nothing listens on.
+fn run_unreachable(args: &[&str], rust_log: Option<&str>) -> Output {
+    let dir = TempDir::new().unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"));
+    command
+        .current_dir(dir.path())
+        .args(["--host", "127.0.0.1", "--port", "1"])
+        .args(args)
+        .env_remove("RUST_LOG");
+    if let Some(filter) = rust_log {
+        command.env("RUST_LOG", filter);
+    }
+    command.output().unwrap()
+}
+
+fn json_lines(output: &Output) -> Vec<Value> {
+    stderr(output)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log line is not JSON"))
+        .collect()
+}
+
+#[test]
+fn verbosity_flags_select_level() {
+    let normal = String::from_utf8(run_unreachable(&[], None).stderr).unwrap();
+    assert!(normal.contains("Connecting to database"));
+    assert!(normal.contains("ERROR"));
+    assert!(!normal.contains("iptmnet:"), "password leaked into the log");
+
+    let quiet = String::from_utf8(run_unreachable(&["-q"], None).stderr).unwrap();
+    assert!(!quiet.contains("Connecting to database"));
+    assert!(quiet.contains("ERROR"));
+
+    let silent = String::from_utf8(run_unreachable(&["-qq"], None).stderr).unwrap();
+    assert!(silent.contains("ERROR"));
+}
+
+#[test]
+fn rust_log_takes_precedence_over_flags() {
+    let output = run_unreachable(&["-vv"], Some("off"));
+    assert!(output.stderr.is_empty());
+    assert!(output.stdout.is_empty());
+
+    let output = run_unreachable(&["-qq"], Some("iptmnet_data_importer=info"));
+    assert!(stderr(&output).contains("Connecting to database"));
+}
+
+#[test]
+fn json_format_writes_one_object_per_line() {
+    let lines = json_lines(&run_unreachable(&["--log-format", "json"], None));
+    assert!(!lines.is_empty());
+    assert!(lines.iter().all(|line| line["timestamp"].is_string() && line["message"].is_string()));
+
+    let error = lines.iter().find(|line| line["level"] == "ERROR").unwrap();
+    assert_eq!(error["error_class"], "connection");
+}
+
+#[test]
+fn json_format_carries_step_fields() {
+    let (cluster, data) = setup();
+
+    let output = cluster.import_with(data.path(), &["--log-format", "json"]);
+    assert_success(&output);
+    assert!(output.stdout.is_empty(), "output bypassed the logger");
+
+    let lines = json_lines(&output);
+    let populate = lines
+        .iter()
+        .find(|line| line["step"] == "populate" && line["table"] == "MV_EVENT" && line["rows"].is_u64())
+        .expect("no populate record for MV_EVENT");
+    assert_eq!(populate["rows"], 4);
+    assert!(populate["bytes"].as_u64().unwrap() > 0);
+    assert!(lines.iter().any(|line| line["step"] == "index" && line["target"] == "uniprot_id_idx"));
+    assert!(lines.iter().any(|line| line["step"] == "commit"));
+}
@@ -0,0 +1,104 @@
+extern crate postgres;
+extern crate serde_json;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use postgres::Connection;
+use serde_json::Value;
+
+use common::{assert_success, setup, stderr, steps, TABLES};
+
+fn write_config(dir: &Path, content: &str) -> String {
+    let config = dir.join("importer.toml");
+    fs::write(&config, content).unwrap();
+    config.to_str().unwrap().to_string()
+}
+
+/// Statistics are flushed to the cumulative statistics system shortly after
+/// a session ends, so they are polled for a while.
+fn stat(conn: &Connection, table: &str, column: &str) -> bool {
+    for _ in 0..50 {
+        let rows = conn
+            .query(
+                &format!("SELECT {} IS NOT NULL FROM pg_stat_user_tables WHERE relname = $1", column),
+                &[&table],
+            )
+            .unwrap();
+        if rows.get(0).get::<_, bool>(0) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn targets(report: &Value, kind: &str) -> Vec<String> {
+    steps(report, kind)
+        .iter()
+        .map(|step| step["target"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn analyzes_every_table_after_commit() {
+    let (cluster, data) = setup();
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    for table in TABLES.iter() {
+        assert!(stat(&conn, table, "last_analyze"), "{} was not analyzed", table);
+    }
+    assert_eq!(targets(&report, "analyze"), ["MV_ENTRY", "MV_EVENT", "MV_EFIP", "MV_PROTEO", "SEQUENCE"]);
+    assert!(targets(&report, "vacuum").is_empty());
+}
+
+#[test]
+fn vacuums_and_clusters_configured_tables() {
+    let (cluster, data) = setup();
+    let config = write_config(
+        data.path(),
+        "[tables.MV_EVENT]\nvacuum = \"freeze\"\ncluster = \"sub_code_idx\"\n\n\
+         [tables.MV_EFIP]\nanalyze = false\n\n\
+         [tables.SEQUENCE]\nvacuum = \"plain\"\nanalyze = false\n",
+    );
+    let (output, report) = cluster.import_reported(data.path(), &["--config", &config]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query("SELECT indisclustered FROM pg_index WHERE indexrelid = 'sub_code_idx'::regclass", &[])
+        .unwrap();
+    assert!(rows.get(0).get::<_, bool>(0));
+    assert!(stat(&conn, "mv_event", "last_vacuum"));
+    assert!(stat(&conn, "mv_event", "last_analyze"));
+    assert!(stat(&conn, "sequence", "last_vacuum"));
+
+    assert_eq!(targets(&report, "cluster"), ["MV_EVENT"]);
+    assert_eq!(targets(&report, "vacuum"), ["MV_EVENT", "SEQUENCE"]);
+    assert_eq!(targets(&report, "analyze"), ["MV_ENTRY", "MV_PROTEO"]);
+    let rows = conn
+        .query("SELECT last_analyze IS NULL FROM pg_stat_user_tables WHERE relname = 'mv_efip'", &[])
+        .unwrap();
+    assert!(rows.get(0).get::<_, bool>(0));
+}
+
+#[test]
+fn clustering_on_a_missing_index_fails_the_run() {
+    let (cluster, data) = setup();
+    let config = write_config(data.path(), "[tables.MV_EVENT]\ncluster = \"no_such_idx\"\n");
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("no_such_idx"));
+
+    let config = write_config(data.path(), "[tables.MV_EVENT]\nvacuum = \"full\"\n");
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("unknown variant `full`"));
+}
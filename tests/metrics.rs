@@ -0,0 +1,143 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Command;
+use std::thread;
+
+use common::{assert_success, setup, stderr};
+use tempfile::TempDir;
+
+fn metric(text: &str, series: &str) -> Option<f64> {
+    text.lines()
+        .find(|line| line.starts_with(series) && line[series.len()..].starts_with(' '))
+        .map(|line| line[series.len()..].trim().parse().unwrap())
+}
+
+/// Reads one request of the importer and returns its request line and body.
+fn read_request(stream: &TcpStream) -> (String, String) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if header.to_lowercase().starts_with("content-length:") {
+            length = header[15..].trim().parse().unwrap();
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    (request_line, String::from_utf8(body).unwrap())
+}
+
+#[test]
+fn textfile_keeps_last_success_across_failures() {
+    let (cluster, data) = setup();
+    let prom = data.path().join("iptmnet.prom");
+    let args = ["--metrics-file", prom.to_str().unwrap()];
+
+    assert_success(&cluster.import_with(data.path(), &args));
+    let first = fs::read_to_string(&prom).unwrap();
+    let last_success = metric(&first, "iptmnet_import_last_success_timestamp_seconds").unwrap();
+    assert_eq!(metric(&first, "iptmnet_import_last_run_success"), Some(1.0));
+    assert_eq!(metric(&first, "iptmnet_import_rows{table=\"mv_event\"}"), Some(4.0));
+    assert!(metric(&first, "iptmnet_import_bytes_processed{table=\"sequence\"}").unwrap() > 0.0);
+    assert!(!first.contains("iptmnet_import_failures_total"));
+
+    let efip = fs::read(data.path().join("mv_efip_export.csv")).unwrap();
+    fs::write(data.path().join("mv_efip_export.csv"), "PPI_EVENT_ID\nnot-a-number\n").unwrap();
+    assert!(!cluster.import_with(data.path(), &args).status.success());
+    fs::write(data.path().join("mv_efip_export.csv"), efip).unwrap();
+    fs::remove_file(data.path().join("sequences.csv")).unwrap();
+    assert!(!cluster.import_with(data.path(), &args).status.success());
+    assert!(!cluster.import_with(data.path(), &args).status.success());
+
+    let last = fs::read_to_string(&prom).unwrap();
+    assert_eq!(metric(&last, "iptmnet_import_last_run_success"), Some(0.0));
+    assert_eq!(metric(&last, "iptmnet_import_last_success_timestamp_seconds"), Some(last_success));
+    assert_eq!(metric(&last, "iptmnet_import_rows{table=\"mv_event\"}"), Some(4.0));
+    assert_eq!(metric(&last, "iptmnet_import_failures_total{class=\"data\"}"), Some(1.0));
+    assert_eq!(metric(&last, "iptmnet_import_failures_total{class=\"input\"}"), Some(2.0));
+}
+
+#[test]
+fn pushgateway_receives_updated_counters() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    // A fake Pushgateway that serves previous metrics and records the push.
+    let server = thread::spawn(move || {
+        let previous = "# TYPE iptmnet_import_failures_total counter\n\
+            iptmnet_import_failures_total{class=\"connection\",instance=\"\",job=\"iptmnet_importer\"} 2\n\
+            iptmnet_import_failures_total{class=\"connection\",instance=\"\",job=\"other\"} 40\n\
+            iptmnet_import_last_success_timestamp_seconds{instance=\"\",job=\"iptmnet_importer\"} 1.5e+09\n\
+            push_time_seconds{instance=\"\",job=\"iptmnet_importer\"} 1.6e+09\n";
+        let mut pushed = None;
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (request_line, body) = read_request(&stream);
+
+            if request_line.starts_with("GET /metrics ") {
+                write!(stream, "HTTP/1.0 200 OK\r\n\r\n{}", previous).unwrap();
+            } else {
+                pushed = Some((request_line, body));
+                write!(stream, "HTTP/1.0 200 OK\r\n\r\n").unwrap();
+            }
+        }
+        pushed.unwrap()
+    });
+
+    let dir = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .current_dir(dir.path())
+        .args(["--host", "127.0.0.1", "--port", "1", "--pushgateway", &url])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let (request_line, body) = server.join().unwrap();
+    assert!(request_line.starts_with("PUT /metrics/job/iptmnet_importer "), "{}", request_line);
+    assert_eq!(metric(&body, "iptmnet_import_failures_total{class=\"connection\"}"), Some(3.0));
+    assert_eq!(metric(&body, "iptmnet_import_last_success_timestamp_seconds"), Some(1.5e9));
+    assert_eq!(metric(&body, "iptmnet_import_last_run_success"), Some(0.0));
+    assert!(!body.contains("push_time_seconds"));
+}
+
+#[test]
+fn pushgateway_errors_are_reported() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let responses = ["HTTP/1.0 503 Service Unavailable\r\n\r\nbusy\n", "SSH-2.0-OpenSSH_9.6\r\n"];
+
+    // Answers each request with the next canned response.
+    let server = thread::spawn(move || {
+        for response in responses.iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&stream);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    let dir = TempDir::new().unwrap();
+    let push = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+            .current_dir(dir.path())
+            .args(["--host", "127.0.0.1", "--port", "1", "--pushgateway", &url])
+            .output()
+            .unwrap();
+        stderr(&output)
+    };
+    let log = push();
+    assert!(log.contains("Could not export metrics - Pushgateway GET failed with status 503: busy"), "{}", log);
+    let log = push();
+    assert!(log.contains("Could not export metrics - malformed Pushgateway response: \"SSH-2.0-OpenSSH_9.6\""), "{}", log);
+    server.join().unwrap();
+}
@@ -0,0 +1,167 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use postgres::Connection;
+
+use common::{assert_success, count, setup, stderr, step};
+
+const OBO: &str = "format-version: 1.2
+ontology: mod
+
+[Term]
+id: MOD:00046
+name: O-phospho-L-serine
+synonym: \"Phospho\" RELATED PSI-MS-label []
+xref: Origin: \"S\"
+xref: TermSpec: \"none\"
+
+[Term]
+id: MOD:00047
+name: O-phospho-L-threonine
+synonym: \"Phospho\" RELATED PSI-MS-label []
+xref: Origin: \"T\"
+
+[Term]
+id: MOD:00048
+name: O4'-phospho-L-tyrosine
+synonym: \"Phospho\" RELATED PSI-MS-label []
+xref: Origin: \"Y\"
+
+[Term]
+id: MOD:00064
+name: N6-acetyl-L-lysine
+synonym: \"Acetyl\" RELATED PSI-MS-label []
+xref: Origin: \"K\"
+
+[Term]
+id: MOD:01000
+name: N2-acetyl-L-lysine
+synonym: \"Acetyl\" RELATED PSI-MS-label []
+xref: Origin: \"K\"
+xref: TermSpec: \"N-term\"
+
+[Term]
+id: MOD:01001
+name: obsolete O-phospho-L-serine
+synonym: \"Phospho\" RELATED PSI-MS-label []
+xref: Origin: \"S\"
+is_obsolete: true
+
+[Term]
+id: MOD:01002
+name: phosphorylated residue
+synonym: \"Phospho\" RELATED PSI-MS-label []
+xref: Origin: \"S, T, Y\"
+
+[Typedef]
+id: part_of
+name: part_of
+";
+
+fn write(dir: &Path, name: &str, content: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn links(conn: &Connection) -> Vec<(String, String, String, Option<String>)> {
+    conn.query(
+        "SELECT EVENT_NAME, RESIDUE, MOD_ID, MOD_NAME FROM EVENT_TYPE_ONTOLOGY ORDER BY 1, 2, 3",
+        &[],
+    )
+    .unwrap()
+    .iter()
+    .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+    .collect()
+}
+
+#[test]
+fn maps_from_a_mapping_file_and_reports_unmapped_combinations() {
+    let (cluster, data) = setup();
+    let mapping = write(
+        data.path(),
+        "event_types.csv",
+        "MOD_ID,EVENT_NAME,RESIDUE,MOD_NAME\n\
+         MOD:00046,phosphorylation,s,O-phospho-L-serine\n\
+         MOD:00047,Phosphorylation,T,\"O-phospho-L-threonine, from the mapping\"\n\
+         MOD:00064,Sumoylation,K,\n",
+    );
+    let (output, report) = cluster.import_reported(data.path(), &["--ontology", &mapping]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(
+        log.contains(
+            "EVENT_TYPE_ONTOLOGY: 2 EVENT_NAME and RESIDUE combinations without a term, \
+             e.g. Acetylation K (1 events); Phosphorylation Y (1 events)"
+        ),
+        "{}",
+        log
+    );
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        links(&conn),
+        [
+            ("Phosphorylation".to_string(), "S".to_string(), "MOD:00046".to_string(), Some("O-phospho-L-serine".to_string())),
+            (
+                "Phosphorylation".to_string(),
+                "T".to_string(),
+                "MOD:00047".to_string(),
+                Some("O-phospho-L-threonine, from the mapping".to_string())
+            ),
+        ]
+    );
+    let rows = conn
+        .query("SELECT indexname FROM pg_indexes WHERE tablename = 'event_type_ontology' ORDER BY 1", &[])
+        .unwrap();
+    let indexes: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(indexes, ["event_type_ontology_event_idx", "event_type_ontology_mod_idx"]);
+    let step = step(&report, "ontology", None);
+    assert_eq!(step["rows"], 2);
+    assert_eq!(step["rejected"], 2);
+
+    // Without --ontology the table from the earlier run goes away.
+    assert_success(&cluster.import(data.path()));
+    let rows = conn
+        .query("SELECT count(*) FROM information_schema.tables WHERE table_name = 'event_type_ontology'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+}
+
+#[test]
+fn maps_single_residue_terms_of_an_obo_file() {
+    let (cluster, data) = setup();
+    let obo = write(data.path(), "PSI-MOD.obo", OBO);
+    let output = cluster.import_with(data.path(), &["--ontology", &obo]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(!log.contains("without a term"), "{}", log);
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "event_type_ontology"), 4);
+    let ids: Vec<(String, String, String)> = links(&conn).into_iter().map(|(name, residue, id, _)| (name, residue, id)).collect();
+    assert_eq!(
+        ids,
+        [
+            ("Acetylation".to_string(), "K".to_string(), "MOD:00064".to_string()),
+            ("Phosphorylation".to_string(), "S".to_string(), "MOD:00046".to_string()),
+            ("Phosphorylation".to_string(), "T".to_string(), "MOD:00047".to_string()),
+            ("Phosphorylation".to_string(), "Y".to_string(), "MOD:00048".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn fails_on_a_malformed_mapping_file() {
+    let (cluster, data) = setup();
+    let mapping = write(data.path(), "event_types.tsv", "EVENT_NAME\tRESIDUE\tMOD_ID\nPhosphorylation\tSer\tMOD:00046\n");
+    let output = cluster.import_with(data.path(), &["--ontology", &mapping]);
+    assert!(!output.status.success(), "import with a malformed mapping file succeeded");
+    let log = stderr(&output);
+    assert!(log.contains("line 2: RESIDUE must be a single letter, got \"SER\""), "{}", log);
+}
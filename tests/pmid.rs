@@ -0,0 +1,86 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use postgres::Connection;
+
+use common::{append, assert_success, setup, stderr, step, steps, variant};
+
+fn events(conn: &Connection, pmid: i64) -> Vec<(i64, String)> {
+    conn.query("SELECT EVENT_ID, SOURCE FROM EVENT_PMID WHERE PMID = $1 ORDER BY 1, 2", &[&pmid])
+        .unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect()
+}
+
+#[test]
+fn links_events_to_their_pmids() {
+    let (cluster, data) = setup();
+    let (output, report) = cluster.import_reported(data.path(), &["--pmids"]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        events(&conn, 10570149),
+        [(101, "MV_EFIP.PTM_PMIDS".to_string()), (101, "MV_EVENT.PMIDS".to_string())]
+    );
+    assert_eq!(events(&conn, 11250899), [(101, "MV_EVENT.PMIDS".to_string())]);
+    assert_eq!(events(&conn, 15053880), [(201, "MV_EFIP.PPI_PMIDS".to_string())]);
+    // A PMID is not found inside a longer one.
+    assert!(events(&conn, 1057014).is_empty());
+
+    let rows = conn
+        .query(
+            "SELECT indexdef FROM pg_indexes WHERE tablename = 'event_pmid' ORDER BY indexname",
+            &[],
+        )
+        .unwrap();
+    let indexes: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert!(indexes[0].ends_with("(event_id, pmid)"), "{:?}", indexes);
+    assert!(indexes[1].ends_with("(pmid, event_id)"), "{:?}", indexes);
+
+    // Proteoforms have no key to link to, their PMIDs are only checked.
+    let proteo = step(&report, "link", Some("MV_PROTEO.PMIDS"));
+    assert_eq!(proteo["rows"], 0);
+    assert_eq!(proteo["rejected"], 0);
+}
+
+#[test]
+fn links_are_only_built_with_pmids() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import_with(data.path(), &["--pmids"]));
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn.query("SELECT to_regclass('event_pmid') IS NULL", &[]).unwrap();
+    assert!(rows.get(0).get::<_, bool>(0), "EVENT_PMID left behind without --pmids");
+    assert!(steps(&report, "link").is_empty());
+}
+
+#[test]
+fn reports_malformed_pmids() {
+    let (cluster, data) = setup();
+    let row = variant(data.path(), "mv_event_export.csv", "101,", "105,");
+    let row = row.replacen("\"10570149,11250899\"", "\"PMID:123; PMC4567 | 42,n/a,  \"", 1);
+    append(data.path(), "mv_event_export.csv", &[row]);
+
+    let (output, report) = cluster.import_reported(data.path(), &["--pmids"]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(
+        log.contains("MV_EVENT.PMIDS: 2 malformed PMIDs left out, e.g. \"PMC4567\" (IPTM_EVENT_ID 105), \"n/a\" (IPTM_EVENT_ID 105)"),
+        "{}",
+        log
+    );
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(events(&conn, 123), [(105, "MV_EVENT.PMIDS".to_string())]);
+    assert_eq!(events(&conn, 42), [(105, "MV_EVENT.PMIDS".to_string())]);
+
+    let step = step(&report, "link", Some("MV_EVENT.PMIDS"));
+    assert_eq!(step["rows"], 6);
+    assert_eq!(step["rejected"], 2);
+}
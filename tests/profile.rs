@@ -0,0 +1,127 @@
+extern crate serde_json;
+extern crate tempfile;
+
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::{append, assert_success, fixture_dir, stderr, variant};
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn profile(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .current_dir(dir)
+        .arg("profile")
+        .args(args)
+        .output()
+        .expect("could not run importer")
+}
+
+fn column<'a>(profiles: &'a Value, table: &str, name: &str) -> &'a Value {
+    let table = profiles
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|profile| profile["table"] == table)
+        .unwrap();
+    table["columns"].as_array().unwrap().iter().find(|column| column["name"] == name).unwrap()
+}
+
+#[test]
+fn reports_column_statistics() {
+    let data = fixture_dir();
+    let output = profile(data.path(), &["MV_EVENT", "--top", "2"]);
+    assert_success(&output);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("MV_EVENT: ./mv_event_export.csv, 4 rows\n"), "{}", text);
+    assert!(
+        text.contains(
+            "  IPTM_EVENT_ID BIGINT\n    null 0 (0.0%), empty 0 (0.0%)\n    length 3 to 3, mean 3.0\n    \
+             distinct 4\n    numeric 4 of 4, range 101 to 104\n"
+        ),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("  ENZ_CODE VARCHAR(25)\n    null 2 (50.0%), empty 0 (0.0%)\n"),
+        "{}",
+        text
+    );
+    assert!(text.contains("    top \"uniprot\" (4)\n"), "{}", text);
+    assert!(!text.contains("MV_ENTRY"), "{}", text);
+    // A VARCHAR(1) filled by every value is not worth a warning.
+    assert!(!text.contains("!"), "{}", text);
+}
+
+#[test]
+fn flags_values_close_to_or_over_the_column_size() {
+    let data = fixture_dir();
+    let near = variant(data.path(), "mv_entry_export.csv", ",TP53,Antigen", &format!(",{},Antigen", "G".repeat(46)));
+    let over = variant(data.path(), "mv_entry_export.csv", "Homo sapiens,Human,", &format!("Homo sapiens,{},", "H".repeat(101)));
+    append(data.path(), "mv_entry_export.csv", &[near, over]);
+
+    let output = profile(data.path(), &["mv_entry", "--format", "json"]);
+    assert_success(&output);
+    let profiles: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(profiles[0]["rows"], 6);
+
+    let gene = column(&profiles, "MV_ENTRY", "GENE_NAME");
+    assert_eq!(gene["max_length"], 46);
+    assert_eq!(gene["flag"], "near");
+    let common = column(&profiles, "MV_ENTRY", "TAXON_COMMON");
+    assert_eq!(common["max_length"], 101);
+    assert_eq!(common["flag"], "over");
+    assert_eq!(column(&profiles, "MV_ENTRY", "PROTEIN_NAME")["flag"], Value::Null);
+
+    let log = stderr(&output);
+    assert!(log.contains("MV_ENTRY.GENE_NAME: longest value has 46 characters, close to VARCHAR(50)"), "{}", log);
+    assert!(log.contains("MV_ENTRY.TAXON_COMMON: longest value has 101 characters, more than VARCHAR(100)"), "{}", log);
+
+    // A smaller margin leaves the 46 characters of GENE_NAME alone.
+    let output = profile(data.path(), &["MV_ENTRY", "--format", "json", "--margin", "5"]);
+    let profiles: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(column(&profiles, "MV_ENTRY", "GENE_NAME")["flag"], Value::Null);
+    assert_eq!(column(&profiles, "MV_ENTRY", "TAXON_COMMON")["flag"], "over");
+}
+
+#[test]
+fn counts_malformed_records_separately() {
+    let data = fixture_dir();
+    append(data.path(), "mv_efip_export.csv", &["1,2,3".to_string()]);
+    let output = profile(data.path(), &["MV_EFIP"]);
+    assert_success(&output);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("MV_EFIP: ./mv_efip_export.csv, 3 rows, 1 malformed and left out\n"), "{}", text);
+}
+
+#[test]
+fn estimates_distinct_values_of_large_exports() {
+    let data = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .args(["generate-fixtures", "--entries", "5000", "--events", "10", "--efip", "1", "--proteo", "1"])
+        .arg("--out-dir")
+        .arg(data.path())
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let output = profile(data.path(), &["MV_ENTRY", "--format", "json"]);
+    assert_success(&output);
+    let profiles: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let id = column(&profiles, "MV_ENTRY", "IPTM_ENTRY_ID");
+    assert_eq!(id["distinct_exact"], false);
+    let distinct = id["distinct"].as_u64().unwrap();
+    assert!(distinct > 4500 && distinct < 5500, "estimated {} distinct values", distinct);
+    assert_eq!(id["min_number"], 1.0);
+    assert_eq!(id["max_number"], 5000.0);
+}
+
+#[test]
+fn rejects_unknown_tables() {
+    let data = fixture_dir();
+    let output = profile(data.path(), &["MV_EVENTS"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown table MV_EVENTS"));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{append, assert_success, count, setup, stderr, step, variant};
+
+/// Adds one good row spanning two lines and one bad row to MV_EVENT, and
+/// one bad row to MV_ENTRY. Returns the lines of the two bad rows.
+fn add_bad_rows(dir: &Path) -> (usize, usize) {
+    let multiline = variant(dir, "mv_event_export.csv", "101,", "105,").replacen(",,,\"", ",,\"two\nlines\",\"", 1);
+    let residue = variant(dir, "mv_event_export.csv", "101,", "106,").replacen(",S,15,", ",ST,15,", 1);
+    let event_line = append(dir, "mv_event_export.csv", &[multiline, residue]) + 2;
+
+    let name = "x".repeat(300);
+    let entry = variant(dir, "mv_entry_export.csv", "Cellular tumor antigen p53", &name).replacen("1,", "5,", 1);
+    let entry_line = append(dir, "mv_entry_export.csv", &[entry]);
+    (event_line, entry_line)
+}
+
+#[test]
+fn tolerant_mode_sets_bad_rows_aside() {
+    let (cluster, data) = setup();
+    let (event_line, entry_line) = add_bad_rows(data.path());
+
+    let output = cluster.import(data.path());
+    assert!(!output.status.success(), "bad rows must fail the import without --tolerant");
+
+    let (output, report) = cluster.import_reported(data.path(), &["--tolerant"]);
+    assert_success(&output);
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_event"), 5);
+    assert_eq!(count(&conn, "mv_entry"), 4);
+    let rows = conn.query("SELECT note FROM mv_event WHERE iptm_event_id = 105", &[]).unwrap();
+    let note: String = rows.get(0).get(0);
+    assert_eq!(note, "two\nlines");
+
+    let rejects = fs::read_to_string(data.path().join("mv_event.rejects.csv")).unwrap();
+    let lines: Vec<&str> = rejects.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", rejects);
+    assert_eq!(lines[0], "LINE,REASON,RECORD");
+    assert!(lines[1].starts_with(&format!("{},\"RESIDUE is 2 characters long", event_line)), "{}", lines[1]);
+    assert!(lines[1].contains("106,"));
+
+    let rejects = fs::read_to_string(data.path().join("mv_entry.rejects.csv")).unwrap();
+    let reject = rejects.lines().nth(1).unwrap();
+    assert!(reject.starts_with(&format!("{},\"PROTEIN_NAME is 300 characters long, longer than VARCHAR(200)", entry_line)));
+
+    let rejects = fs::read_to_string(data.path().join("sequence.rejects.csv")).unwrap();
+    assert_eq!(rejects, "LINE,REASON,RECORD\n");
+
+    let populate = step(&report, "populate", Some("MV_EVENT"));
+    assert_eq!(populate["rows"], 5);
+    assert_eq!(populate["rejected"], 1);
+}
+
+#[test]
+fn reject_limit_decides_whether_to_commit() {
+    let (cluster, data) = setup();
+    add_bad_rows(data.path());
+
+    let output = cluster.import_with(data.path(), &["--tolerant", "--max-rejects", "0"]);
+    assert!(!output.status.success());
+    let log = stderr(&output);
+    assert!(log.contains("MV_ENTRY: 1 of 5 rows rejected"), "{}", log);
+    let conn = cluster.connect("iptmnet");
+    let rows = conn.query("SELECT to_regclass('mv_entry') IS NULL", &[]).unwrap();
+    assert!(rows.get(0).get::<_, bool>(0), "a failed run must not commit");
+
+    // One of five MV_ENTRY rows is 20%, one of six MV_EVENT rows 16.7%.
+    let output = cluster.import_with(data.path(), &["--tolerant", "--max-rejects", "15%"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("MV_ENTRY: 1 of 5 rows rejected"));
+
+    assert_success(&cluster.import_with(data.path(), &["--tolerant", "--max-rejects", "20%"]));
+    assert_success(&cluster.import_with(data.path(), &["--tolerant", "--max-rejects", "1"]));
+    assert_eq!(count(&conn, "mv_event"), 5);
+}
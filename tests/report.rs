@@ -0,0 +1,76 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::{assert_success, fixture_rows, read_report, setup};
+use tempfile::TempDir;
+
+#[test]
+fn successful_run_reports_every_step() {
+    let (cluster, data) = setup();
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    assert_eq!(report["outcome"], "success");
+    assert!(report["error"].is_null());
+    assert!(report["finished_at"].is_string());
+
+    let steps = report["steps"].as_array().unwrap();
+    let kinds: Vec<&str> = steps.iter().map(|step| step["step"].as_str().unwrap()).collect();
+    assert_eq!(kinds[..2], ["lock", "begin"]);
+    let commit = kinds.iter().position(|&kind| kind == "commit").unwrap();
+    assert_eq!(kinds[commit + 1..], ["analyze"; 5]);
+    assert_eq!(kinds.iter().filter(|&&kind| kind == "drop").count(), 24);
+    assert_eq!(kinds.iter().filter(|&&kind| kind == "create").count(), 5);
+    assert_eq!(kinds.iter().filter(|&&kind| kind == "index").count(), 14);
+    assert!(steps.iter().all(|step| step["outcome"] == "success"));
+    assert!(steps.iter().all(|step| step["duration_seconds"].as_f64().unwrap() >= 0.0));
+
+    let populate = steps
+        .iter()
+        .find(|step| step["step"] == "populate" && step["target"] == "MV_EVENT")
+        .unwrap();
+    assert_eq!(
+        populate["rows"].as_i64().unwrap(),
+        fixture_rows(data.path(), "mv_event_export.csv")
+    );
+    let size = fs::metadata(data.path().join("mv_event_export.csv")).unwrap().len();
+    assert_eq!(populate["bytes"].as_u64().unwrap(), size);
+}
+
+#[test]
+fn failed_run_reports_failing_step() {
+    let (cluster, data) = setup();
+    fs::write(data.path().join("mv_efip_export.csv"), "PPI_EVENT_ID\nnot-a-number\n").unwrap();
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert!(!output.status.success());
+    assert_eq!(report["outcome"], "failure");
+    assert!(report["error"].as_str().unwrap().contains("bigint"));
+    let last = report["steps"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(last["step"], "populate");
+    assert_eq!(last["target"], "MV_EFIP");
+    assert_eq!(last["outcome"], "failure");
+}
+
+#[test]
+fn unreachable_database_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let report_path = dir.path().join("report.json");
+
+    // Nothing listens on port 1, so the connection is refused.
+    let output = Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+        .current_dir(dir.path())
+        .args(["--host", "127.0.0.1", "--port", "1", "--report"])
+        .arg(&report_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let report = read_report(&report_path);
+    assert_eq!(report["outcome"], "failure");
+    assert!(report["steps"].as_array().unwrap().is_empty());
+}
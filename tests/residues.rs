@@ -0,0 +1,106 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+
+use common::{append, assert_success, setup, stderr, step, variant};
+
+#[test]
+fn fixtures_match_their_sequences() {
+    let (cluster, data) = setup();
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+
+    let qa = step(&report, "qa", None);
+    assert_eq!(qa["rows"], 4);
+    assert_eq!(qa["rejected"], 0);
+    let sources: Vec<(&str, u64)> = report["residues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|source| (source["source_label"].as_str().unwrap(), source["matched"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(sources, [("HPRD", 1), ("PSP", 2), ("RLIMS-P", 1)]);
+}
+
+#[test]
+fn reports_residue_problems_per_source() {
+    let (cluster, data) = setup();
+    let event = "101,,P04637,uniprot,P04637,TP53,9606,Human,p-S15,";
+    let rows = vec![
+        // P04637 has S at 15.
+        variant(data.path(), "mv_event_export.csv", event, "105,,P04637,uniprot,P04637,TP53,9606,Human,p-T15,")
+            .replacen(",PSP,N,S,15,", ",PSP,N,T,15,", 1),
+        variant(data.path(), "mv_event_export.csv", event, "106,,P04637,uniprot,P04637,TP53,9606,Human,p-S5000,")
+            .replacen(",PSP,N,S,15,", ",PSP,N,S,5000,", 1),
+        variant(data.path(), "mv_event_export.csv", event, "107,,Q99999,uniprot,Q99999,XYZ,9606,Human,p-S15,")
+            .replacen(",PSP,N,S,15,", ",HPRD,N,S,15,", 1),
+    ];
+    append(data.path(), "mv_event_export.csv", &rows);
+
+    let (output, report) = cluster.import_reported(data.path(), &[]);
+    assert_success(&output);
+    let log = stderr(&output);
+    for expected in [
+        "PSP: 4 residues checked, 2 matched, 1 mismatched, 1 out of range, 0 without a sequence",
+        "HPRD: 2 residues checked, 1 matched, 0 mismatched, 0 out of range, 1 without a sequence",
+        "IPTM_EVENT_ID 105: T15 of P04637 is S in the sequence",
+        "IPTM_EVENT_ID 106: S5000 is outside of P04637, which has",
+        "IPTM_EVENT_ID 107: Q99999 has no sequence",
+    ]
+    .iter()
+    {
+        assert!(log.contains(expected), "{} not in {}", expected, log);
+    }
+
+    let qa = step(&report, "qa", None);
+    assert_eq!(qa["rows"], 7);
+    assert_eq!(qa["rejected"], 3);
+    let psp = report["residues"].as_array().unwrap().iter().find(|source| source["source_label"] == "PSP").unwrap();
+    assert_eq!(psp["mismatched"], 1);
+    assert_eq!(psp["out_of_range"], 1);
+}
+
+#[test]
+fn positions_beyond_integer_range_are_out_of_range() {
+    let (cluster, data) = setup();
+    let row = variant(data.path(), "mv_event_export.csv", "101,", "105,").replacen(",PSP,N,S,15,", ",PSP,N,S,3000000000,", 1);
+    append(data.path(), "mv_event_export.csv", &[row]);
+
+    let (output, report) = cluster.import_reported(data.path(), &["--site-windows"]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(log.contains("IPTM_EVENT_ID 105: S3000000000 is outside of P04637, which has"), "{}", log);
+
+    let psp = report["residues"].as_array().unwrap().iter().find(|source| source["source_label"] == "PSP").unwrap();
+    assert_eq!(psp["out_of_range"], 1);
+    let window = step(&report, "window", None);
+    assert_eq!(window["rows"], 4);
+}
+
+#[test]
+fn events_without_a_numeric_position_are_not_checked() {
+    let (cluster, data) = setup();
+    let rows = vec![
+        variant(data.path(), "mv_event_export.csv", "101,", "105,").replacen(",PSP,N,S,15,", ",PSP,N,S,,", 1),
+        variant(data.path(), "mv_event_export.csv", "101,", "106,").replacen(",PSP,N,S,15,", ",PSP,N,S,S15,", 1),
+    ];
+    append(data.path(), "mv_event_export.csv", &rows);
+
+    let output = cluster.import(data.path());
+    assert!(!output.status.success(), "a non-numeric POSITION must fail the import without --tolerant");
+
+    let (output, report) = cluster.import_reported(data.path(), &["--tolerant"]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(!log.contains("IPTM_EVENT_ID 105"), "{}", log);
+    let rejects = fs::read_to_string(data.path().join("mv_event.rejects.csv")).unwrap();
+    assert!(rejects.contains("POSITION is not a valid BIGINT: \"\"S15\"\""), "{}", rejects);
+
+    // The event without a position is loaded but left out of the check.
+    assert_eq!(step(&report, "populate", Some("MV_EVENT"))["rows"], 5);
+    let qa = step(&report, "qa", None);
+    assert_eq!(qa["rows"], 4);
+    assert_eq!(qa["rejected"], 0);
+}
@@ -0,0 +1,73 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use postgres::Connection;
+
+use common::{assert_success, setup};
+
+fn search(conn: &Connection, query: &str) -> Vec<String> {
+    conn.query("SELECT iptm_entry_code FROM search_entries($1)", &[&query])
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect()
+}
+
+#[test]
+fn builds_search_column_indexes_and_function() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import_with(data.path(), &["--search"]));
+
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query(
+            "SELECT indexname FROM pg_indexes WHERE tablename = 'mv_entry' AND indexdef LIKE '%USING gin%' ORDER BY 1",
+            &[],
+        )
+        .unwrap();
+    let indexes: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(
+        indexes,
+        [
+            "entry_gene_name_trgm_idx",
+            "entry_gene_synonyms_trgm_idx",
+            "entry_protein_name_trgm_idx",
+            "entry_protein_synonyms_trgm_idx",
+            "entry_search_idx",
+        ]
+    );
+
+    // Whole words, a synonym, a misspelling and a substring.
+    assert_eq!(search(&conn, "tumor antigen")[0], "P04637");
+    assert_eq!(search(&conn, "PKB")[0], "P31749");
+    assert_eq!(search(&conn, "AKT")[0], "P31749");
+    assert_eq!(search(&conn, "Phosphoprotien p53")[0], "P04637");
+    assert!(search(&conn, "no such protein at all").is_empty());
+}
+
+#[test]
+fn search_step_is_optional_and_repeatable() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+    let conn = cluster.connect("iptmnet");
+    let rows = conn
+        .query(
+            "SELECT count(*) FROM information_schema.columns WHERE table_name = 'mv_entry' AND column_name = 'search'",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+
+    assert_success(&cluster.import_with(data.path(), &["--search"]));
+    assert_success(&cluster.import_with(data.path(), &["--search"]));
+    assert_eq!(search(&conn, "TP53")[0], "P04637");
+
+    // A run without --search drops the function with the column it reads.
+    assert_success(&cluster.import(data.path()));
+    let rows = conn
+        .query("SELECT count(*) FROM pg_proc WHERE proname = 'search_entries'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+}
@@ -0,0 +1,83 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use postgres::Connection;
+
+use common::{append, assert_success, count, setup, stderr, variant};
+
+fn sites(conn: &Connection, condition: &str) -> Vec<(String, Option<i64>, String, String, i64, String)> {
+    conn.query(
+        &format!(
+            "SELECT OWNER_TABLE || '.' || OWNER_COLUMN, OWNER_ID, PROTEIN, RESIDUE, POSITION, PTM_TYPE FROM SITE \
+             WHERE {} ORDER BY 1, 2, 5",
+            condition
+        ),
+        &[],
+    )
+    .unwrap()
+    .iter()
+    .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4), row.get(5)))
+    .collect()
+}
+
+#[test]
+fn parses_site_lists_into_typed_rows() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import_with(data.path(), &["--sites"]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        sites(&conn, "PROTEIN = 'P04637'"),
+        [
+            ("MV_ENTRY.SITES".to_string(), Some(1), "P04637".to_string(), "S".to_string(), 15, "p".to_string()),
+            ("MV_ENTRY.SITES".to_string(), Some(1), "P04637".to_string(), "S".to_string(), 20, "p".to_string()),
+            ("MV_ENTRY.SITES".to_string(), Some(1), "P04637".to_string(), "K".to_string(), 120, "ac".to_string()),
+            ("MV_EVENT.SUB_SITES".to_string(), Some(101), "P04637".to_string(), "S".to_string(), 15, "p".to_string()),
+            ("MV_EVENT.SUB_SITES".to_string(), Some(102), "P04637".to_string(), "K".to_string(), 120, "ac".to_string()),
+            ("MV_PROTEO.SITES".to_string(), None, "P04637".to_string(), "S".to_string(), 15, "p".to_string()),
+        ]
+    );
+    assert_eq!(
+        sites(&conn, "PTM_TYPE = 'p' AND POSITION BETWEEN 300 AND 400 AND PROTEIN = 'P31749' AND OWNER_TABLE = 'MV_ENTRY'"),
+        [("MV_ENTRY.SITES".to_string(), Some(2), "P31749".to_string(), "T".to_string(), 308, "p".to_string())]
+    );
+    let rows = conn
+        .query("SELECT count(*) FROM pg_indexes WHERE tablename = 'site'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 3);
+}
+
+#[test]
+fn reports_unparseable_sites() {
+    let (cluster, data) = setup();
+    let row = variant(data.path(), "mv_event_export.csv", "101,,P04637,uniprot,P04637,TP53,9606,Human,p-S15,", "105,,P04637,uniprot,P04637,TP53,9606,Human,\"p-s46; S15, T18-p p-Y\",");
+    append(data.path(), "mv_event_export.csv", &[row]);
+
+    let output = cluster.import_with(data.path(), &["--sites"]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(
+        log.contains("MV_EVENT.SUB_SITES: 3 unparseable sites left out, e.g. \"S15\" (IPTM_EVENT_ID 105), \"T18-p\" (IPTM_EVENT_ID 105), \"p-Y\" (IPTM_EVENT_ID 105)"),
+        "{}",
+        log
+    );
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(
+        sites(&conn, "OWNER_ID = 105"),
+        [("MV_EVENT.SUB_SITES".to_string(), Some(105), "P04637".to_string(), "S".to_string(), 46, "p".to_string())]
+    );
+}
+
+#[test]
+fn site_table_is_only_built_when_asked_for() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import_with(data.path(), &["--sites"]));
+    let conn = cluster.connect("iptmnet");
+    assert!(count(&conn, "SITE") > 0);
+
+    assert_success(&cluster.import(data.path()));
+    let rows = conn.query("SELECT to_regclass('site') IS NULL", &[]).unwrap();
+    assert!(rows.get(0).get::<_, bool>(0));
+}
@@ -0,0 +1,108 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use postgres::Connection;
+
+use common::{assert_success, count, fixture_dir, setup, stderr, TestCluster};
+
+/// Imports once, then opens a transaction that keeps reading MV_ENTRY, the
+/// way a slow API query would.
+fn reader(cluster: &TestCluster, data: &Path) -> Connection {
+    assert_success(&cluster.import(data));
+    let reader = cluster.connect("iptmnet");
+    reader.execute("SET application_name = 'iptmnet_api'", &[]).unwrap();
+    reader.batch_execute("BEGIN; SELECT count(*) FROM mv_entry;").unwrap();
+    reader
+}
+
+#[test]
+fn gives_up_and_reports_blocking_sessions() {
+    let (cluster, data) = setup();
+    let _reader = reader(&cluster, data.path());
+
+    let started = Instant::now();
+    let (output, report) = cluster.import_reported(
+        data.path(),
+        &["--lock-timeout", "200ms", "--ddl-retries", "2", "--ddl-backoff", "100ms"],
+    );
+    assert!(!output.status.success());
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let log = stderr(&output);
+    assert!(log.contains("canceling statement due to lock timeout"), "{}", log);
+    assert!(log.contains("DROPPING uniprot_id_idx: lock not granted within the lock timeout, retrying in 100ms (1 of 2)"), "{}", log);
+    assert!(log.contains("retrying in 200ms (2 of 2)"), "{}", log);
+    assert!(log.contains("blocked by pid "), "{}", log);
+    assert!(log.contains("iptmnet_api) in a transaction since 20"), "{}", log);
+    assert!(log.contains("idle in transaction: \"BEGIN; SELECT count(*) FROM mv_entry;\""), "{}", log);
+
+    assert_eq!(report["error_class"], "lock");
+    // The earlier drops were rolled back with the transaction.
+    assert_eq!(count(&cluster.connect("iptmnet"), "mv_event"), 4);
+}
+
+#[test]
+fn retries_until_the_lock_is_released() {
+    let (cluster, data) = setup();
+    let reader = reader(&cluster, data.path());
+    let release = thread::spawn(move || {
+        thread::sleep(Duration::from_secs(1));
+        reader.batch_execute("COMMIT;").unwrap();
+    });
+
+    let output = cluster.import_with(
+        data.path(),
+        &["--lock-timeout", "200ms", "--ddl-retries", "10", "--ddl-backoff", "200ms"],
+    );
+    release.join().unwrap();
+    assert_success(&output);
+    assert!(stderr(&output).contains("retrying in 200ms (1 of 10)"));
+}
+
+#[test]
+fn terminates_blocking_sessions() {
+    let (cluster, data) = setup();
+    let reader = reader(&cluster, data.path());
+
+    let output = cluster.import_with(
+        data.path(),
+        &["--lock-timeout", "200ms", "--ddl-backoff", "100ms", "--blockers", "terminate"],
+    );
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(log.contains("Terminated 1 sessions blocking uniprot_id_idx"), "{}", log);
+    assert!(reader.query("SELECT 1", &[]).is_err());
+}
+
+#[test]
+fn statement_timeout_cancels_a_waiting_statement() {
+    let (cluster, data) = setup();
+    let _reader = reader(&cluster, data.path());
+
+    let (output, report) =
+        cluster.import_reported(data.path(), &["--lock-timeout", "0", "--statement-timeout", "300ms"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("canceling statement due to statement timeout"));
+    assert_eq!(report["error_class"], "timeout");
+}
+
+#[test]
+fn rejects_malformed_durations() {
+    let data = fixture_dir();
+    for value in ["10 hours", "18446744073709551615min"].iter() {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_iptmnet_data_importer"))
+            .current_dir(data.path())
+            .args(["--lock-timeout", value])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let log = stderr(&output);
+        assert!(log.contains("LOCK_TIMEOUT must be a duration such as 500ms, 10s or 2min"), "{}", log);
+    }
+}
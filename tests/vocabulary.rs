@@ -0,0 +1,109 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use postgres::Connection;
+
+use common::{append, assert_success, count, setup, stderr, variant};
+
+fn write_config(dir: &Path, content: &str) -> String {
+    let config = dir.join("importer.toml");
+    fs::write(&config, content).unwrap();
+    config.to_str().unwrap().to_string()
+}
+
+/// Whether the foreign key exists and was validated.
+fn foreign_key(conn: &Connection, name: &str) -> Option<bool> {
+    let rows = conn
+        .query("SELECT convalidated FROM pg_constraint WHERE contype = 'f' AND conname = $1", &[&name])
+        .unwrap();
+    rows.iter().next().map(|row| row.get(0))
+}
+
+#[test]
+fn fails_on_unknown_values_by_default() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import(data.path()));
+    let row = variant(data.path(), "mv_event_export.csv", "101,", "105,").replacen("Phosphorylation", "phosphorylation", 1);
+    append(data.path(), "mv_event_export.csv", &[row]);
+    let config = write_config(
+        data.path(),
+        "[vocabularies.EVENT_NAME]\ncolumns = [\"MV_EVENT.EVENT_NAME\", \"MV_EFIP.PTM_EVENT_NAME\"]\nvalues = [\"Phosphorylation\", \"Acetylation\"]\n",
+    );
+
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert!(!output.status.success(), "import with an unknown event name succeeded");
+    let log = stderr(&output);
+    assert!(
+        log.contains("MV_EVENT.EVENT_NAME: 1 rows with values not in EVENT_NAME, e.g. \"phosphorylation\" (1 rows, listed as \"Phosphorylation\")"),
+        "{}",
+        log
+    );
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "mv_event"), 4);
+}
+
+#[test]
+fn warns_or_adds_and_ties_columns_to_the_lookups() {
+    let (cluster, data) = setup();
+    let config = write_config(
+        data.path(),
+        "[vocabularies.EVENT_NAME]\ncolumns = [\"MV_EVENT.EVENT_NAME\"]\nvalues = [\"Phosphorylation\"]\non_unknown = \"auto-add\"\nforeign_keys = true\n\n\
+         [vocabularies.IMPACT]\ncolumns = [\"MV_EFIP.IMPACT\"]\nvalues = [\"increased\"]\non_unknown = \"warn\"\nforeign_keys = true\n",
+    );
+
+    let output = cluster.import_with(data.path(), &["--config", &config]);
+    assert_success(&output);
+    let log = stderr(&output);
+    assert!(log.contains("MV_EVENT.EVENT_NAME: added 1 values to EVENT_NAME, e.g. \"Acetylation\" (1 rows)"), "{}", log);
+    assert!(log.contains("MV_EFIP.IMPACT: 1 rows with values not in IMPACT, e.g. \"decreased\" (1 rows)"), "{}", log);
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "event_name"), 2);
+    assert_eq!(count(&conn, "impact"), 1);
+    assert_eq!(foreign_key(&conn, "mv_event_event_name_fkey"), Some(true));
+    // The unknown impacts are still there, so the foreign key is NOT VALID.
+    assert_eq!(foreign_key(&conn, "mv_efip_impact_fkey"), Some(false));
+    let error = conn
+        .execute("UPDATE MV_EVENT SET EVENT_NAME = 'Glycosylation' WHERE IPTM_EVENT_ID = 101", &[])
+        .unwrap_err();
+    assert!(error.to_string().contains("mv_event_event_name_fkey"), "{}", error);
+
+    // Without the vocabularies the next import drops the foreign keys with
+    // the tables, and the lookup tables that are no longer configured.
+    let config = write_config(
+        data.path(),
+        "[vocabularies.IMPACT]\ncolumns = [\"MV_EFIP.IMPACT\"]\nvalues = [\"increased\"]\non_unknown = \"warn\"\n",
+    );
+    assert_success(&cluster.import_with(data.path(), &["--config", &config]));
+    assert_eq!(foreign_key(&conn, "mv_event_event_name_fkey"), None);
+    let rows = conn.query("SELECT to_regclass('event_name') IS NULL, to_regclass('impact') IS NULL", &[]).unwrap();
+    assert_eq!((rows.get(0).get::<_, bool>(0), rows.get(0).get::<_, bool>(1)), (true, false));
+    assert_success(&cluster.import(data.path()));
+    let rows = conn.query("SELECT to_regclass('impact') IS NULL", &[]).unwrap();
+    assert!(rows.get(0).get::<_, bool>(0), "IMPACT left behind");
+}
+
+#[test]
+fn rejects_invalid_vocabularies() {
+    let (cluster, data) = setup();
+    for (content, error) in [
+        ("[vocabularies.EVENT_NAME]\ncolumns = [\"MV_EVENT.NO_SUCH\"]\nvalues = [\"x\"]\n", "unknown column MV_EVENT.NO_SUCH"),
+        ("[vocabularies.POSITIONS]\ncolumns = [\"MV_EVENT.POSITION\"]\nvalues = [\"1\"]\n", "not a text column"),
+        ("[vocabularies.MV_EVENT]\ncolumns = [\"MV_EVENT.EVENT_NAME\"]\nvalues = [\"x\"]\n", "already taken"),
+        ("[vocabularies.IMPACT]\ncolumns = [\"MV_EFIP.IMPACT\"]\nvalues = [\"x\", \"x\"]\n", "listed twice"),
+        ("[vocabularies.IMPACT]\ncolumns = [\"MV_EFIP.IMPACT\"]\nvalues = [\"x\"]\non_unknown = \"ignore\"\n", "unknown variant"),
+    ]
+    .iter()
+    {
+        let config = write_config(data.path(), content);
+        let output = cluster.import_with(data.path(), &["--config", &config]);
+        assert!(!output.status.success());
+        let log = stderr(&output);
+        assert!(log.contains(error), "{}", log);
+    }
+}
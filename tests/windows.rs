@@ -0,0 +1,65 @@
+extern crate postgres;
+extern crate tempfile;
+
+mod common;
+
+use postgres::Connection;
+
+use common::{append, assert_success, count, setup, variant};
+
+fn windows(conn: &Connection) -> Vec<(i64, String, i64, String, String)> {
+    conn.query(
+        "SELECT IPTM_EVENT_ID, UNIPROT_ID, POSITION, RESIDUE, PEPTIDE FROM SITE_WINDOW ORDER BY 1",
+        &[],
+    )
+    .unwrap()
+    .iter()
+    .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4)))
+    .collect()
+}
+
+#[test]
+fn builds_seven_residue_flanks_indexed_by_peptide() {
+    let (cluster, data) = setup();
+    assert_success(&cluster.import_with(data.path(), &["--site-windows"]));
+
+    let conn = cluster.connect("iptmnet");
+    assert_eq!(count(&conn, "site_window"), 4);
+    let rows = conn
+        .query("SELECT IPTM_EVENT_ID, RESIDUE FROM SITE_WINDOW WHERE PEPTIDE = 'PYHQAIRSKSDLSEM'", &[])
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows.get(0).get::<_, i64>(0), 101);
+    assert_eq!(rows.get(0).get::<_, String>(1), "S");
+    let rows = conn
+        .query("SELECT indexname FROM pg_indexes WHERE tablename = 'site_window' ORDER BY 1", &[])
+        .unwrap();
+    let indexes: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    assert_eq!(indexes, ["site_window_peptide_idx", "site_window_protein_position_idx"]);
+
+    // Without --site-windows the table from the earlier run goes away.
+    assert_success(&cluster.import(data.path()));
+    let rows = conn
+        .query("SELECT count(*) FROM information_schema.tables WHERE table_name = 'site_window'", &[])
+        .unwrap();
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+}
+
+#[test]
+fn pads_wide_windows_and_skips_mismatched_residues() {
+    let (cluster, data) = setup();
+    // P04637 has S at 15, not T.
+    let row = variant(data.path(), "mv_event_export.csv", "101,", "105,").replacen(",PSP,N,S,15,", ",PSP,N,T,15,", 1);
+    append(data.path(), "mv_event_export.csv", &[row]);
+    assert_success(&cluster.import_with(data.path(), &["--site-windows", "--window-flank", "20"]));
+
+    let conn = cluster.connect("iptmnet");
+    let windows = windows(&conn);
+    assert_eq!(windows.len(), 4);
+    assert_eq!(
+        windows[0],
+        (101, "P04637".to_string(), 15, "S".to_string(), "______MMVFNWGPYHQAIRSKSDLSEMVFNWGPYHQAIRC".to_string())
+    );
+    assert_eq!(windows[3].4, "______MWGPYHQAIRCKSDYTEMVFNWGPYHQAIRCKSDL");
+    assert!(windows.iter().all(|window| window.0 != 105));
+}